@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use thrift_parser::codegen::rust::generate_workspace;
+use thrift_parser::workspace::Workspace;
+
+// Include the Rust code of a Thrift file and the files it includes, as `build::Config` generates
+// it, e.g. `include_thrift!("idl/user.thrift")`. The path is relative to the directory of the
+// crate's Cargo.toml, included files are looked up relative to the files including them.
+// Errors in the files become `compile_error!`s giving the file, line and column.
+#[proc_macro]
+pub fn include_thrift(input: TokenStream) -> TokenStream {
+    let (path, span) = match path_of(input) {
+        Ok(found) => found,
+        Err((message, span)) => return compile_error(message, span),
+    };
+    let mut root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    root.push(path);
+
+    let workspace = match Workspace::load(&root, &[]) {
+        Ok(workspace) => workspace,
+        Err(e) => return compile_error(&e.to_string(), span),
+    };
+    let code = match generate_workspace(&workspace) {
+        Ok(code) => code,
+        Err(diagnostics) => {
+            let diagnostics: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+            return compile_error(&diagnostics.join("\n"), span);
+        }
+    };
+    // Build again when a file changes.
+    let mut tracked = String::new();
+    for program in workspace.programs() {
+        let path = program.path.to_string_lossy();
+        tracked += &format!("const _: &[u8] = include_bytes!({:?});\n", path);
+    }
+    match (tracked + &code).parse() {
+        Ok(tokens) => tokens,
+        Err(e) => compile_error(&format!("cannot parse the generated code: {}", e), span),
+    }
+}
+
+// The path in the string literal the macro is called with.
+fn path_of(input: TokenStream) -> Result<(String, Span), (&'static str, Span)> {
+    let mut tokens = input.into_iter();
+    let (literal, span) = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => (literal.to_string(), literal.span()),
+        (Some(token), _) => return Err(("expected a path", token.span())),
+        (None, _) => return Err(("expected a path", Span::call_site())),
+    };
+    let raw = literal.trim_start_matches('r').trim_matches('#');
+    match raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+        true if raw.len() == literal.len() => unescape(&raw[1..raw.len() - 1])
+            .map(|path| (path, span))
+            .ok_or(("expected a path", span)),
+        true => Ok((raw[1..raw.len() - 1].into(), span)),
+        false => Err(("expected a path", span)),
+    }
+}
+
+// The value of the body of a string literal, which the compiler has checked already.
+fn unescape(body: &str) -> Option<String> {
+    let mut result = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            't' => result.push('\t'),
+            '0' => result.push('\0'),
+            c @ ('\\' | '\'' | '"') => result.push(c),
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                let code = u8::from_str_radix(&digits, 16).ok()?;
+                result.push(char::from(code));
+            }
+            'u' => {
+                let digits: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                let code = u32::from_str_radix(&digits.replace('_', ""), 16).ok()?;
+                result.push(char::from_u32(code)?);
+            }
+            // A line continuation skips the line break and the whitespace after it.
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    // The compiler prefixes the message already.
+    let message = message.strip_prefix("error: ").unwrap_or(message);
+    let mut message = Literal::string(message);
+    message.set_span(span);
+    let tokens: Vec<TokenTree> = vec![
+        Ident::new("compile_error", span).into(),
+        Punct::new('!', Spacing::Alone).into(),
+        Group::new(Delimiter::Parenthesis, TokenTree::from(message).into()).into(),
+        Punct::new(';', Spacing::Alone).into(),
+    ];
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"idl\\a.thrift").unwrap(), r"idl\a.thrift");
+        assert_eq!(unescape(r"idl/\u{61}.thrift").unwrap(), "idl/a.thrift");
+        assert_eq!(unescape(r#"a\"b\tc\x41"#).unwrap(), "a\"b\tcA");
+        assert_eq!(unescape("a\\\n    b").unwrap(), "ab");
+        assert_eq!(unescape(r"a\q"), None);
+    }
+}
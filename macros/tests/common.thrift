@@ -0,0 +1,3 @@
+namespace rs idl.common
+
+typedef i64 Id
@@ -0,0 +1,137 @@
+use std::convert::TryFrom;
+use std::future::{ready, Future};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use thrift_parser_macros::include_thrift;
+
+include_thrift!("tests/user.thrift");
+
+use _thrift_runtime::{Decode, DecodeError, Encode, Transport, Value};
+
+#[test]
+fn test_include_thrift() {
+    use idl::user::{Kind, User, UserServiceGetError};
+
+    let user = User::default();
+    assert_eq!(user.id, 0);
+    assert_eq!(user.name, None);
+    assert_eq!(user.kind, Kind::Person);
+    assert_eq!(Kind::try_from(2), Ok(Kind::Bot));
+
+    let error = UserServiceGetError::from(idl::user::NotFound { id: 1 });
+    assert_eq!(error.to_string(), "Missing(NotFound { id: 1 })");
+    let _: idl::common::Id = 1i64;
+}
+
+// Replies to every call with `reply`, after checking the encoded arguments are `args`.
+struct Replay {
+    args: Value,
+    reply: Value,
+}
+
+impl Transport for Replay {
+    type Error = DecodeError;
+
+    fn call<A, R>(
+        &self,
+        service: &'static str,
+        method: &'static str,
+        args: A,
+    ) -> impl Future<Output = Result<R, DecodeError>> + Send
+    where
+        A: Encode + Send + 'static,
+        R: Decode + Send + 'static,
+    {
+        assert_eq!((service, method), ("UserService", "get"));
+        assert_eq!(args.encode(), self.args);
+        ready(R::decode(self.reply.clone()))
+    }
+
+    fn send<A>(
+        &self,
+        _: &'static str,
+        _: &'static str,
+        _: A,
+    ) -> impl Future<Output = Result<(), DecodeError>> + Send
+    where
+        A: Encode + Send + 'static,
+    {
+        ready(Ok(()))
+    }
+}
+
+// A waker doing nothing, as the futures of `Replay` are ready at once.
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    // Safe because the vtable ignores the data pointer.
+    unsafe { Waker::from_raw(clone(std::ptr::null())) }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(&noop_waker()))
+    {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("the future is pending"),
+    }
+}
+
+#[test]
+fn test_transport() {
+    use idl::user::{Kind, NotFound, User, UserServiceClient, UserServiceGetError};
+
+    let args = Value::Struct(vec![(1, Value::I64(7))]);
+    let user = User {
+        id: 7,
+        name: Some("x".into()),
+        kind: Kind::Bot,
+    };
+    let reply = Value::Struct(vec![(0, user.encode())]);
+    assert_eq!(
+        reply,
+        Value::Struct(vec![(
+            0,
+            Value::Struct(vec![
+                (1, Value::I64(7)),
+                (2, Value::String("x".into())),
+                (3, Value::I32(2)),
+            ])
+        )])
+    );
+    let client = UserServiceClient::new(Replay {
+        args: args.clone(),
+        reply,
+    });
+    assert_eq!(block_on(client.get(7)), Ok(Ok(user)));
+
+    // Fields that are not set are decoded as their defaults, or fail if they are required.
+    let missing = NotFound { id: 3 }.encode();
+    let client = UserServiceClient::new(Replay {
+        args: args.clone(),
+        reply: Value::Struct(vec![(1, missing)]),
+    });
+    let error = UserServiceGetError::Missing(NotFound { id: 3 });
+    assert_eq!(block_on(client.get(7)), Ok(Err(error)));
+    assert_eq!(
+        User::decode(Value::Struct(vec![(1, Value::Binary(b"7".to_vec()))])),
+        Err(DecodeError::new("expected I64, found String"))
+    );
+    assert_eq!(
+        User::decode(Value::Struct(vec![(2, Value::String("x".into()))])),
+        Err(DecodeError::new("required field 1 of User is not set"))
+    );
+    let client = UserServiceClient::new(Replay {
+        args,
+        reply: Value::Struct(vec![]),
+    });
+    assert_eq!(
+        block_on(client.get(7)),
+        Err(DecodeError::new("no field of get_result is set"))
+    );
+}
@@ -0,0 +1,23 @@
+use thrift_parser_macros::include_thrift;
+
+include_thrift!("tests/unordered.thrift");
+
+use _thrift_runtime::{Decode, Encode, ListMap, ListSet};
+
+// Doubles and structs with doubles in them have no total order, so sets of them and maps keyed by
+// them are kept as lists.
+#[test]
+fn test_unordered() {
+    use idl::unordered::{origin, xs, Point, Shape};
+
+    assert_eq!(xs(), ListSet(vec![1.5, 2.0]));
+    assert_eq!(origin(), ListMap(vec![(Point { x: 0.0 }, 1)]));
+
+    let shape = Shape {
+        xs: ListSet(vec![0.5]),
+        counts: ListMap(vec![(0.5, 1)]),
+        points: ListSet(vec![Point { x: 1.0 }]),
+        names: vec![("a".to_string(), 0.5)].into_iter().collect(),
+    };
+    assert_eq!(Shape::decode(shape.encode()), Ok(shape));
+}
@@ -0,0 +1,15 @@
+namespace rs idl.unordered
+
+struct Point {
+    1: double x
+}
+
+struct Shape {
+    1: set<double> xs
+    2: map<double, i32> counts
+    3: set<Point> points
+    4: map<string, double> names
+}
+
+const set<double> XS = [1.5, 2]
+const map<Point, i32> ORIGIN = {{"x": 0}: 1}
@@ -0,0 +1,22 @@
+include "common.thrift"
+
+namespace rs idl.user
+
+enum Kind {
+    PERSON = 1
+    BOT = 2
+}
+
+struct User {
+    1: required common.Id id
+    2: optional string name
+    3: Kind kind = Kind.PERSON
+}
+
+exception NotFound {
+    1: common.Id id
+}
+
+service UserService {
+    User get(1: common.Id id) throws (1: NotFound missing)
+}
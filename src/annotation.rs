@@ -0,0 +1,154 @@
+use nom::character::complete::char as cchar;
+use nom::combinator::{map, opt};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+
+use crate::basic::{Identifier, IdentifierRef, Literal, LiteralRef, Separator};
+use crate::constant::parse_list_separator;
+use crate::{IResult, Parser};
+
+// TypeAnnotations ::=  '(' TypeAnnotation* ')'
+#[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone, Default)]
+pub struct AnnotationsRef<'a>(Vec<AnnotationRef<'a>>);
+
+impl<'a> AnnotationsRef<'a> {
+    // Returns the value of the last annotation with the given key.
+    // A key without value is reported as Some(None).
+    pub fn get(&self, key: &str) -> Option<Option<&LiteralRef<'a>>> {
+        self.0
+            .iter()
+            .rev()
+            .find(|a| *a.key == key)
+            .map(|a| a.value.as_ref())
+    }
+}
+
+impl<'a> Parser<'a> for AnnotationsRef<'a> {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        map(
+            delimited(
+                pair(cchar('('), opt(Separator::parse)),
+                many0(terminated(AnnotationRef::parse, opt(parse_list_separator))),
+                pair(opt(Separator::parse), cchar(')')),
+            ),
+            Self,
+        )(input)
+    }
+}
+
+#[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotations(Vec<Annotation>);
+
+impl Annotations {
+    // Returns the value of the last annotation with the given key.
+    // A key without value is reported as Some(None).
+    pub fn get(&self, key: &str) -> Option<Option<&Literal>> {
+        self.0
+            .iter()
+            .rev()
+            .find(|a| a.key.as_str() == key)
+            .map(|a| a.value.as_ref())
+    }
+}
+
+impl<'a> From<AnnotationsRef<'a>> for Annotations {
+    fn from(r: AnnotationsRef<'a>) -> Self {
+        Self(r.0.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'a> Parser<'a> for Annotations {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        AnnotationsRef::parse(input).map(|(remains, parsed)| (remains, parsed.into()))
+    }
+}
+
+// TypeAnnotation  ::=  Identifier ('=' Literal)? ListSeparator?
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct AnnotationRef<'a> {
+    pub key: IdentifierRef<'a>,
+    pub value: Option<LiteralRef<'a>>,
+}
+
+impl<'a> Parser<'a> for AnnotationRef<'a> {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        map(
+            pair(
+                IdentifierRef::parse,
+                opt(preceded(
+                    tuple((opt(Separator::parse), cchar('='), opt(Separator::parse))),
+                    LiteralRef::parse,
+                )),
+            ),
+            |(key, value)| Self { key, value },
+        )(input)
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    pub key: Identifier,
+    pub value: Option<Literal>,
+}
+
+impl<'a> From<AnnotationRef<'a>> for Annotation {
+    fn from(r: AnnotationRef<'a>) -> Self {
+        Self {
+            key: r.key.into(),
+            value: r.value.map(Into::into),
+        }
+    }
+}
+
+impl<'a> Parser<'a> for Annotation {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        AnnotationRef::parse(input).map(|(remains, parsed)| (remains, parsed.into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_annotations() {
+        let expected = AnnotationsRef::from(vec![
+            AnnotationRef {
+                key: IdentifierRef::from("go.tag"),
+                value: Some(LiteralRef::from(r#"json:\"id\""#)),
+            },
+            AnnotationRef {
+                key: IdentifierRef::from("cpp.ref"),
+                value: Some(LiteralRef::from("true")),
+            },
+            AnnotationRef {
+                key: IdentifierRef::from("deprecated"),
+                value: None,
+            },
+        ]);
+        assert_eq!(
+            AnnotationsRef::parse(r#"(go.tag = "json:\"id\"", cpp.ref = "true", deprecated)"#)
+                .unwrap()
+                .1,
+            expected
+        );
+        assert_eq!(
+            AnnotationsRef::parse(r#"( go.tag="json:\"id\""; cpp.ref='true' deprecated, )"#)
+                .unwrap()
+                .1,
+            expected
+        );
+        assert_eq!(
+            expected.get("cpp.ref"),
+            Some(Some(&LiteralRef::from("true")))
+        );
+        assert_eq!(expected.get("deprecated"), Some(None));
+        assert_eq!(expected.get("missing"), None);
+        assert_eq!(
+            AnnotationsRef::parse("()").unwrap().1,
+            AnnotationsRef::default()
+        );
+    }
+}
@@ -1,14 +1,17 @@
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_till, take_until, take_while};
-use nom::character::complete::{char as cchar, multispace1, one_of, satisfy};
-use nom::combinator::{map, opt, recognize};
-use nom::multi::many1;
-use nom::sequence::{delimited, preceded, tuple};
-use nom::IResult;
+use nom::bytes::complete::{is_not, tag, take_till, take_until, take_while};
+use nom::character::complete::{
+    anychar, char as cchar, line_ending, multispace1, one_of, satisfy, space0,
+};
+use nom::combinator::{map, not, opt, recognize};
+use nom::multi::{fold_many1, many0, many1, separated_list1};
+use nom::sequence::{delimited, pair, preceded, tuple};
 
-use crate::Parser;
+use crate::{IResult, Parser};
 
 // Literal         ::=  ('"' [^"]* '"') | ("'" [^']* "'")
+// Note: A backslash escapes the following char, so "json:\"id\"" is one literal.
+// The escape sequences are kept as they are in the source.
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
 pub struct LiteralRef<'a>(&'a str);
 
@@ -16,8 +19,22 @@ impl<'a> Parser<'a> for LiteralRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
         map(
             alt((
-                delimited(cchar('"'), take_until("\""), cchar('"')),
-                delimited(cchar('\''), take_until("'"), cchar('\'')),
+                delimited(
+                    cchar('"'),
+                    recognize(many0(alt((
+                        is_not("\\\""),
+                        recognize(pair(cchar('\\'), anychar)),
+                    )))),
+                    cchar('"'),
+                ),
+                delimited(
+                    cchar('\''),
+                    recognize(many0(alt((
+                        is_not("\\'"),
+                        recognize(pair(cchar('\\'), anychar)),
+                    )))),
+                    cchar('\''),
+                ),
             )),
             Self,
         )(input)
@@ -25,6 +42,7 @@ impl<'a> Parser<'a> for LiteralRef<'a> {
 }
 
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Literal(String);
 
 impl<'a> From<LiteralRef<'a>> for Literal {
@@ -57,6 +75,7 @@ impl<'a> Parser<'a> for IdentifierRef<'a> {
 }
 
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Identifier(String);
 
 impl<'a> From<IdentifierRef<'a>> for Identifier {
@@ -100,6 +119,7 @@ impl<'a> Parser<'a> for CommentRef<'a> {
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Comment(String);
 
 impl<'a> From<CommentRef<'a>> for Comment {
@@ -114,6 +134,122 @@ impl<'a> Parser<'a> for Comment {
     }
 }
 
+// A doc comment, either a /** ... */ block or consecutive lines starting with ///.
+// The comment is kept as it is in the source, use `text` to get the content.
+#[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+pub struct DocRef<'a>(&'a str);
+
+impl<'a> DocRef<'a> {
+    // The content of the comment without the comment markers.
+    pub fn text(&self) -> String {
+        doc_text(self.0)
+    }
+}
+
+impl<'a> Parser<'a> for DocRef<'a> {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        map(
+            alt((
+                recognize(delimited(tag("/**"), take_until("*/"), tag("*/"))),
+                recognize(separated_list1(
+                    tuple((space0, line_ending, space0)),
+                    tuple((tag("///"), not(cchar('/')), take_till(|c| c == '\n'))),
+                )),
+            )),
+            Self,
+        )(input)
+    }
+}
+
+#[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Doc(String);
+
+impl Doc {
+    // The content of the comment without the comment markers.
+    pub fn text(&self) -> String {
+        doc_text(&self.0)
+    }
+}
+
+impl<'a> From<DocRef<'a>> for Doc {
+    fn from(r: DocRef<'a>) -> Self {
+        Self(r.0.into())
+    }
+}
+
+impl<'a> Parser<'a> for Doc {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        DocRef::parse(input).map(|(remains, parsed)| (remains, parsed.into()))
+    }
+}
+
+// Strip the comment markers and the leading `*` of each line in a block.
+fn doc_text(raw: &str) -> String {
+    let lines: Vec<&str> = match raw.strip_prefix("/**") {
+        Some(block) => block
+            .trim_end_matches("*/")
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                let line = line.strip_prefix('*').unwrap_or(line);
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect(),
+        None => raw
+            .lines()
+            .map(|line| {
+                let line = line.trim().trim_start_matches('/');
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect(),
+    };
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
+
+// Nodes that may have a doc comment in front of them.
+pub(crate) trait Documented<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>>;
+}
+
+// Skip the spaces and comments in front of what `parser` parses, and attach the last doc comment
+// among them to the parsed node. A doc comment followed by a normal comment is not attached.
+pub(crate) fn documented<'a, O, F>(parser: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+where
+    O: Documented<'a>,
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    map(
+        pair(
+            map(
+                opt(fold_many1(
+                    alt((
+                        map(DocRef::parse, |doc| Some(Some(doc))),
+                        map(CommentRef::parse, |_| Some(None)),
+                        map(multispace1, |_| None),
+                    )),
+                    None,
+                    |doc, item| item.unwrap_or(doc),
+                )),
+                Option::flatten,
+            ),
+            parser,
+        ),
+        |(doc, mut node)| {
+            *node.doc_mut() = doc;
+            node
+        },
+    )
+}
+
 // 1. Comment
 // 2. Space
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -145,8 +281,9 @@ mod test {
                 "'ihcia\"h'''''",
                 "\"ihciah\"balabala",
                 "\"ihcia'h\"''''",
+                r#""json:\"id\"""#,
             ],
-            vec!["ihciah", "ihcia\"h", "ihciah", "ihcia'h"],
+            vec!["ihciah", "ihcia\"h", "ihciah", "ihcia'h", r#"json:\"id\""#],
             LiteralRef::parse,
             LiteralRef,
         );
@@ -189,4 +326,22 @@ mod test {
             CommentRef,
         );
     }
+
+    #[test]
+    fn test_doc() {
+        let (remains, doc) =
+            DocRef::parse("/**\n * The user.\n *\n * Keep it safe.\n */\nstruct").unwrap();
+        assert_eq!(remains, "\nstruct");
+        assert_eq!(doc.text(), "The user.\n\nKeep it safe.");
+        assert_eq!(
+            DocRef::parse("/** one line */").unwrap().1.text(),
+            "one line"
+        );
+
+        let (remains, doc) = DocRef::parse("/// first\n  /// second\n\n/// third").unwrap();
+        assert_eq!(remains, "\n\n/// third");
+        assert_eq!(Doc::from(doc).text(), "first\nsecond");
+        assert!(DocRef::parse("/**/").is_err());
+        assert!(DocRef::parse("//// banner").is_err());
+    }
 }
@@ -0,0 +1,125 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process;
+
+use thrift_parser::formatter::format;
+use thrift_parser::printer::{Printer, QuoteStyle, SeparatorStyle};
+
+const USAGE: &str = "\
+usage: thrift-fmt [options] [FILE]...
+
+Format Thrift files in place. Without files, format the standard input to the standard output.
+
+options:
+    --check          do not write anything, exit with 1 if some file is not formatted
+    --indent N       indent with N spaces, 4 by default
+    --semicolon      end fields, enum values and functions with `;` instead of `,`
+    --single-quote   quote literals with `'` instead of `\"`
+    -h, --help       print this message";
+
+struct Options {
+    check: bool,
+    printer: Printer,
+    files: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        check: false,
+        printer: Printer::default(),
+        files: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--indent" => {
+                let value = args.next().ok_or("--indent needs a value")?;
+                options.printer.indent = value
+                    .parse()
+                    .map_err(|_| format!("invalid indent `{}`", value))?;
+            }
+            "--semicolon" => options.printer.separator = SeparatorStyle::Semicolon,
+            "--single-quote" => options.printer.quote = QuoteStyle::Single,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => options.files.push(arg.into()),
+        }
+    }
+    Ok(options)
+}
+
+// Exit with 0 when everything is formatted, 1 when `--check` finds unformatted input and 2 on
+// errors.
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    process::exit(if options.files.is_empty() {
+        run_stdin(&options)
+    } else {
+        run_files(&options)
+    })
+}
+
+fn run_stdin(options: &Options) -> i32 {
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+        eprintln!("error: cannot read the standard input: {}", e);
+        return 2;
+    }
+    match format(&source, &options.printer) {
+        Ok(formatted) if options.check => (formatted != source) as i32,
+        Ok(formatted) => match io::stdout().write_all(formatted.as_bytes()) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("error: cannot write the standard output: {}", e);
+                2
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+fn run_files(options: &Options) -> i32 {
+    let mut code = 0;
+    for path in &options.files {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: cannot read {}: {}", path.display(), e);
+                code = 2;
+                continue;
+            }
+        };
+        let formatted = match format(&source, &options.printer) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}", e.with_path(path));
+                code = 2;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if options.check {
+            println!("{} is not formatted", path.display());
+            code = code.max(1);
+        } else if let Err(e) = std::fs::write(path, formatted) {
+            eprintln!("error: cannot write {}: {}", path.display(), e);
+            code = 2;
+        }
+    }
+    code
+}
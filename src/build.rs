@@ -0,0 +1,218 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::codegen::rust::{generate, module_path, Modules, RUNTIME, RUNTIME_MODULE};
+use crate::resolve::Resolver;
+use crate::validate::validate;
+use crate::workspace::{LoadError, Workspace};
+use crate::Diagnostic;
+
+// The file declaring the modules of all the generated files.
+pub const INCLUDE_FILE: &str = "_thrift.rs";
+
+// Compile Thrift files to Rust in a build script:
+//
+// thrift_parser::build::Config::new()
+//     .include_dir("idl")
+//     .compile(&["idl/api.thrift"])?;
+//
+// The code of every file and of the files it includes, see `codegen::rust`, is written to
+// `OUT_DIR` as `a.b.rs` for the module `a::b`, the code they share as `_thrift_runtime.rs`, and
+// `INCLUDE_FILE` declares all these modules.
+// The crate then includes them with
+//
+// include!(concat!(env!("OUT_DIR"), "/_thrift.rs"));
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    include_dirs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Also look included files up in `dir`, see `Workspace::load`.
+    pub fn include_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    // Write to `dir` instead of `OUT_DIR`.
+    pub fn out_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.out_dir = Some(dir.into());
+        self
+    }
+
+    // Parse and check `files` with everything they include, and generate their code. Cargo is
+    // told to run the build script again when any of the parsed files changes, and warnings are
+    // passed on to it.
+    pub fn compile(&self, files: &[impl AsRef<Path>]) -> Result<(), BuildError> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(BuildError::OutDir)?,
+        };
+
+        let mut modules = Modules::default();
+        let file = format!("{}.rs", RUNTIME_MODULE);
+        write(&out_dir.join(&file), RUNTIME)?;
+        let include = format!("include!({:?});\n", file);
+        modules.insert(vec![RUNTIME_MODULE.into()], PathBuf::new(), include);
+        for file in files {
+            let workspace = Workspace::load(file, &self.include_dirs).map_err(BuildError::Load)?;
+            for program in workspace.programs() {
+                println!("cargo:rerun-if-changed={}", program.path.display());
+            }
+            Resolver::new(&workspace)
+                .check(&workspace)
+                .map_err(BuildError::Diagnostics)?;
+
+            for program in workspace.programs() {
+                let module = module_path(program);
+                match modules.source(&module) {
+                    Some(path) if *path == program.path => continue,
+                    Some(path) => {
+                        return Err(BuildError::Module {
+                            module: module.join("::"),
+                            paths: [path.to_path_buf(), program.path.clone()],
+                        })
+                    }
+                    None => {}
+                }
+                for warning in validate(&program.document) {
+                    if !warning.is_error() {
+                        println!(
+                            "cargo:warning={} --> {}:{}:{}",
+                            warning.message,
+                            program.path.display(),
+                            warning.span.line,
+                            warning.span.column
+                        );
+                    }
+                }
+                let code = generate(&workspace, program).map_err(BuildError::Diagnostics)?;
+                let file = format!("{}.rs", module.join("."));
+                write(&out_dir.join(&file), &code)?;
+                // The path given to `include!` is relative to the file the macro is in, which
+                // is the include file in the same directory.
+                let include = format!("include!({:?});\n", file);
+                modules.insert(module, program.path.clone(), include);
+            }
+        }
+
+        let mut index = String::new();
+        modules.write(0, &mut index);
+        write(&out_dir.join(INCLUDE_FILE), &index)
+    }
+}
+
+fn write(path: &Path, content: &str) -> Result<(), BuildError> {
+    std::fs::write(path, content).map_err(|error| BuildError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    // `OUT_DIR` is not set because the build is not run by cargo, and no output dir is given.
+    OutDir,
+    Load(LoadError),
+    Diagnostics(Vec<Diagnostic>),
+    // Two files would be generated into the same module.
+    Module { module: String, paths: [PathBuf; 2] },
+    Io { path: PathBuf, error: io::Error },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::OutDir => {
+                f.write_str("error: OUT_DIR is not set and no output dir is given")
+            }
+            BuildError::Load(e) => write!(f, "{}", e),
+            BuildError::Diagnostics(diagnostics) => {
+                let diagnostics: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+                f.write_str(&diagnostics.join("\n"))
+            }
+            BuildError::Module { module, paths } => write!(
+                f,
+                "error: {} and {} are both generated into module `{}`",
+                paths[0].display(),
+                paths[1].display(),
+                module
+            ),
+            BuildError::Io { path, error } => {
+                write!(f, "error: cannot write {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Load(e) => Some(e),
+            BuildError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "thrift", "workspace", name]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_compile() {
+        let out_dir = std::env::temp_dir().join("thrift-parser-test-compile");
+        let _ = std::fs::remove_dir_all(&out_dir);
+        std::fs::create_dir_all(&out_dir).unwrap();
+
+        Config::new()
+            .include_dir(fixture("lib"))
+            .out_dir(&out_dir)
+            .compile(&[fixture("main.thrift"), fixture("shared.thrift")])
+            .unwrap();
+        let read = |name: &str| std::fs::read_to_string(out_dir.join(name)).unwrap();
+        assert_eq!(
+            read(INCLUDE_FILE),
+            r#"pub mod _thrift_runtime {
+    include!("_thrift_runtime.rs");
+}
+pub mod base {
+    include!("base.rs");
+}
+pub mod shared {
+    include!("shared.rs");
+}
+pub mod workspace {
+    pub mod main {
+        include!("workspace.main.rs");
+    }
+}
+"#
+        );
+        assert!(read("workspace.main.rs").contains("pub shared: super::super::shared::Shared,"));
+        assert!(read("base.rs").contains("pub type Id = i64;"));
+
+        let error = Config::new()
+            .out_dir(&out_dir)
+            .compile(&[fixture("main.thrift")])
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            BuildError::Load(LoadError::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::annotation::Annotations;
+use crate::basic::Doc;
+use crate::definition::{Const, Definition, Enum, Service, Typedef};
+use crate::eval::{Evaluator, ResolvedConstValue};
+use crate::field::Field;
+use crate::functions::Function;
+use crate::json::Json;
+use crate::resolve::{CanonicalType, Resolver, Symbol, SymbolKind};
+use crate::span::Span;
+use crate::types::FieldType;
+use crate::validate::field_ids;
+use crate::workspace::{Program, Workspace};
+use crate::Diagnostic;
+
+// Generate what `thrift --gen json` generates for `program`: an object with `name`,
+// `namespaces`, `includes`, `enums`, `typedefs`, `structs`, `constants` and `services`.
+// Types are described by a `typeId`, plus a `type` object for structs and containers. Types from
+// included files are qualified with the name of the file, and constant values are evaluated.
+pub fn generate(workspace: &Workspace, program: &Program) -> Result<String, Vec<Diagnostic>> {
+    let mut generator = Generator {
+        workspace,
+        program,
+        resolver: Resolver::new(workspace),
+        evaluator: Evaluator::new(workspace),
+        diagnostics: Vec::new(),
+    };
+    let json = generator.program();
+    match generator.diagnostics.is_empty() {
+        true => Ok(json.pretty()),
+        false => Err(generator.diagnostics),
+    }
+}
+
+struct Generator<'w> {
+    workspace: &'w Workspace,
+    program: &'w Program,
+    resolver: Resolver,
+    evaluator: Evaluator<'w>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'w> Generator<'w> {
+    fn program(&mut self) -> Json {
+        let document = &self.program.document;
+        let namespaces: BTreeMap<_, _> = document
+            .namespaces
+            .iter()
+            .map(|n| (n.scope.to_string(), Json::String(n.name.to_string())))
+            .collect();
+        let includes = document
+            .includes
+            .iter()
+            .map(|i| {
+                let stem = Path::new(i.as_str()).file_stem().unwrap_or_default();
+                Json::String(stem.to_string_lossy().into_owned())
+            })
+            .collect();
+
+        let (mut enums, mut typedefs, mut structs, mut constants, mut services) =
+            (vec![], vec![], vec![], vec![], vec![]);
+        for definition in &document.definitions {
+            match definition {
+                Definition::Const(x) => constants.push(self.constant(x)),
+                Definition::Typedef(x) => typedefs.push(self.typedef(x)),
+                Definition::Enum(x) => enums.push(self.enumeration(x)),
+                Definition::Struct(x) => structs.push(self.structure(
+                    &x.name,
+                    &x.fields,
+                    false,
+                    false,
+                    &x.doc,
+                    &x.annotations,
+                )),
+                Definition::Union(x) => structs.push(self.structure(
+                    &x.name,
+                    &x.fields,
+                    false,
+                    true,
+                    &x.doc,
+                    &x.annotations,
+                )),
+                Definition::Exception(x) => structs.push(self.structure(
+                    &x.name,
+                    &x.fields,
+                    true,
+                    false,
+                    &x.doc,
+                    &x.annotations,
+                )),
+                Definition::Service(x) => services.push(self.service(x)),
+            }
+        }
+
+        Json::Object(vec![
+            ("name".into(), Json::String(self.program.alias.clone())),
+            (
+                "namespaces".into(),
+                Json::Object(namespaces.into_iter().collect()),
+            ),
+            ("includes".into(), Json::Array(includes)),
+            ("enums".into(), Json::Array(enums)),
+            ("typedefs".into(), Json::Array(typedefs)),
+            ("structs".into(), Json::Array(structs)),
+            ("constants".into(), Json::Array(constants)),
+            ("services".into(), Json::Array(services)),
+        ])
+    }
+
+    fn enumeration(&mut self, enum_: &Enum) -> Json {
+        let mut object = vec![("name".into(), Json::String(enum_.name.to_string()))];
+        extras(&mut object, &enum_.doc, &enum_.annotations);
+        let values = enum_.values().unwrap_or_else(|e| {
+            self.diagnostics.push(e);
+            Vec::new()
+        });
+        let members = enum_
+            .children
+            .iter()
+            .zip(values)
+            .map(|(child, (_, value))| {
+                let mut member = vec![
+                    ("name".into(), Json::String(child.name.to_string())),
+                    ("value".into(), Json::Int(value.into())),
+                ];
+                extras(&mut member, &child.doc, &child.annotations);
+                Json::Object(member)
+            })
+            .collect();
+        object.push(("members".into(), Json::Array(members)));
+        Json::Object(object)
+    }
+
+    fn typedef(&mut self, typedef: &Typedef) -> Json {
+        let mut object = vec![("name".into(), Json::String(typedef.alias.to_string()))];
+        object.extend(self.type_entries("typeId", "type", &typedef.old, typedef.span));
+        extras(&mut object, &typedef.doc, &typedef.annotations);
+        Json::Object(object)
+    }
+
+    fn constant(&mut self, const_: &Const) -> Json {
+        let mut object = vec![("name".into(), Json::String(const_.name.to_string()))];
+        object.extend(self.type_entries("typeId", "type", &const_.type_, const_.span));
+        extras(&mut object, &const_.doc, &None);
+        let value = match self.evaluator.eval_const(self.program, const_) {
+            Ok(value) => value_json(&value),
+            Err(e) => {
+                self.diagnostics.push(e);
+                Json::Null
+            }
+        };
+        object.push(("value".into(), value));
+        Json::Object(object)
+    }
+
+    fn structure(
+        &mut self,
+        name: &str,
+        fields: &[Field],
+        is_exception: bool,
+        is_union: bool,
+        doc: &Option<Doc>,
+        annotations: &Option<Annotations>,
+    ) -> Json {
+        let mut object = vec![("name".into(), Json::String(name.into()))];
+        extras(&mut object, doc, annotations);
+        object.push(("isException".into(), Json::Bool(is_exception)));
+        object.push(("isUnion".into(), Json::Bool(is_union)));
+        object.push(("fields".into(), self.fields(fields)));
+        Json::Object(object)
+    }
+
+    fn fields(&mut self, fields: &[Field]) -> Json {
+        let fields = fields
+            .iter()
+            .zip(field_ids(fields))
+            .map(|(field, id)| {
+                let mut object = vec![
+                    ("key".into(), Json::Int(id)),
+                    ("name".into(), Json::String(field.name.to_string())),
+                ];
+                object.extend(self.type_entries("typeId", "type", &field.type_, field.span));
+                extras(&mut object, &field.doc, &field.annotations);
+                let required = match field.required {
+                    Some(true) => "required",
+                    Some(false) => "optional",
+                    None => "req_out",
+                };
+                object.push(("required".into(), Json::String(required.into())));
+                match self.evaluator.eval_default(self.program, field) {
+                    Ok(Some(value)) => object.push(("default".into(), value_json(&value))),
+                    Ok(None) => {}
+                    Err(e) => self.diagnostics.push(e),
+                }
+                Json::Object(object)
+            })
+            .collect();
+        Json::Array(fields)
+    }
+
+    fn service(&mut self, service: &Service) -> Json {
+        let mut object = vec![("name".into(), Json::String(service.name.to_string()))];
+        if let Some(extension) = &service.extension {
+            match self.resolver.resolve_service(self.program, extension) {
+                Ok(symbol) => {
+                    object.push(("extends".into(), Json::String(self.qualified(&symbol))))
+                }
+                Err(_) => self.diagnostics.push(self.error(
+                    service.span,
+                    format!("cannot find service `{}`", extension.as_str()),
+                )),
+            }
+        }
+        extras(&mut object, &service.doc, &service.annotations);
+        let functions = service.functions.iter().map(|f| self.function(f)).collect();
+        object.push(("functions".into(), Json::Array(functions)));
+        Json::Object(object)
+    }
+
+    fn function(&mut self, function: &Function) -> Json {
+        let mut object = vec![("name".into(), Json::String(function.name.to_string()))];
+        match &function.returns {
+            Some(returns) => object.extend(self.type_entries(
+                "returnTypeId",
+                "returnType",
+                returns,
+                function.span,
+            )),
+            None => object.push(("returnTypeId".into(), Json::String("void".into()))),
+        }
+        object.push(("oneway".into(), Json::Bool(function.oneway)));
+        extras(&mut object, &function.doc, &function.annotations);
+        object.push(("arguments".into(), self.fields(&function.parameters)));
+        let exceptions = self.fields(function.exceptions.as_deref().unwrap_or_default());
+        object.push(("exceptions".into(), exceptions));
+        Json::Object(object)
+    }
+
+    // The `typeId` of a type, and its `type` object if it needs one.
+    fn type_entries(
+        &mut self,
+        id_key: &str,
+        type_key: &str,
+        type_: &FieldType,
+        span: Span,
+    ) -> Vec<(String, Json)> {
+        match self
+            .resolver
+            .canonical_type(self.workspace, self.program, type_)
+        {
+            Ok(canonical) => self.canonical_entries(id_key, type_key, &canonical),
+            Err(e) => {
+                self.diagnostics.push(self.error(span, e.to_string()));
+                vec![(id_key.into(), Json::String("(unknown)".into()))]
+            }
+        }
+    }
+
+    fn canonical_entries(
+        &self,
+        id_key: &str,
+        type_key: &str,
+        type_: &CanonicalType,
+    ) -> Vec<(String, Json)> {
+        let mut entries = vec![(id_key.into(), Json::String(type_id(type_).into()))];
+        if let Some(spec) = self.type_spec(type_) {
+            entries.push((type_key.into(), spec));
+        }
+        entries
+    }
+
+    // Structs and containers are described further in an object.
+    fn type_spec(&self, type_: &CanonicalType) -> Option<Json> {
+        let mut object = vec![("typeId".into(), Json::String(type_id(type_).into()))];
+        match type_ {
+            CanonicalType::Named(symbol) if symbol.kind != SymbolKind::Enum => {
+                object.push(("class".into(), Json::String(self.qualified(symbol))));
+            }
+            CanonicalType::Map(key, value) => {
+                object.extend(self.canonical_entries("keyTypeId", "keyType", key));
+                object.extend(self.canonical_entries("valueTypeId", "valueType", value));
+            }
+            CanonicalType::Set(element) | CanonicalType::List(element) => {
+                object.extend(self.canonical_entries("elemTypeId", "elemType", element));
+            }
+            _ => return None,
+        }
+        Some(Json::Object(object))
+    }
+
+    // Names from other files are prefixed with the name of the file.
+    fn qualified(&self, symbol: &Symbol) -> String {
+        match self.workspace.get(&symbol.path) {
+            Some(program) if program.path != self.program.path => {
+                format!("{}.{}", program.alias, symbol.name)
+            }
+            _ => symbol.name.clone(),
+        }
+    }
+
+    fn error(&self, span: Span, message: String) -> Diagnostic {
+        Diagnostic::error(span, message).with_path(&self.program.path)
+    }
+}
+
+fn type_id(type_: &CanonicalType) -> &'static str {
+    match type_ {
+        CanonicalType::Bool => "bool",
+        CanonicalType::Byte | CanonicalType::I8 => "i8",
+        CanonicalType::I16 => "i16",
+        CanonicalType::I32 => "i32",
+        CanonicalType::I64 => "i64",
+        CanonicalType::Double => "double",
+        CanonicalType::String => "string",
+        CanonicalType::Binary => "binary",
+        CanonicalType::Map(..) => "map",
+        CanonicalType::Set(_) => "set",
+        CanonicalType::List(_) => "list",
+        CanonicalType::Named(symbol) => match symbol.kind {
+            SymbolKind::Enum => "i32",
+            SymbolKind::Union => "union",
+            SymbolKind::Exception => "exception",
+            _ => "struct",
+        },
+    }
+}
+
+// Add `doc` and `annotations` if there are any. Like the Apache compiler, doc ends with a new
+// line and annotations without value have the value "1".
+fn extras(object: &mut Vec<(String, Json)>, doc: &Option<Doc>, annotations: &Option<Annotations>) {
+    if let Some(doc) = doc {
+        object.push(("doc".into(), Json::String(format!("{}\n", doc.text()))));
+    }
+    if let Some(annotations) = annotations.as_ref().filter(|a| !a.is_empty()) {
+        let annotations = annotations
+            .iter()
+            .map(|a| {
+                let value = a.value.as_ref().map_or("1", |v| v.as_str());
+                (a.key.to_string(), Json::String(value.into()))
+            })
+            .collect();
+        object.push(("annotations".into(), Json::Object(annotations)));
+    }
+}
+
+// Bools and enum values are numbers, map keys are strings.
+fn value_json(value: &ResolvedConstValue) -> Json {
+    match value {
+        ResolvedConstValue::Bool(v) => Json::Int(*v as i64),
+        ResolvedConstValue::Byte(v) => Json::Int(*v as i64),
+        ResolvedConstValue::I16(v) => Json::Int(*v as i64),
+        ResolvedConstValue::I32(v) => Json::Int(*v as i64),
+        ResolvedConstValue::I64(v) => Json::Int(*v),
+        ResolvedConstValue::Enum { value, .. } => Json::Int(*value as i64),
+        ResolvedConstValue::Double(v) => Json::Double(*v),
+        ResolvedConstValue::String(v) => Json::String(v.clone()),
+        ResolvedConstValue::Binary(v) => Json::String(String::from_utf8_lossy(v).into_owned()),
+        ResolvedConstValue::List(v) | ResolvedConstValue::Set(v) => {
+            Json::Array(v.iter().map(value_json).collect())
+        }
+        ResolvedConstValue::Map(v) => Json::Object(
+            v.iter()
+                .map(|(k, v)| {
+                    let key = match value_json(k) {
+                        Json::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (key, value_json(v))
+                })
+                .collect(),
+        ),
+        ResolvedConstValue::Struct(v) => Json::Object(
+            v.iter()
+                .map(|(name, v)| (name.clone(), value_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "thrift", "workspace", name]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_generate() {
+        let source = r#"
+include "shared.thrift"
+namespace rs demo
+namespace * demo.all
+
+/** Kinds of users. */
+enum Kind { PERSON, BOT = 5 (deprecated) }
+typedef map<string, list<shared.Shared>> Index
+const Kind DEFAULT_KIND = Kind.BOT
+const map<i32, bool> FLAGS = {1: true}
+
+struct User {
+    1: required i64 id
+    2: optional Kind kind = Kind.PERSON
+    string nick (go.tag = "nick")
+}
+union Either { 1: User user }
+exception NotFound {}
+
+service UserService extends shared.SharedService {
+    User Get(1: i64 id) throws (1: NotFound e)
+    oneway void Touch()
+}
+"#;
+        let workspace =
+            Workspace::from_source(fixture("demo.thrift"), source, &[fixture("lib")]).unwrap();
+        let output = generate(&workspace, workspace.root()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "demo",
+                "namespaces": {"*": "demo.all", "rs": "demo"},
+                "includes": ["shared"],
+                "enums": [{
+                    "name": "Kind",
+                    "doc": "Kinds of users.\n",
+                    "members": [
+                        {"name": "PERSON", "value": 0},
+                        {"name": "BOT", "value": 5, "annotations": {"deprecated": "1"}},
+                    ],
+                }],
+                "typedefs": [{
+                    "name": "Index",
+                    "typeId": "map",
+                    "type": {
+                        "typeId": "map",
+                        "keyTypeId": "string",
+                        "valueTypeId": "list",
+                        "valueType": {
+                            "typeId": "list",
+                            "elemTypeId": "struct",
+                            "elemType": {"typeId": "struct", "class": "shared.Shared"},
+                        },
+                    },
+                }],
+                "structs": [
+                    {
+                        "name": "User",
+                        "isException": false,
+                        "isUnion": false,
+                        "fields": [
+                            {"key": 1, "name": "id", "typeId": "i64", "required": "required"},
+                            {
+                                "key": 2,
+                                "name": "kind",
+                                "typeId": "i32",
+                                "required": "optional",
+                                "default": 0,
+                            },
+                            {
+                                "key": -1,
+                                "name": "nick",
+                                "typeId": "string",
+                                "annotations": {"go.tag": "nick"},
+                                "required": "req_out",
+                            },
+                        ],
+                    },
+                    {
+                        "name": "Either",
+                        "isException": false,
+                        "isUnion": true,
+                        "fields": [{
+                            "key": 1,
+                            "name": "user",
+                            "typeId": "struct",
+                            "type": {"typeId": "struct", "class": "User"},
+                            "required": "req_out",
+                        }],
+                    },
+                    {"name": "NotFound", "isException": true, "isUnion": false, "fields": []},
+                ],
+                "constants": [
+                    {"name": "DEFAULT_KIND", "typeId": "i32", "value": 5},
+                    {
+                        "name": "FLAGS",
+                        "typeId": "map",
+                        "type": {"typeId": "map", "keyTypeId": "i32", "valueTypeId": "bool"},
+                        "value": {"1": 1},
+                    },
+                ],
+                "services": [{
+                    "name": "UserService",
+                    "extends": "shared.SharedService",
+                    "functions": [
+                        {
+                            "name": "Get",
+                            "returnTypeId": "struct",
+                            "returnType": {"typeId": "struct", "class": "User"},
+                            "oneway": false,
+                            "arguments": [
+                                {"key": 1, "name": "id", "typeId": "i64", "required": "req_out"},
+                            ],
+                            "exceptions": [{
+                                "key": 1,
+                                "name": "e",
+                                "typeId": "exception",
+                                "type": {"typeId": "exception", "class": "NotFound"},
+                                "required": "req_out",
+                            }],
+                        },
+                        {
+                            "name": "Touch",
+                            "returnTypeId": "void",
+                            "oneway": true,
+                            "arguments": [],
+                            "exceptions": [],
+                        },
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let source = "struct User { 1: Missing m }\nconst i32 C = \"text\"";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let messages: Vec<_> = generate(&workspace, workspace.root())
+            .unwrap_err()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["cannot find type `Missing`", "expected i32, found \"text\"",]
+        );
+    }
+}
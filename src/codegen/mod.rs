@@ -0,0 +1,3 @@
+// Generators turning parsed files into other forms.
+pub mod json;
+pub mod rust;
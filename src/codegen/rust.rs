@@ -0,0 +1,2076 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::basic::Doc;
+use crate::definition::{Const, Definition, Enum, Service, Typedef};
+use crate::eval::{Evaluator, ResolvedConstValue};
+use crate::field::Field;
+use crate::functions::Function;
+use crate::resolve::{
+    CanonicalType, Resolver, ServiceError, ServiceFunction, Symbol, SymbolKind, TypeError,
+};
+use crate::span::Span;
+use crate::types::FieldType;
+use crate::validate::{field_ids, validate};
+use crate::workspace::{Program, Workspace};
+use crate::Diagnostic;
+
+// Generate Rust types for the definitions of `program`, which is in `workspace`:
+// - A struct is a struct whose optional fields are `Option`s, with a `Default` giving the
+//   default values of the fields. Types containing themselves are boxed.
+// - An exception is a struct that also implements `std::error::Error`.
+// - A union is an enum with a variant for each field.
+// - An enum is a fieldless enum, convertible from i32 with `TryFrom` and to i32 with `From`.
+// - A typedef is a type alias.
+// - A const of a number, bool, string, binary or enum type is a `const` item, any other const is
+//   a function returning the value.
+// - A service is a trait with an async method for each function. A function throwing exceptions
+//   returns a `Result` whose error is an enum with a variant for each exception. A service
+//   extending another has its trait as a supertrait.
+// - A service also has a client, with an async method for each function including inherited
+//   ones. A call encodes the arguments struct of the function and decodes its result struct,
+//   through a `Transport` which writes and reads them in a protocol.
+// - Structs, exceptions, unions and enums implement `Encode` and `Decode`.
+// Names are converted to Rust conventions. The code is meant to be the module `module_path`
+// gives, types from other files are referred to relative to it. `Transport`, `Encode`, `Decode`
+// and what they need are in the code `RUNTIME`, which is meant to be the module `RUNTIME_MODULE`
+// at the root of the modules of the files.
+pub fn generate(workspace: &Workspace, program: &Program) -> Result<String, Vec<Diagnostic>> {
+    let errors: Vec<_> = validate(&program.document)
+        .into_iter()
+        .filter(Diagnostic::is_error)
+        .map(|d| d.with_path(&program.path))
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut generator = Generator {
+        workspace,
+        program,
+        module: module_path(program),
+        resolver: Resolver::new(workspace),
+        evaluator: Evaluator::new(workspace),
+        diagnostics: Vec::new(),
+    };
+    let mut body = String::new();
+    for definition in &program.document.definitions {
+        let code = match definition {
+            Definition::Const(x) => generator.constant(x),
+            Definition::Typedef(x) => generator.typedef(x),
+            Definition::Enum(x) => generator.enumeration(x),
+            Definition::Struct(x) => {
+                generator.structure(SymbolKind::Struct, x.name.as_str(), &x.fields, &x.doc)
+            }
+            Definition::Union(x) => generator.union(x.name.as_str(), &x.fields, &x.doc),
+            Definition::Exception(x) => {
+                generator.structure(SymbolKind::Exception, x.name.as_str(), &x.fields, &x.doc)
+            }
+            Definition::Service(x) => generator.service(x),
+        };
+        body.push('\n');
+        body.push_str(&code);
+    }
+
+    let mut out = format!(
+        "// Generated by thrift-parser from {}.thrift, do not edit.\n",
+        program.alias
+    );
+    if body.contains("_thrift_runtime::") {
+        let mut path = vec!["super"; generator.module.len()];
+        path.push(RUNTIME_MODULE);
+        let _ = writeln!(out, "\nuse {};", path.join("::"));
+    }
+    out.push_str(&body);
+    match generator.diagnostics.is_empty() {
+        true => Ok(out),
+        false => Err(generator.diagnostics),
+    }
+}
+
+// Generate the code of every file in `workspace`, each in the modules `module_path` gives, so
+// that they can refer to each other.
+pub fn generate_workspace(workspace: &Workspace) -> Result<String, Vec<Diagnostic>> {
+    Resolver::new(workspace).check(workspace)?;
+    let mut modules = Modules::default();
+    modules.insert(vec![RUNTIME_MODULE.into()], PathBuf::new(), RUNTIME.into());
+    for program in workspace.programs() {
+        let module = module_path(program);
+        if let Some(other) = modules.source(&module) {
+            let mut span = Span::default();
+            span.line = 1;
+            span.column = 1;
+            let message = format!(
+                "{} and {} are both generated into module `{}`",
+                other.display(),
+                program.path.display(),
+                module.join("::")
+            );
+            return Err(vec![
+                Diagnostic::error(span, message).with_path(&program.path)
+            ]);
+        }
+        let code = generate(workspace, program)?;
+        modules.insert(module, program.path.clone(), code);
+    }
+    let mut out = String::new();
+    modules.write(0, &mut out);
+    Ok(out)
+}
+
+// Nested modules with the code generated for them.
+#[derive(Debug, Default)]
+pub(crate) struct Modules {
+    // The file the module is generated from, and the code of the module.
+    code: Option<(PathBuf, String)>,
+    children: BTreeMap<String, Modules>,
+}
+
+impl Modules {
+    // The file `module` is generated from, if it is already generated.
+    pub(crate) fn source(&self, module: &[String]) -> Option<&Path> {
+        let node = module
+            .iter()
+            .try_fold(self, |node, name| node.children.get(name))?;
+        node.code.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    pub(crate) fn insert(&mut self, module: Vec<String>, path: PathBuf, code: String) {
+        let node = module
+            .into_iter()
+            .fold(self, |node, name| node.children.entry(name).or_default());
+        node.code = Some((path, code));
+    }
+
+    pub(crate) fn write(&self, level: usize, out: &mut String) {
+        let indent = "    ".repeat(level);
+        if let Some((_, code)) = &self.code {
+            for line in code.lines() {
+                match line.is_empty() {
+                    true => out.push('\n'),
+                    false => {
+                        let _ = writeln!(out, "{}{}", indent, line);
+                    }
+                }
+            }
+        }
+        for (name, child) in &self.children {
+            let _ = writeln!(out, "{}pub mod {} {{", indent, name);
+            child.write(level + 1, out);
+            let _ = writeln!(out, "{}}}", indent);
+        }
+    }
+}
+
+// The Rust module of a file: the `rs` namespace split at dots, or the name of the file if it has
+// no such namespace.
+pub fn module_path(program: &Program) -> Vec<String> {
+    program
+        .document
+        .namespaces
+        .iter()
+        .rev()
+        .find(|n| n.scope.as_str() == "rs")
+        .map(|n| n.name.split('.').map(module_name).collect())
+        .unwrap_or_else(|| vec![module_name(&program.alias)])
+}
+
+// The module of `RUNTIME`. Modules of files never start with an underscore and a letter, so
+// none of them is this one.
+pub const RUNTIME_MODULE: &str = "_thrift_runtime";
+
+// The code shared by the code of every file: the `Value`s that transports write in a protocol and
+// read from one, the `Encode` and `Decode` traits converting generated types to and from them,
+// and `Transport`.
+pub const RUNTIME: &str = r#"// Generated by thrift-parser, do not edit.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+
+/// The type of a value on the wire. Binary values are strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Bool,
+    Byte,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Struct,
+    Map,
+    Set,
+    List,
+}
+
+/// A value for transports to write in a protocol, or read from one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Byte(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    String(String),
+    Binary(Vec<u8>),
+    /// The fields that are set, by id.
+    Struct(Vec<(i16, Value)>),
+    /// Entries with the types of their keys and values, which some protocols write even when
+    /// there are no entries. So do sets and lists with the type of their items.
+    Map(Type, Type, Vec<(Value, Value)>),
+    Set(Type, Vec<Value>),
+    List(Type, Vec<Value>),
+}
+
+impl Value {
+    pub fn type_(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::Byte(_) => Type::Byte,
+            Value::I16(_) => Type::I16,
+            Value::I32(_) => Type::I32,
+            Value::I64(_) => Type::I64,
+            Value::Double(_) => Type::Double,
+            Value::String(_) | Value::Binary(_) => Type::String,
+            Value::Struct(_) => Type::Struct,
+            Value::Map(..) => Type::Map,
+            Value::Set(..) => Type::Set,
+            Value::List(..) => Type::List,
+        }
+    }
+}
+
+/// Why a value cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub message: String,
+}
+
+impl DecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    fn mismatch(expected: Type, found: &Value) -> Self {
+        Self::new(format!("expected {:?}, found {:?}", expected, found.type_()))
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Types that are written as a `Value`.
+pub trait Encode {
+    const TYPE: Type;
+
+    fn encode(&self) -> Value;
+}
+
+/// Types that are read from a `Value`.
+pub trait Decode: Sized {
+    fn decode(value: Value) -> Result<Self, DecodeError>;
+}
+
+/// The fields of a struct value, taken by id when decoding it.
+pub struct Fields(Vec<(i16, Value)>);
+
+impl Fields {
+    pub fn new(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Struct(fields) => Ok(Self(fields)),
+            value => Err(DecodeError::mismatch(Type::Struct, &value)),
+        }
+    }
+
+    /// Decode field `id`, if it is set.
+    pub fn take<T: Decode>(&mut self, id: i16) -> Result<Option<T>, DecodeError> {
+        match self.0.iter().position(|(i, _)| *i == id) {
+            Some(i) => T::decode(self.0.swap_remove(i).1).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode field `id` of `struct_`, which must be set.
+    pub fn required<T: Decode>(&mut self, id: i16, struct_: &str) -> Result<T, DecodeError> {
+        self.take(id)?.ok_or_else(|| {
+            DecodeError::new(format!("required field {} of {} is not set", id, struct_))
+        })
+    }
+}
+
+macro_rules! scalar {
+    ($rust:ty, $variant:ident, $type_:ident) => {
+        impl Encode for $rust {
+            const TYPE: Type = Type::$type_;
+
+            fn encode(&self) -> Value {
+                Value::$variant(*self)
+            }
+        }
+
+        impl Decode for $rust {
+            fn decode(value: Value) -> Result<Self, DecodeError> {
+                match value {
+                    Value::$variant(v) => Ok(v),
+                    value => Err(DecodeError::mismatch(Type::$type_, &value)),
+                }
+            }
+        }
+    };
+}
+
+scalar!(bool, Bool, Bool);
+scalar!(i8, Byte, Byte);
+scalar!(i16, I16, I16);
+scalar!(i32, I32, I32);
+scalar!(i64, I64, I64);
+scalar!(f64, Double, Double);
+
+// Strings and binaries are the same on the wire, so either decodes as both.
+impl Encode for String {
+    const TYPE: Type = Type::String;
+
+    fn encode(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl Decode for String {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::String(v) => Ok(v),
+            Value::Binary(v) => String::from_utf8(v).map_err(|e| DecodeError::new(e.to_string())),
+            value => Err(DecodeError::mismatch(Type::String, &value)),
+        }
+    }
+}
+
+impl Encode for Vec<u8> {
+    const TYPE: Type = Type::String;
+
+    fn encode(&self) -> Value {
+        Value::Binary(self.clone())
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Binary(v) => Ok(v),
+            Value::String(v) => Ok(v.into_bytes()),
+            value => Err(DecodeError::mismatch(Type::String, &value)),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    const TYPE: Type = T::TYPE;
+
+    fn encode(&self) -> Value {
+        (**self).encode()
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        T::decode(value).map(Box::new)
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    const TYPE: Type = Type::List;
+
+    fn encode(&self) -> Value {
+        Value::List(T::TYPE, self.iter().map(T::encode).collect())
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::List(_, items) => items.into_iter().map(T::decode).collect(),
+            value => Err(DecodeError::mismatch(Type::List, &value)),
+        }
+    }
+}
+
+impl<T: Encode> Encode for BTreeSet<T> {
+    const TYPE: Type = Type::Set;
+
+    fn encode(&self) -> Value {
+        Value::Set(T::TYPE, self.iter().map(T::encode).collect())
+    }
+}
+
+impl<T: Decode + Ord> Decode for BTreeSet<T> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Set(_, items) => items.into_iter().map(T::decode).collect(),
+            value => Err(DecodeError::mismatch(Type::Set, &value)),
+        }
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    const TYPE: Type = Type::Map;
+
+    fn encode(&self) -> Value {
+        let entries = self.iter().map(|(k, v)| (k.encode(), v.encode()));
+        Value::Map(K::TYPE, V::TYPE, entries.collect())
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Map(_, _, entries) => entries
+                .into_iter()
+                .map(|(k, v)| Ok((K::decode(k)?, V::decode(v)?)))
+                .collect(),
+            value => Err(DecodeError::mismatch(Type::Map, &value)),
+        }
+    }
+}
+
+/// A set of values without a total order, like doubles, kept as a list.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ListSet<T>(pub Vec<T>);
+
+impl<T> Default for ListSet<T> {
+    fn default() -> Self {
+        ListSet(Vec::new())
+    }
+}
+
+impl<T: Encode> Encode for ListSet<T> {
+    const TYPE: Type = Type::Set;
+
+    fn encode(&self) -> Value {
+        Value::Set(T::TYPE, self.0.iter().map(T::encode).collect())
+    }
+}
+
+impl<T: Decode> Decode for ListSet<T> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Set(_, items) => items.into_iter().map(T::decode).collect::<Result<_, _>>().map(ListSet),
+            value => Err(DecodeError::mismatch(Type::Set, &value)),
+        }
+    }
+}
+
+/// A map with keys without a total order, like doubles, kept as a list of entries.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ListMap<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> Default for ListMap<K, V> {
+    fn default() -> Self {
+        ListMap(Vec::new())
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for ListMap<K, V> {
+    const TYPE: Type = Type::Map;
+
+    fn encode(&self) -> Value {
+        let entries = self.0.iter().map(|(k, v)| (k.encode(), v.encode()));
+        Value::Map(K::TYPE, V::TYPE, entries.collect())
+    }
+}
+
+impl<K: Decode, V: Decode> Decode for ListMap<K, V> {
+    fn decode(value: Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Map(_, _, entries) => entries
+                .into_iter()
+                .map(|(k, v)| Ok((K::decode(k)?, V::decode(v)?)))
+                .collect::<Result<_, _>>()
+                .map(ListMap),
+            value => Err(DecodeError::mismatch(Type::Map, &value)),
+        }
+    }
+}
+
+/// Makes the calls of the service clients.
+pub trait Transport: Send + Sync {
+    type Error;
+
+    /// Call `method` of `service` with the `<method>_args` struct `args`, and wait for the
+    /// `<method>_result` struct of the reply.
+    fn call<A, R>(
+        &self,
+        service: &'static str,
+        method: &'static str,
+        args: A,
+    ) -> impl Future<Output = Result<R, Self::Error>> + Send
+    where
+        A: Encode + Send + 'static,
+        R: Decode + Send + 'static;
+
+    /// Call oneway `method` of `service`, without a reply.
+    fn send<A>(
+        &self,
+        service: &'static str,
+        method: &'static str,
+        args: A,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        A: Encode + Send + 'static;
+}
+"#;
+
+pub(crate) struct Generator<'w> {
+    pub(crate) workspace: &'w Workspace,
+    pub(crate) program: &'w Program,
+    pub(crate) module: Vec<String>,
+    pub(crate) resolver: Resolver,
+    pub(crate) evaluator: Evaluator<'w>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl<'w> Generator<'w> {
+    fn enumeration(&mut self, enum_: &Enum) -> String {
+        let name = type_name(&enum_.name);
+        let values = enum_.numbered();
+        // Fields of an enum start as its first value, which an empty enum does not have.
+        let first = match values.first() {
+            Some((first, _)) => variant_name(&first.name),
+            None => {
+                let message = format!("enum {} has no values", *enum_.name);
+                let diagnostic = Diagnostic::error(enum_.span, message);
+                self.diagnostics
+                    .push(diagnostic.with_path(&self.program.path));
+                return String::new();
+            }
+        };
+        let mut out = doc(&enum_.doc, "");
+        out.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]\n");
+        let _ = writeln!(out, "pub enum {} {{", name);
+        for (child, value) in &values {
+            out.push_str(&doc(&child.doc, "    "));
+            let _ = writeln!(out, "    {} = {},", variant_name(&child.name), value);
+        }
+        out.push_str("}\n\n");
+
+        let _ = writeln!(out, "impl ::std::convert::TryFrom<i32> for {} {{", name);
+        out.push_str("    type Error = i32;\n\n");
+        out.push_str("    fn try_from(value: i32) -> ::std::result::Result<Self, i32> {\n");
+        out.push_str("        match value {\n");
+        for (child, value) in &values {
+            let variant = variant_name(&child.name);
+            let _ = writeln!(out, "            {} => Ok({}::{}),", value, name, variant);
+        }
+        out.push_str("            _ => Err(value),\n        }\n    }\n}\n\n");
+
+        let _ = writeln!(out, "impl From<{}> for i32 {{", name);
+        let _ = writeln!(out, "    fn from(value: {}) -> i32 {{", name);
+        out.push_str("        value as i32\n    }\n}\n");
+
+        let _ = writeln!(out, "\nimpl Default for {} {{", name);
+        out.push_str("    fn default() -> Self {\n");
+        let _ = writeln!(out, "        {}::{}", name, first);
+        out.push_str("    }\n}\n");
+
+        out.push_str(&encode_impl(
+            &name,
+            "I32",
+            "_thrift_runtime::Value::I32(i32::from(*self))",
+        ));
+        out.push_str(&decode_impl(
+            &name,
+            &format!(
+                "let value: i32 = _thrift_runtime::Decode::decode(value)?;\n\
+                 ::std::convert::TryFrom::try_from(value).map_err(|value| {{\n    \
+                 _thrift_runtime::DecodeError::new(format!(\"unknown value {{}} of enum {}\", value))\n}})",
+                enum_.name
+            ),
+        ));
+        out
+    }
+
+    fn typedef(&mut self, typedef: &Typedef) -> String {
+        self.canonical(&typedef.old, typedef.span);
+        let mut out = doc(&typedef.doc, "");
+        let _ = writeln!(
+            out,
+            "pub type {} = {};",
+            type_name(&typedef.alias),
+            self.rust_type(self.program, &typedef.old)
+        );
+        out
+    }
+
+    fn constant(&mut self, const_: &Const) -> String {
+        let canonical = self.canonical(&const_.type_, const_.span);
+        let value = match self.evaluator.eval_const(self.program, const_) {
+            Ok(value) => value,
+            Err(e) => {
+                self.diagnostics.push(e);
+                return String::new();
+            }
+        };
+        let canonical = match canonical {
+            Some(canonical) => canonical,
+            None => return String::new(),
+        };
+        let mut out = doc(&const_.doc, "");
+        let name = const_name(&const_.name);
+        match (&canonical, &value) {
+            (CanonicalType::String, ResolvedConstValue::String(v)) => {
+                let _ = writeln!(out, "pub const {}: &str = {:?};", name, v);
+            }
+            (CanonicalType::Binary, ResolvedConstValue::Binary(v)) => {
+                let _ = writeln!(out, "pub const {}: &[u8] = &{:?};", name, v);
+            }
+            (CanonicalType::Map(..), _)
+            | (CanonicalType::Set(_), _)
+            | (CanonicalType::List(_), _)
+            | (
+                CanonicalType::Named(Symbol {
+                    kind: SymbolKind::Struct,
+                    ..
+                }),
+                _,
+            )
+            | (
+                CanonicalType::Named(Symbol {
+                    kind: SymbolKind::Union,
+                    ..
+                }),
+                _,
+            )
+            | (
+                CanonicalType::Named(Symbol {
+                    kind: SymbolKind::Exception,
+                    ..
+                }),
+                _,
+            ) => {
+                let _ = writeln!(
+                    out,
+                    "pub fn {}() -> {} {{\n    {}\n}}",
+                    field_name(&const_.name),
+                    self.rust_type(self.program, &const_.type_),
+                    self.value(&canonical, &value)
+                );
+            }
+            _ => {
+                let _ = writeln!(
+                    out,
+                    "pub const {}: {} = {};",
+                    name,
+                    self.rust_type(self.program, &const_.type_),
+                    self.value(&canonical, &value)
+                );
+            }
+        }
+        out
+    }
+
+    fn structure(
+        &mut self,
+        kind: SymbolKind,
+        name: &str,
+        fields: &[Field],
+        doc_: &Option<Doc>,
+    ) -> String {
+        let symbol = self.symbol(kind, name);
+        let name = type_name(name);
+        let mut out = doc(doc_, "");
+        out.push_str(&self.derives(&symbol));
+        let _ = writeln!(out, "pub struct {} {{", name);
+        let mut defaults = String::new();
+        for field in fields {
+            let canonical = self.canonical(&field.type_, field.span);
+            let boxed = canonical
+                .as_ref()
+                .is_some_and(|t| self.recursive(&symbol, t));
+            let optional = field.required == Some(false);
+            let mut type_ = self.rust_type(self.program, &field.type_);
+            if boxed {
+                type_ = format!("Box<{}>", type_);
+            }
+            if optional {
+                type_ = format!("Option<{}>", type_);
+            }
+            out.push_str(&doc(&field.doc, "    "));
+            let _ = writeln!(out, "    pub {}: {},", field_name(&field.name), type_);
+
+            let default = match self.evaluator.eval_default(self.program, field) {
+                Ok(Some(value)) => match &canonical {
+                    Some(canonical) => wrap(self.value(canonical, &value), boxed, optional),
+                    None => "Default::default()".into(),
+                },
+                Ok(None) if optional => "None".into(),
+                Ok(None) => "Default::default()".into(),
+                Err(e) => {
+                    self.diagnostics.push(e);
+                    "Default::default()".into()
+                }
+            };
+            let _ = writeln!(
+                defaults,
+                "            {}: {},",
+                field_name(&field.name),
+                default
+            );
+        }
+        out.push_str("}\n\n");
+
+        let _ = writeln!(out, "impl Default for {} {{", name);
+        out.push_str("    fn default() -> Self {\n        Self {\n");
+        out.push_str(&defaults);
+        out.push_str("        }\n    }\n}\n");
+
+        out.push_str(&encode_impl(&name, "Struct", &encode_fields(fields)));
+        let mut decoded = String::new();
+        for (field, id) in fields.iter().zip(field_ids(fields)) {
+            let take = match field.required {
+                Some(true) => format!("fields.required({}, {:?})?", id, symbol.name),
+                Some(false) => format!("fields.take({})?", id),
+                None => format!(
+                    "fields.take({})?.unwrap_or(default.{})",
+                    id,
+                    field_name(&field.name)
+                ),
+            };
+            let _ = writeln!(decoded, "    {}: {},", field_name(&field.name), take);
+        }
+        let body = match fields.is_empty() {
+            true => "_thrift_runtime::Fields::new(value).map(|_| Self {})".to_string(),
+            false => {
+                let default = match fields.iter().any(|f| f.required.is_none()) {
+                    true => "let default = Self::default();\n",
+                    false => "",
+                };
+                format!(
+                    "let mut fields = _thrift_runtime::Fields::new(value)?;\n{}Ok(Self {{\n{}}})",
+                    default, decoded
+                )
+            }
+        };
+        out.push_str(&decode_impl(&name, &body));
+
+        if kind == SymbolKind::Exception {
+            let _ = writeln!(out, "\nimpl ::std::fmt::Display for {} {{", name);
+            out.push_str(
+                "    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {\n",
+            );
+            out.push_str("        write!(f, \"{:?}\", self)\n    }\n}\n");
+            let _ = writeln!(out, "\nimpl ::std::error::Error for {} {{}}", name);
+        }
+        out
+    }
+
+    fn union(&mut self, name: &str, fields: &[Field], doc_: &Option<Doc>) -> String {
+        let symbol = self.symbol(SymbolKind::Union, name);
+        let name = type_name(name);
+        let mut out = doc(doc_, "");
+        out.push_str(&self.derives(&symbol));
+        let _ = writeln!(out, "pub enum {} {{", name);
+        for field in fields {
+            let canonical = self.canonical(&field.type_, field.span);
+            let boxed = canonical
+                .as_ref()
+                .is_some_and(|t| self.recursive(&symbol, t));
+            let mut type_ = self.rust_type(self.program, &field.type_);
+            if boxed {
+                type_ = format!("Box<{}>", type_);
+            }
+            out.push_str(&doc(&field.doc, "    "));
+            let _ = writeln!(out, "    {}({}),", variant_name(&field.name), type_);
+        }
+        out.push_str("}\n");
+
+        if let Some(first) = fields.first() {
+            let _ = writeln!(out, "\nimpl Default for {} {{", name);
+            out.push_str("    fn default() -> Self {\n");
+            let variant = variant_name(&first.name);
+            let _ = writeln!(out, "        {}::{}(Default::default())", name, variant);
+            out.push_str("    }\n}\n");
+        }
+
+        let mut encoded = String::new();
+        let mut decoded = String::new();
+        for (field, id) in fields.iter().zip(field_ids(fields)) {
+            let variant = variant_name(&field.name);
+            let _ = writeln!(
+                encoded,
+                "    {}::{}(value) => vec![({}, _thrift_runtime::Encode::encode(value))],",
+                name, variant, id
+            );
+            let _ = writeln!(
+                decoded,
+                "if let Some(value) = fields.take({})? {{\n    return Ok({}::{}(value));\n}}",
+                id, name, variant
+            );
+        }
+        let encoded = match fields.is_empty() {
+            true => "match *self {}".to_string(),
+            false => format!(
+                "_thrift_runtime::Value::Struct(match self {{\n{}}})",
+                encoded
+            ),
+        };
+        out.push_str(&encode_impl(&name, "Struct", &encoded));
+        let fields = match fields.is_empty() {
+            true => "_thrift_runtime::Fields::new(value)?;\n",
+            false => "let mut fields = _thrift_runtime::Fields::new(value)?;\n",
+        };
+        let body = format!(
+            "{}{}Err(_thrift_runtime::DecodeError::new(\"no field of union {} is set\"))",
+            fields, decoded, symbol.name
+        );
+        out.push_str(&decode_impl(&name, &body));
+        out
+    }
+
+    fn service(&mut self, service: &Service) -> String {
+        let functions = match self
+            .resolver
+            .service_functions(self.workspace, self.program, service)
+        {
+            Ok(functions) => functions,
+            Err(e) => {
+                let span = match &e {
+                    ServiceError::Duplicate { span, .. } => *span,
+                    _ => service.span,
+                };
+                let diagnostic = Diagnostic::error(span, e.to_string());
+                self.diagnostics
+                    .push(diagnostic.with_path(&self.program.path));
+                return String::new();
+            }
+        };
+        let symbol = self.symbol(SymbolKind::Service, &service.name);
+        let name = type_name(&service.name);
+        let mut out = String::new();
+        for function in &service.functions {
+            out.push_str(&self.arguments(&name, function));
+            out.push('\n');
+            if let Some(exceptions) = function.exceptions.as_ref().filter(|e| !e.is_empty()) {
+                out.push_str(&self.errors(&name, function, exceptions));
+                out.push('\n');
+            }
+            if !function.oneway {
+                out.push_str(&self.result(&name, &symbol, function));
+                out.push('\n');
+            }
+        }
+
+        let supertrait = match &service.extension {
+            Some(extension) => match self.resolver.resolve_service(self.program, extension) {
+                Ok(parent) => self.path_to(&parent),
+                Err(_) => return String::new(),
+            },
+            None => "Send + Sync".into(),
+        };
+        out.push_str(&doc(&service.doc, ""));
+        let _ = writeln!(out, "pub trait {}: {} {{", name, supertrait);
+        for (i, function) in service.functions.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            for parameter in &function.parameters {
+                self.canonical(&parameter.type_, parameter.span);
+            }
+            if let Some(returns) = &function.returns {
+                self.canonical(returns, function.span);
+            }
+            out.push_str(&doc(&function.doc, "    "));
+            let _ = writeln!(
+                out,
+                "    fn {}(&self{}) -> impl ::std::future::Future<Output = {}> + Send;",
+                field_name(&function.name),
+                self.parameters(self.program, function),
+                self.output(self.program, &symbol, function)
+            );
+        }
+        out.push_str("}\n\n");
+
+        let client = format!("{}Client", name);
+        let _ = writeln!(out, "/// A client of {} calling it with a transport.", name);
+        let _ = writeln!(
+            out,
+            "pub struct {}<T> {{\n    pub transport: T,\n}}\n",
+            client
+        );
+        let _ = writeln!(out, "impl<T: _thrift_runtime::Transport> {}<T> {{", client);
+        out.push_str("    pub fn new(transport: T) -> Self {\n        Self { transport }\n    }\n");
+        for ServiceFunction { function, origin } in functions {
+            let program = match self.workspace.get(&origin.path) {
+                Some(program) => program,
+                None => continue,
+            };
+            let service_name = type_name(&origin.name);
+            let arguments: Vec<_> = function
+                .parameters
+                .iter()
+                .map(|p| field_name(&p.name))
+                .collect();
+            let arguments = match arguments.is_empty() {
+                true => String::new(),
+                false => format!(" {} ", arguments.join(", ")),
+            };
+            let arguments = format!(
+                "{} {{{}}}",
+                self.path_in(
+                    &origin.path,
+                    format!("{}{}Args", service_name, type_name(&function.name))
+                ),
+                arguments
+            );
+            let (call, output) = match function.oneway {
+                true => ("send".to_string(), "()".to_string()),
+                false => {
+                    let result = self.path_in(
+                        &origin.path,
+                        format!("{}{}Result", service_name, type_name(&function.name)),
+                    );
+                    let call = format!("call::<_, {}>", result);
+                    (call, self.output(program, &origin, function))
+                }
+            };
+            out.push('\n');
+            out.push_str(&doc(&function.doc, "    "));
+            let _ = writeln!(
+                out,
+                "    pub async fn {}(&self{}) -> ::std::result::Result<{}, T::Error> {{",
+                field_name(&function.name),
+                self.parameters(program, function),
+                output
+            );
+            let _ = writeln!(
+                out,
+                "        self.transport\n            .{}({:?}, {:?}, {})\n            .await",
+                call,
+                origin.name,
+                function.name.as_str(),
+                arguments
+            );
+            if !function.oneway {
+                out.push_str("            .map(|result| result.0)\n");
+            }
+            out.push_str("    }\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // The struct of the arguments of a function of a service, which calls encode.
+    fn arguments(&mut self, service: &str, function: &Function) -> String {
+        let name = format!("{}{}Args", service, type_name(&function.name));
+        let mut out = format!(
+            "/// The arguments of {}.{}.\n#[derive(Debug, Clone, PartialEq)]\n",
+            service, function.name
+        );
+        if function.parameters.is_empty() {
+            let _ = writeln!(out, "pub struct {} {{}}", name);
+        } else {
+            let _ = writeln!(out, "pub struct {} {{", name);
+            for parameter in &function.parameters {
+                let mut type_ = self.rust_type(self.program, &parameter.type_);
+                if parameter.required == Some(false) {
+                    type_ = format!("Option<{}>", type_);
+                }
+                out.push_str(&doc(&parameter.doc, "    "));
+                let _ = writeln!(out, "    pub {}: {},", field_name(&parameter.name), type_);
+            }
+            out.push_str("}\n");
+        }
+        out.push_str(&encode_impl(
+            &name,
+            "Struct",
+            &encode_fields(&function.parameters),
+        ));
+        out
+    }
+
+    // What a call of a function of a service returns, which calls decode from the result
+    // struct: field 0 is the returned value, the others are the exceptions.
+    fn result(&mut self, service: &str, symbol: &Symbol, function: &Function) -> String {
+        let name = format!("{}{}Result", service, type_name(&function.name));
+        let mut out = format!(
+            "/// The result of {}.{}.\n#[derive(Debug, Clone, PartialEq)]\n",
+            service, function.name
+        );
+        let _ = writeln!(
+            out,
+            "pub struct {}(pub {});",
+            name,
+            self.output(self.program, symbol, function)
+        );
+
+        let exceptions = function.exceptions.as_deref().unwrap_or_default();
+        let result_name = format!("{}_result", function.name);
+        let body = match (&function.returns, exceptions.is_empty()) {
+            (None, true) => "_thrift_runtime::Fields::new(value).map(|_| Self(()))".to_string(),
+            (Some(_), true) => format!(
+                "let mut fields = _thrift_runtime::Fields::new(value)?;\nOk(Self(fields.required(0, {:?})?))",
+                result_name
+            ),
+            (returns, false) => {
+                let error = format!("{}{}Error", service, type_name(&function.name));
+                let mut body = "let mut fields = _thrift_runtime::Fields::new(value)?;\n".to_string();
+                if returns.is_some() {
+                    body.push_str("if let Some(value) = fields.take(0)? {\n");
+                    body.push_str("    return Ok(Self(Ok(value)));\n}\n");
+                }
+                for (exception, id) in exceptions.iter().zip(field_ids(exceptions)) {
+                    let _ = writeln!(
+                        body,
+                        "if let Some(e) = fields.take({})? {{\n    return Ok(Self(Err({}::{}(e))));\n}}",
+                        id,
+                        error,
+                        variant_name(&exception.name)
+                    );
+                }
+                match returns {
+                    Some(_) => {
+                        let _ = write!(
+                            body,
+                            "Err(_thrift_runtime::DecodeError::new(\"no field of {} is set\"))",
+                            result_name
+                        );
+                    }
+                    None => body.push_str("Ok(Self(Ok(())))"),
+                }
+                body
+            }
+        };
+        out.push_str(&decode_impl(&name, &body));
+        out
+    }
+
+    // The error enum of a function throwing `exceptions`.
+    fn errors(&mut self, service: &str, function: &Function, exceptions: &[Field]) -> String {
+        let name = format!("{}{}Error", service, type_name(&function.name));
+        let mut out = format!(
+            "/// The exceptions of {}.{}.\n#[derive(Debug, Clone, PartialEq)]\n",
+            service, function.name
+        );
+        let _ = writeln!(out, "pub enum {} {{", name);
+        let mut types = Vec::new();
+        for exception in exceptions {
+            self.canonical(&exception.type_, exception.span);
+            let type_ = self.rust_type(self.program, &exception.type_);
+            out.push_str(&doc(&exception.doc, "    "));
+            let _ = writeln!(out, "    {}({}),", variant_name(&exception.name), type_);
+            types.push((variant_name(&exception.name), type_));
+        }
+        out.push_str("}\n\n");
+
+        let _ = writeln!(out, "impl ::std::fmt::Display for {} {{", name);
+        out.push_str(
+            "    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {\n",
+        );
+        out.push_str("        write!(f, \"{:?}\", self)\n    }\n}\n");
+        let _ = writeln!(out, "\nimpl ::std::error::Error for {} {{}}", name);
+
+        // Exceptions of the same type cannot be told apart.
+        for (variant, type_) in &types {
+            if types.iter().filter(|(_, t)| t == type_).count() > 1 {
+                continue;
+            }
+            let _ = writeln!(out, "\nimpl From<{}> for {} {{", type_, name);
+            let _ = writeln!(out, "    fn from(e: {}) -> Self {{", type_);
+            let _ = writeln!(out, "        {}::{}(e)\n    }}\n}}", name, variant);
+        }
+        out
+    }
+
+    // The parameters of a function after `&self`.
+    fn parameters(&self, program: &Program, function: &Function) -> String {
+        let mut out = String::new();
+        for parameter in &function.parameters {
+            let mut type_ = self.rust_type(program, &parameter.type_);
+            if parameter.required == Some(false) {
+                type_ = format!("Option<{}>", type_);
+            }
+            let _ = write!(out, ", {}: {}", field_name(&parameter.name), type_);
+        }
+        out
+    }
+
+    // What a function of `service`, which is defined in `program`, returns.
+    fn output(&self, program: &Program, service: &Symbol, function: &Function) -> String {
+        if function.oneway {
+            return "()".into();
+        }
+        let returns = match &function.returns {
+            Some(returns) => self.rust_type(program, returns),
+            None => "()".into(),
+        };
+        match &function.exceptions {
+            Some(exceptions) if !exceptions.is_empty() => {
+                let error = format!(
+                    "{}{}Error",
+                    type_name(&service.name),
+                    type_name(&function.name)
+                );
+                format!(
+                    "::std::result::Result<{}, {}>",
+                    returns,
+                    self.path_in(&service.path, error)
+                )
+            }
+            _ => returns,
+        }
+    }
+
+    fn symbol(&self, kind: SymbolKind, name: &str) -> Symbol {
+        Symbol {
+            path: self.program.path.clone(),
+            kind,
+            name: name.into(),
+        }
+    }
+
+    // Types without doubles in them are totally ordered and hashable.
+    fn derives(&self, symbol: &Symbol) -> String {
+        let type_ = CanonicalType::Named(symbol.clone());
+        match self.orderable(&type_, &mut HashSet::new()) {
+            true => "#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]\n".into(),
+            false => "#[derive(Debug, Clone, PartialEq, PartialOrd)]\n".into(),
+        }
+    }
+
+    fn orderable(&self, type_: &CanonicalType, seen: &mut HashSet<Symbol>) -> bool {
+        match type_ {
+            CanonicalType::Double => false,
+            CanonicalType::Map(k, v) => self.orderable(k, seen) && self.orderable(v, seen),
+            CanonicalType::Set(x) | CanonicalType::List(x) => self.orderable(x, seen),
+            CanonicalType::Named(symbol) => match self.fields_of(symbol) {
+                // A type being checked is assumed to be orderable.
+                Some(_) if !seen.insert(symbol.clone()) => true,
+                Some((program, fields)) => fields.iter().all(|f| {
+                    self.resolver
+                        .canonical_type(self.workspace, program, &f.type_)
+                        .map_or(true, |t| self.orderable(&t, seen))
+                }),
+                None => true,
+            },
+            _ => true,
+        }
+    }
+
+    // Whether the elements of sets and keys of maps of `type_`, used in `program`, can be kept in
+    // a `BTreeSet` or `BTreeMap`. The others are kept in a `ListSet` or `ListMap`.
+    fn ordered(&self, program: &Program, type_: &FieldType) -> bool {
+        self.resolver
+            .canonical_type(self.workspace, program, type_)
+            .map_or(true, |t| self.orderable(&t, &mut HashSet::new()))
+    }
+
+    // Whether a value of `type_` stored in `owner` would contain `owner`, and needs a box.
+    pub(crate) fn recursive(&self, owner: &Symbol, type_: &CanonicalType) -> bool {
+        match type_ {
+            CanonicalType::Named(symbol) => self.reaches(symbol, owner, &mut HashSet::new()),
+            _ => false,
+        }
+    }
+
+    fn reaches(&self, from: &Symbol, target: &Symbol, seen: &mut HashSet<Symbol>) -> bool {
+        if from == target {
+            return true;
+        }
+        if !seen.insert(from.clone()) {
+            return false;
+        }
+        let (program, fields) = match self.fields_of(from) {
+            Some(found) => found,
+            None => return false,
+        };
+        fields.iter().any(|f| {
+            match self
+                .resolver
+                .canonical_type(self.workspace, program, &f.type_)
+            {
+                Ok(CanonicalType::Named(symbol)) => self.reaches(&symbol, target, seen),
+                _ => false,
+            }
+        })
+    }
+
+    // The fields of a struct, union or exception, with the file defining it.
+    pub(crate) fn fields_of(&self, symbol: &Symbol) -> Option<(&'w Program, &'w [Field])> {
+        let program = self.workspace.get(&symbol.path)?;
+        match symbol.definition(self.workspace)? {
+            Definition::Struct(x) => Some((program, &x.fields)),
+            Definition::Union(x) => Some((program, &x.fields)),
+            Definition::Exception(x) => Some((program, &x.fields)),
+            _ => None,
+        }
+    }
+
+    // The canonical form of a type used in the program, reporting errors.
+    pub(crate) fn canonical(&mut self, type_: &FieldType, span: Span) -> Option<CanonicalType> {
+        match self
+            .resolver
+            .canonical_type(self.workspace, self.program, type_)
+        {
+            Ok(canonical) => Some(canonical),
+            Err(e) => {
+                let diagnostic = Diagnostic::error(span, e.to_string());
+                self.diagnostics
+                    .push(diagnostic.with_path(&self.program.path));
+                None
+            }
+        }
+    }
+
+    // The Rust type of a type used in `program`, relative to the module of the program the code
+    // is generated for. Typedefs are kept.
+    pub(crate) fn rust_type(&self, program: &Program, type_: &FieldType) -> String {
+        match type_ {
+            FieldType::Identifier(name) => match self.resolver.resolve_type(program, name) {
+                Ok(symbol) => self.path_to(&symbol),
+                // Reported by `canonical`.
+                Err(error) => TypeError::Resolve {
+                    name: name.to_string(),
+                    error,
+                }
+                .to_string(),
+            },
+            FieldType::Bool => "bool".into(),
+            FieldType::Byte | FieldType::I8 => "i8".into(),
+            FieldType::I16 => "i16".into(),
+            FieldType::I32 => "i32".into(),
+            FieldType::I64 => "i64".into(),
+            FieldType::Double => "f64".into(),
+            FieldType::String => "String".into(),
+            FieldType::Binary => "Vec<u8>".into(),
+            FieldType::Map(k, v, _) => format!(
+                "{}<{}, {}>",
+                match self.ordered(program, k) {
+                    true => "::std::collections::BTreeMap",
+                    false => "_thrift_runtime::ListMap",
+                },
+                self.rust_type(program, k),
+                self.rust_type(program, v)
+            ),
+            FieldType::Set(x, _) => format!(
+                "{}<{}>",
+                match self.ordered(program, x) {
+                    true => "::std::collections::BTreeSet",
+                    false => "_thrift_runtime::ListSet",
+                },
+                self.rust_type(program, x)
+            ),
+            FieldType::List(x, _) => format!("Vec<{}>", self.rust_type(program, x)),
+        }
+    }
+
+    // The path to a type, relative to the module of the program.
+    pub(crate) fn path_to(&self, symbol: &Symbol) -> String {
+        self.path_in(&symbol.path, type_name(&symbol.name))
+    }
+
+    // The path to an item named `name` generated for the file at `path`.
+    pub(crate) fn path_in(&self, path: &Path, name: String) -> String {
+        let target = match self.workspace.get(path) {
+            Some(program) if program.path != self.program.path => module_path(program),
+            _ => return name,
+        };
+        let common = self
+            .module
+            .iter()
+            .zip(&target)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut path = vec!["super".to_string(); self.module.len() - common];
+        path.extend(target[common..].iter().cloned());
+        path.push(name);
+        path.join("::")
+    }
+
+    // A Rust expression of a value of a type.
+    pub(crate) fn value(&self, type_: &CanonicalType, value: &ResolvedConstValue) -> String {
+        match (type_, value) {
+            (_, ResolvedConstValue::Bool(v)) => v.to_string(),
+            (_, ResolvedConstValue::Byte(v)) => v.to_string(),
+            (_, ResolvedConstValue::I16(v)) => v.to_string(),
+            (_, ResolvedConstValue::I32(v)) => v.to_string(),
+            (_, ResolvedConstValue::I64(v)) => v.to_string(),
+            (_, ResolvedConstValue::Double(v)) if v.is_nan() => "f64::NAN".into(),
+            (_, ResolvedConstValue::Double(v)) if v.is_infinite() => match *v > 0.0 {
+                true => "f64::INFINITY".into(),
+                false => "f64::NEG_INFINITY".into(),
+            },
+            (_, ResolvedConstValue::Double(v)) => format!("{:?}", v),
+            (_, ResolvedConstValue::String(v)) => format!("{:?}.to_string()", v),
+            (_, ResolvedConstValue::Binary(v)) => format!("vec!{:?}", v),
+            (CanonicalType::Named(symbol), ResolvedConstValue::Enum { name, .. }) => {
+                format!("{}::{}", self.path_to(symbol), variant_name(name))
+            }
+            (CanonicalType::List(x), ResolvedConstValue::List(items)) => {
+                format!("vec![{}]", self.values(x, items))
+            }
+            (CanonicalType::Set(x), ResolvedConstValue::Set(items))
+                if !self.orderable(x, &mut HashSet::new()) =>
+            {
+                format!("_thrift_runtime::ListSet(vec![{}])", self.values(x, items))
+            }
+            (CanonicalType::Set(_), ResolvedConstValue::Set(items)) if items.is_empty() => {
+                "::std::collections::BTreeSet::new()".into()
+            }
+            (CanonicalType::Set(x), ResolvedConstValue::Set(items)) => {
+                format!("vec![{}].into_iter().collect()", self.values(x, items))
+            }
+            (CanonicalType::Map(k, _), ResolvedConstValue::Map(items))
+                if items.is_empty() && self.orderable(k, &mut HashSet::new()) =>
+            {
+                "::std::collections::BTreeMap::new()".into()
+            }
+            (CanonicalType::Map(k, v), ResolvedConstValue::Map(items)) => {
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("({}, {})", self.value(k, key), self.value(v, value))
+                    })
+                    .collect();
+                match self.orderable(k, &mut HashSet::new()) {
+                    true => format!("vec![{}].into_iter().collect()", items.join(", ")),
+                    false => format!("_thrift_runtime::ListMap(vec![{}])", items.join(", ")),
+                }
+            }
+            (CanonicalType::Named(symbol), ResolvedConstValue::Struct(items)) => {
+                self.struct_value(symbol, items)
+            }
+            _ => "Default::default()".into(),
+        }
+    }
+
+    fn values(&self, type_: &CanonicalType, items: &[ResolvedConstValue]) -> String {
+        let items: Vec<_> = items.iter().map(|v| self.value(type_, v)).collect();
+        items.join(", ")
+    }
+
+    fn struct_value(&self, symbol: &Symbol, items: &[(String, ResolvedConstValue)]) -> String {
+        let (program, fields) = match self.fields_of(symbol) {
+            Some(found) => found,
+            None => return "Default::default()".into(),
+        };
+        let mut values = Vec::new();
+        for (name, value) in items {
+            let field = match fields.iter().find(|f| f.name.as_str() == name) {
+                Some(field) => field,
+                None => continue,
+            };
+            let type_ = match self
+                .resolver
+                .canonical_type(self.workspace, program, &field.type_)
+            {
+                Ok(type_) => type_,
+                Err(_) => continue,
+            };
+            let boxed = self.recursive(symbol, &type_);
+            let optional = field.required == Some(false);
+            values.push((name, self.value(&type_, value), boxed, optional));
+        }
+
+        let path = self.path_to(symbol);
+        if symbol.kind == SymbolKind::Union {
+            return match values.into_iter().next() {
+                Some((name, value, boxed, _)) => {
+                    format!(
+                        "{}::{}({})",
+                        path,
+                        variant_name(name),
+                        wrap(value, boxed, false)
+                    )
+                }
+                None => "Default::default()".into(),
+            };
+        }
+        let complete = values.len() == fields.len();
+        let mut values: Vec<_> = values
+            .into_iter()
+            .map(|(name, value, boxed, optional)| {
+                format!("{}: {}", field_name(name), wrap(value, boxed, optional))
+            })
+            .collect();
+        if !complete {
+            values.push("..Default::default()".into());
+        }
+        format!("{} {{ {} }}", path, values.join(", "))
+    }
+}
+
+// The `Encode` impl of `name`, which is encoded by `body` as a value of `type_`.
+fn encode_impl(name: &str, type_: &str, body: &str) -> String {
+    let mut out = format!("\nimpl _thrift_runtime::Encode for {} {{\n", name);
+    let _ = writeln!(
+        out,
+        "    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::{};\n",
+        type_
+    );
+    out.push_str("    fn encode(&self) -> _thrift_runtime::Value {\n");
+    out.push_str(&indent(body, "        "));
+    out.push_str("    }\n}\n");
+    out
+}
+
+// The `Decode` impl of `name`, which is decoded from `value` by `body`.
+fn decode_impl(name: &str, body: &str) -> String {
+    let mut out = format!("\nimpl _thrift_runtime::Decode for {} {{\n", name);
+    out.push_str(
+        "    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {\n",
+    );
+    out.push_str(&indent(body, "        "));
+    out.push_str("    }\n}\n");
+    out
+}
+
+// Encode the fields of `self`, leaving out the optional ones that are not set.
+fn encode_fields(fields: &[Field]) -> String {
+    if fields.is_empty() {
+        return "_thrift_runtime::Value::Struct(Vec::new())".into();
+    }
+    let optional = fields.iter().any(|f| f.required == Some(false));
+    let mut out = String::new();
+    for (field, id) in fields.iter().zip(field_ids(fields)) {
+        let name = field_name(&field.name);
+        let _ = match (optional, field.required) {
+            (false, _) => writeln!(
+                out,
+                "    ({}, _thrift_runtime::Encode::encode(&self.{})),",
+                id, name
+            ),
+            (true, Some(false)) => writeln!(
+                out,
+                "    self.{}.as_ref().map(|value| ({}, _thrift_runtime::Encode::encode(value))),",
+                name, id
+            ),
+            (true, _) => writeln!(
+                out,
+                "    Some(({}, _thrift_runtime::Encode::encode(&self.{}))),",
+                id, name
+            ),
+        };
+    }
+    match optional {
+        true => format!(
+            "let fields = vec![\n{}];\n_thrift_runtime::Value::Struct(fields.into_iter().flatten().collect())",
+            out
+        ),
+        false => format!("_thrift_runtime::Value::Struct(vec![\n{}])", out),
+    }
+}
+
+// Indent every line of `code`, and end it with a newline.
+fn indent(code: &str, indent: &str) -> String {
+    let mut out = String::new();
+    for line in code.lines() {
+        match line.is_empty() {
+            true => out.push('\n'),
+            false => {
+                let _ = writeln!(out, "{}{}", indent, line);
+            }
+        }
+    }
+    out
+}
+
+fn wrap(value: String, boxed: bool, optional: bool) -> String {
+    let value = match boxed {
+        true => format!("Box::new({})", value),
+        false => value,
+    };
+    match optional {
+        true => format!("Some({})", value),
+        false => value,
+    }
+}
+
+fn doc(doc: &Option<Doc>, indent: &str) -> String {
+    let mut out = String::new();
+    if let Some(doc) = doc {
+        for line in doc.text().lines() {
+            match line.is_empty() {
+                true => out.push_str(&format!("{}///\n", indent)),
+                false => out.push_str(&format!("{}/// {}\n", indent, line)),
+            }
+        }
+    }
+    out
+}
+
+// Split a name into words at underscores and case changes, e.g. `HTTPServer_v2` is `HTTP`,
+// `Server` and `v2`.
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '.' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_lower)
+            {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn camel(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().map(|c| c.to_uppercase().collect::<String>());
+            first.unwrap_or_default() + &chars.as_str().to_lowercase()
+        })
+        .collect()
+}
+
+fn snake(name: &str) -> String {
+    let words: Vec<_> = words(name).iter().map(|w| w.to_lowercase()).collect();
+    words.join("_")
+}
+
+pub(crate) fn type_name(name: &str) -> String {
+    escape(camel(name))
+}
+
+pub(crate) fn variant_name(name: &str) -> String {
+    escape(camel(name))
+}
+
+pub(crate) fn field_name(name: &str) -> String {
+    escape(snake(name))
+}
+
+fn const_name(name: &str) -> String {
+    escape(snake(name).to_uppercase())
+}
+
+fn module_name(name: &str) -> String {
+    escape(snake(name))
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+// Make a name usable as an identifier.
+fn escape(name: String) -> String {
+    match name.as_str() {
+        "self" | "Self" | "super" | "crate" | "" => name + "_",
+        _ if KEYWORDS.contains(&name.as_str()) => format!("r#{}", name),
+        _ if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", name),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "thrift", "workspace", name]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(words("HTTPServer_v2"), vec!["HTTP", "Server", "v2"]);
+        assert_eq!(type_name("user_info"), "UserInfo");
+        assert_eq!(variant_name("STATUS_OK"), "StatusOk");
+        assert_eq!(field_name("userID"), "user_id");
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(const_name("maxCount"), "MAX_COUNT");
+    }
+
+    #[test]
+    fn test_generate() {
+        let source = r#"
+include "shared.thrift"
+namespace rs demo.types
+
+/** Kinds of users. */
+enum Kind { PERSON, BOT = 5 }
+typedef i64 Id
+const Kind DEFAULT_KIND = Kind.BOT
+const string GREETING = "hi"
+const list<Id> IDS = [1, 2]
+const User ROOT = {"id": 0, "kind": Kind.BOT, "parent": {"id": 1}}
+
+struct User {
+    1: required Id id
+    /// Unset for bots.
+    2: optional Kind kind = Kind.PERSON
+    3: string nick = "x"
+    4: optional User parent
+    5: map<string, set<i32>> tags
+    6: shared.Shared shared
+}
+union Either { 1: User user, 2: double score }
+exception NotFound { 1: string message }
+"#;
+        let workspace =
+            Workspace::from_source(fixture("demo.thrift"), source, &[fixture("lib")]).unwrap();
+        let program = workspace.root();
+        assert_eq!(module_path(program), vec!["demo", "types"]);
+        assert_eq!(
+            module_path(workspace.included(program, "shared").unwrap()),
+            vec!["shared"]
+        );
+        let code = generate(&workspace, program).unwrap();
+        assert_eq!(
+            code,
+            r#"// Generated by thrift-parser from demo.thrift, do not edit.
+
+use super::super::_thrift_runtime;
+
+/// Kinds of users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Kind {
+    Person = 0,
+    Bot = 5,
+}
+
+impl ::std::convert::TryFrom<i32> for Kind {
+    type Error = i32;
+
+    fn try_from(value: i32) -> ::std::result::Result<Self, i32> {
+        match value {
+            0 => Ok(Kind::Person),
+            5 => Ok(Kind::Bot),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<Kind> for i32 {
+    fn from(value: Kind) -> i32 {
+        value as i32
+    }
+}
+
+impl Default for Kind {
+    fn default() -> Self {
+        Kind::Person
+    }
+}
+
+impl _thrift_runtime::Encode for Kind {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::I32;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        _thrift_runtime::Value::I32(i32::from(*self))
+    }
+}
+
+impl _thrift_runtime::Decode for Kind {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let value: i32 = _thrift_runtime::Decode::decode(value)?;
+        ::std::convert::TryFrom::try_from(value).map_err(|value| {
+            _thrift_runtime::DecodeError::new(format!("unknown value {} of enum Kind", value))
+        })
+    }
+}
+
+pub type Id = i64;
+
+pub const DEFAULT_KIND: Kind = Kind::Bot;
+
+pub const GREETING: &str = "hi";
+
+pub fn ids() -> Vec<Id> {
+    vec![1, 2]
+}
+
+pub fn root() -> User {
+    User { id: 0, kind: Some(Kind::Bot), parent: Some(Box::new(User { id: 1, ..Default::default() })), ..Default::default() }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct User {
+    pub id: Id,
+    /// Unset for bots.
+    pub kind: Option<Kind>,
+    pub nick: String,
+    pub parent: Option<Box<User>>,
+    pub tags: ::std::collections::BTreeMap<String, ::std::collections::BTreeSet<i32>>,
+    pub shared: super::super::shared::Shared,
+}
+
+impl Default for User {
+    fn default() -> Self {
+        Self {
+            id: Default::default(),
+            kind: Some(Kind::Person),
+            nick: "x".to_string(),
+            parent: None,
+            tags: Default::default(),
+            shared: Default::default(),
+        }
+    }
+}
+
+impl _thrift_runtime::Encode for User {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        let fields = vec![
+            Some((1, _thrift_runtime::Encode::encode(&self.id))),
+            self.kind.as_ref().map(|value| (2, _thrift_runtime::Encode::encode(value))),
+            Some((3, _thrift_runtime::Encode::encode(&self.nick))),
+            self.parent.as_ref().map(|value| (4, _thrift_runtime::Encode::encode(value))),
+            Some((5, _thrift_runtime::Encode::encode(&self.tags))),
+            Some((6, _thrift_runtime::Encode::encode(&self.shared))),
+        ];
+        _thrift_runtime::Value::Struct(fields.into_iter().flatten().collect())
+    }
+}
+
+impl _thrift_runtime::Decode for User {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let mut fields = _thrift_runtime::Fields::new(value)?;
+        let default = Self::default();
+        Ok(Self {
+            id: fields.required(1, "User")?,
+            kind: fields.take(2)?,
+            nick: fields.take(3)?.unwrap_or(default.nick),
+            parent: fields.take(4)?,
+            tags: fields.take(5)?.unwrap_or(default.tags),
+            shared: fields.take(6)?.unwrap_or(default.shared),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Either {
+    User(User),
+    Score(f64),
+}
+
+impl Default for Either {
+    fn default() -> Self {
+        Either::User(Default::default())
+    }
+}
+
+impl _thrift_runtime::Encode for Either {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        _thrift_runtime::Value::Struct(match self {
+            Either::User(value) => vec![(1, _thrift_runtime::Encode::encode(value))],
+            Either::Score(value) => vec![(2, _thrift_runtime::Encode::encode(value))],
+        })
+    }
+}
+
+impl _thrift_runtime::Decode for Either {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let mut fields = _thrift_runtime::Fields::new(value)?;
+        if let Some(value) = fields.take(1)? {
+            return Ok(Either::User(value));
+        }
+        if let Some(value) = fields.take(2)? {
+            return Ok(Either::Score(value));
+        }
+        Err(_thrift_runtime::DecodeError::new("no field of union Either is set"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NotFound {
+    pub message: String,
+}
+
+impl Default for NotFound {
+    fn default() -> Self {
+        Self {
+            message: Default::default(),
+        }
+    }
+}
+
+impl _thrift_runtime::Encode for NotFound {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        _thrift_runtime::Value::Struct(vec![
+            (1, _thrift_runtime::Encode::encode(&self.message)),
+        ])
+    }
+}
+
+impl _thrift_runtime::Decode for NotFound {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let mut fields = _thrift_runtime::Fields::new(value)?;
+        let default = Self::default();
+        Ok(Self {
+            message: fields.take(1)?.unwrap_or(default.message),
+        })
+    }
+}
+
+impl ::std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ::std::error::Error for NotFound {}
+"#
+        );
+
+        let source = "union Node { 1: i32 leaf, 2: Node node }";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let code = generate(&workspace, workspace.root()).unwrap();
+        assert!(code.contains("    Node(Box<Node>),\n"));
+
+        let source = "const double BIG = 1e400\nconst double LOW = -1e400";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let code = generate(&workspace, workspace.root()).unwrap();
+        assert!(code.contains("pub const BIG: f64 = f64::INFINITY;\n"));
+        assert!(code.contains("pub const LOW: f64 = f64::NEG_INFINITY;\n"));
+    }
+
+    #[test]
+    fn test_generate_services() {
+        let source = r#"
+include "shared.thrift"
+
+exception NotFound { 1: string message }
+
+/** Users of the system. */
+service UserService extends shared.SharedService {
+    /** Look a user up. */
+    string get(1: i64 id, 2: optional bool cached) throws (1: NotFound missing)
+    oneway void forget(1: i64 id)
+}
+"#;
+        let workspace =
+            Workspace::from_source(fixture("demo.thrift"), source, &[fixture("lib")]).unwrap();
+        let code = generate(&workspace, workspace.root()).unwrap();
+        assert_eq!(
+            code,
+            r#"// Generated by thrift-parser from demo.thrift, do not edit.
+
+use super::_thrift_runtime;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NotFound {
+    pub message: String,
+}
+
+impl Default for NotFound {
+    fn default() -> Self {
+        Self {
+            message: Default::default(),
+        }
+    }
+}
+
+impl _thrift_runtime::Encode for NotFound {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        _thrift_runtime::Value::Struct(vec![
+            (1, _thrift_runtime::Encode::encode(&self.message)),
+        ])
+    }
+}
+
+impl _thrift_runtime::Decode for NotFound {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let mut fields = _thrift_runtime::Fields::new(value)?;
+        let default = Self::default();
+        Ok(Self {
+            message: fields.take(1)?.unwrap_or(default.message),
+        })
+    }
+}
+
+impl ::std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ::std::error::Error for NotFound {}
+
+/// The arguments of UserService.get.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserServiceGetArgs {
+    pub id: i64,
+    pub cached: Option<bool>,
+}
+
+impl _thrift_runtime::Encode for UserServiceGetArgs {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        let fields = vec![
+            Some((1, _thrift_runtime::Encode::encode(&self.id))),
+            self.cached.as_ref().map(|value| (2, _thrift_runtime::Encode::encode(value))),
+        ];
+        _thrift_runtime::Value::Struct(fields.into_iter().flatten().collect())
+    }
+}
+
+/// The exceptions of UserService.get.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserServiceGetError {
+    Missing(NotFound),
+}
+
+impl ::std::fmt::Display for UserServiceGetError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ::std::error::Error for UserServiceGetError {}
+
+impl From<NotFound> for UserServiceGetError {
+    fn from(e: NotFound) -> Self {
+        UserServiceGetError::Missing(e)
+    }
+}
+
+/// The result of UserService.get.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserServiceGetResult(pub ::std::result::Result<String, UserServiceGetError>);
+
+impl _thrift_runtime::Decode for UserServiceGetResult {
+    fn decode(value: _thrift_runtime::Value) -> ::std::result::Result<Self, _thrift_runtime::DecodeError> {
+        let mut fields = _thrift_runtime::Fields::new(value)?;
+        if let Some(value) = fields.take(0)? {
+            return Ok(Self(Ok(value)));
+        }
+        if let Some(e) = fields.take(1)? {
+            return Ok(Self(Err(UserServiceGetError::Missing(e))));
+        }
+        Err(_thrift_runtime::DecodeError::new("no field of get_result is set"))
+    }
+}
+
+/// The arguments of UserService.forget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserServiceForgetArgs {
+    pub id: i64,
+}
+
+impl _thrift_runtime::Encode for UserServiceForgetArgs {
+    const TYPE: _thrift_runtime::Type = _thrift_runtime::Type::Struct;
+
+    fn encode(&self) -> _thrift_runtime::Value {
+        _thrift_runtime::Value::Struct(vec![
+            (1, _thrift_runtime::Encode::encode(&self.id)),
+        ])
+    }
+}
+
+/// Users of the system.
+pub trait UserService: super::shared::SharedService {
+    /// Look a user up.
+    fn get(&self, id: i64, cached: Option<bool>) -> impl ::std::future::Future<Output = ::std::result::Result<String, UserServiceGetError>> + Send;
+
+    fn forget(&self, id: i64) -> impl ::std::future::Future<Output = ()> + Send;
+}
+
+/// A client of UserService calling it with a transport.
+pub struct UserServiceClient<T> {
+    pub transport: T,
+}
+
+impl<T: _thrift_runtime::Transport> UserServiceClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub async fn ping(&self) -> ::std::result::Result<(), T::Error> {
+        self.transport
+            .call::<_, super::shared::SharedServicePingResult>("SharedService", "ping", super::shared::SharedServicePingArgs {})
+            .await
+            .map(|result| result.0)
+    }
+
+    /// Look a user up.
+    pub async fn get(&self, id: i64, cached: Option<bool>) -> ::std::result::Result<::std::result::Result<String, UserServiceGetError>, T::Error> {
+        self.transport
+            .call::<_, UserServiceGetResult>("UserService", "get", UserServiceGetArgs { id, cached })
+            .await
+            .map(|result| result.0)
+    }
+
+    pub async fn forget(&self, id: i64) -> ::std::result::Result<(), T::Error> {
+        self.transport
+            .send("UserService", "forget", UserServiceForgetArgs { id })
+            .await
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let source = "struct A { 1: B b, 1: i32 c }";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let errors = generate(&workspace, workspace.root()).unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "field id 1 of `c` in struct A is already used by `b`"
+        );
+
+        let source = "struct A { 1: B b }";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let errors = generate(&workspace, workspace.root()).unwrap_err();
+        assert_eq!(errors[0].message, "cannot find type `B`");
+
+        let source = "enum E {}\nstruct T { 1: E e }";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let errors = generate(&workspace, workspace.root()).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "error: enum E has no values\n --> inline.thrift:1:1"
+        );
+
+        let source = "include \"shared.thrift\"\nnamespace rs shared";
+        let workspace =
+            Workspace::from_source(fixture("demo.thrift"), source, &[fixture("lib")]).unwrap();
+        let errors = generate_workspace(&workspace).unwrap_err();
+        assert!(errors[0]
+            .message
+            .ends_with("demo.thrift are both generated into module `shared`"));
+    }
+}
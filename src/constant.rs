@@ -2,13 +2,13 @@ use std::str::FromStr;
 
 use nom::branch::alt;
 use nom::character::complete::{char as cchar, digit0, digit1};
-use nom::combinator::{map, map_res, opt, recognize};
+use nom::combinator::{cut, map, map_res, opt, recognize};
+use nom::error::context;
 use nom::multi::separated_list0;
-use nom::sequence::{delimited, pair, separated_pair, tuple};
-use nom::IResult;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 
 use crate::basic::{Identifier, IdentifierRef, ListSeparator, Literal, LiteralRef, Separator};
-use crate::Parser;
+use crate::{IResult, Parser};
 
 // ConstValue      ::=  IntConstant | DoubleConstant | Literal | Identifier | ConstList | ConstMap
 #[derive(Debug, Clone, PartialEq)]
@@ -35,6 +35,8 @@ impl<'a> Parser<'a> for ConstValueRef<'a> {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ConstValue {
     Identifier(Identifier),
     Literal(Literal),
@@ -65,6 +67,7 @@ impl<'a> Parser<'a> for ConstValue {
 
 // IntConstant     ::=  ('+' | '-')? Digit+
 #[derive(derive_newtype::NewType, Hash, Eq, PartialEq, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntConstant(i64);
 
 impl<'a> Parser<'a> for IntConstant {
@@ -81,6 +84,7 @@ impl<'a> Parser<'a> for IntConstant {
 
 // DoubleConstant  ::=  ('+' | '-')? Digit* ('.' Digit+)? ( ('E' | 'e') IntConstant )?
 #[derive(derive_newtype::NewType, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DoubleConstant(f64);
 
 impl<'a> Parser<'a> for DoubleConstant {
@@ -133,10 +137,15 @@ pub struct ConstListRef<'a>(Vec<ConstValueRef<'a>>);
 impl<'a> Parser<'a> for ConstListRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
         map(
-            delimited(
+            preceded(
                 pair(cchar('['), opt(Separator::parse)),
-                separated_list0(parse_list_separator, ConstValueRef::parse),
-                pair(opt(Separator::parse), cchar(']')),
+                cut(terminated(
+                    separated_list0(parse_list_separator, ConstValueRef::parse),
+                    context(
+                        "expected ']' to close list",
+                        pair(opt(parse_list_separator), cchar(']')),
+                    ),
+                )),
             ),
             Self,
         )(input)
@@ -144,6 +153,7 @@ impl<'a> Parser<'a> for ConstListRef<'a> {
 }
 
 #[derive(derive_newtype::NewType, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstList(Vec<ConstValue>);
 
 impl<'a> From<ConstListRef<'a>> for ConstList {
@@ -165,17 +175,25 @@ pub struct ConstMapRef<'a>(Vec<(ConstValueRef<'a>, ConstValueRef<'a>)>);
 impl<'a> Parser<'a> for ConstMapRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
         map(
-            delimited(
+            preceded(
                 pair(cchar('{'), opt(Separator::parse)),
-                separated_list0(
-                    parse_list_separator,
-                    separated_pair(
-                        ConstValueRef::parse,
-                        delimited(opt(Separator::parse), cchar(':'), opt(Separator::parse)),
-                        ConstValueRef::parse,
+                cut(terminated(
+                    separated_list0(
+                        parse_list_separator,
+                        separated_pair(
+                            ConstValueRef::parse,
+                            context(
+                                "expected ':'",
+                                delimited(opt(Separator::parse), cchar(':'), opt(Separator::parse)),
+                            ),
+                            context("expected map value", ConstValueRef::parse),
+                        ),
+                    ),
+                    context(
+                        "expected '}' to close map",
+                        pair(opt(parse_list_separator), cchar('}')),
                     ),
-                ),
-                pair(opt(Separator::parse), cchar('}')),
+                )),
             ),
             Self,
         )(input)
@@ -183,6 +201,7 @@ impl<'a> Parser<'a> for ConstMapRef<'a> {
 }
 
 #[derive(derive_newtype::NewType, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstMap(Vec<(ConstValue, ConstValue)>);
 
 impl<'a> From<ConstMapRef<'a>> for ConstMap {
@@ -1,17 +1,23 @@
+use std::convert::TryFrom;
+
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::char as cchar;
-use nom::combinator::{map, opt};
-use nom::multi::separated_list0;
-use nom::sequence::{delimited, pair, preceded, tuple};
-use nom::IResult;
+use nom::combinator::{cut, map, opt};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::{pair, preceded, terminated, tuple};
 
-use crate::basic::{Identifier, IdentifierRef, ListSeparator, Separator};
-use crate::constant::{parse_list_separator, ConstValue, ConstValueRef, IntConstant};
+use crate::annotation::{Annotations, AnnotationsRef};
+use crate::basic::{
+    documented, Doc, DocRef, Documented, Identifier, IdentifierRef, ListSeparator, Separator,
+};
+use crate::constant::{ConstValue, ConstValueRef, IntConstant};
 use crate::field::{Field, FieldRef};
 use crate::functions::{Function, FunctionRef};
+use crate::span::{located, LineIndex, Located, Span};
 use crate::types::{FieldType, FieldTypeRef};
-use crate::Parser;
+use crate::{Diagnostic, IResult, Parser};
 
 // Const           ::=  'const' FieldType Identifier '=' ConstValue ListSeparator?
 #[derive(Debug, Clone, PartialEq)]
@@ -19,29 +25,63 @@ pub struct ConstRef<'a> {
     pub name: IdentifierRef<'a>,
     pub type_: FieldTypeRef<'a>,
     pub value: ConstValueRef<'a>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for ConstRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                tag("const"),
-                preceded(Separator::parse, FieldTypeRef::parse),
-                preceded(Separator::parse, IdentifierRef::parse),
-                preceded(opt(Separator::parse), cchar('=')),
-                preceded(opt(Separator::parse), ConstValueRef::parse),
-                opt(pair(opt(Separator::parse), ListSeparator::parse)),
+        terminated(
+            located(map(
+                preceded(
+                    pair(tag("const"), Separator::parse),
+                    cut(tuple((
+                        context("expected constant type", FieldTypeRef::parse),
+                        context(
+                            "expected constant name",
+                            preceded(Separator::parse, IdentifierRef::parse),
+                        ),
+                        context("expected '='", preceded(opt(Separator::parse), cchar('='))),
+                        context(
+                            "expected constant value",
+                            preceded(opt(Separator::parse), ConstValueRef::parse),
+                        ),
+                    ))),
+                ),
+                |(type_, name, _, value)| Self {
+                    name,
+                    type_,
+                    value,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(_, type_, name, _, value, _)| Self { name, type_, value },
+            opt(pair(opt(Separator::parse), ListSeparator::parse)),
         )(input)
     }
 }
 
+impl<'a> Located for ConstRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+}
+
+impl<'a> Documented<'a> for ConstRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Const {
     pub name: Identifier,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: FieldType,
     pub value: ConstValue,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<ConstRef<'a>> for Const {
@@ -50,6 +90,8 @@ impl<'a> From<ConstRef<'a>> for Const {
             name: r.name.into(),
             type_: r.type_.into(),
             value: r.value.into(),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -60,39 +102,63 @@ impl<'a> Parser<'a> for Const {
     }
 }
 
-// Typedef         ::=  'typedef' DefinitionType Identifier
-// DefinitionType  ::=  BaseType | ContainerType
-// BaseType        ::=  'bool' | 'byte' | 'i8' | 'i16' | 'i32' | 'i64' | 'double' | 'string' | 'binary'
-// ContainerType   ::=  MapType | SetType | ListType
+// Typedef         ::=  'typedef' FieldType Identifier TypeAnnotations?
+// Note: The official grammar only allows BaseType and ContainerType, but the Apache compiler
+// accepts any FieldType, e.g. `typedef shared.User SharedUser`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypedefRef<'a> {
     pub old: FieldTypeRef<'a>,
     pub alias: IdentifierRef<'a>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for TypedefRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                tag("typedef"),
-                preceded(
-                    Separator::parse,
-                    alt((
-                        FieldTypeRef::parse_base_type,
-                        FieldTypeRef::parse_container_type,
-                    )),
-                ),
-                preceded(Separator::parse, IdentifierRef::parse),
-            )),
-            |(_, old, alias)| Self { old, alias },
-        )(input)
+        located(map(
+            preceded(
+                pair(tag("typedef"), Separator::parse),
+                cut(tuple((
+                    context("expected type", FieldTypeRef::parse),
+                    context(
+                        "expected typedef name",
+                        preceded(Separator::parse, IdentifierRef::parse),
+                    ),
+                    opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                ))),
+            ),
+            |(old, alias, annotations)| Self {
+                old,
+                alias,
+                annotations,
+                doc: None,
+                span: Span::default(),
+            },
+        ))(input)
+    }
+}
+
+impl<'a> Located for TypedefRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+}
+
+impl<'a> Documented<'a> for TypedefRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Typedef {
     pub old: FieldType,
     pub alias: Identifier,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<TypedefRef<'a>> for Typedef {
@@ -100,6 +166,9 @@ impl<'a> From<TypedefRef<'a>> for Typedef {
         Self {
             old: r.old.into(),
             alias: r.alias.into(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -110,64 +179,163 @@ impl<'a> Parser<'a> for Typedef {
     }
 }
 
-// Enum            ::=  'enum' Identifier '{' (Identifier ('=' IntConstant)? ListSeparator?)* '}'
+// Enum            ::=  'enum' Identifier '{' EnumValue* '}' TypeAnnotations?
+// EnumValue       ::=  Identifier ('=' IntConstant)? TypeAnnotations? ListSeparator?
 #[derive(Debug, Clone, PartialEq)]
 pub struct EnumRef<'a> {
     pub name: IdentifierRef<'a>,
     pub children: Vec<EnumValueRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct EnumValueRef<'a> {
     pub name: IdentifierRef<'a>,
     pub value: Option<IntConstant>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for EnumRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                tag("enum"),
-                preceded(Separator::parse, IdentifierRef::parse),
-                tuple((opt(Separator::parse), cchar('{'), opt(Separator::parse))),
-                separated_list0(parse_list_separator, EnumValueRef::parse),
-                preceded(opt(Separator::parse), cchar('}')),
+        context(
+            "enum",
+            located(map(
+                preceded(
+                    pair(tag("enum"), Separator::parse),
+                    cut(tuple((
+                        context("expected enum name", IdentifierRef::parse),
+                        context("expected '{'", pair(opt(Separator::parse), cchar('{'))),
+                        many0(terminated(
+                            documented(EnumValueRef::parse),
+                            opt(pair(opt(Separator::parse), ListSeparator::parse)),
+                        )),
+                        context(
+                            "expected '}' to close",
+                            pair(opt(Separator::parse), cchar('}')),
+                        ),
+                        opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                    ))),
+                ),
+                |(name, _, children, _, annotations)| Self {
+                    name,
+                    children,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(_, name, _, children, _)| Self { name, children },
         )(input)
     }
 }
 
+impl<'a> Located for EnumRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.children.iter_mut().for_each(|x| x.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for EnumRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 impl<'a> Parser<'a> for EnumValueRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
+        located(map(
             tuple((
                 IdentifierRef::parse,
-                opt(map(
-                    tuple((
-                        opt(Separator::parse),
-                        cchar('='),
-                        opt(Separator::parse),
-                        IntConstant::parse,
-                    )),
-                    |(_, _, _, i)| (i),
+                opt(preceded(
+                    tuple((opt(Separator::parse), cchar('='), opt(Separator::parse))),
+                    cut(context("expected enum value", IntConstant::parse)),
                 )),
+                opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
             )),
-            |(name, value)| Self { name, value },
-        )(input)
+            |(name, value, annotations)| Self {
+                name,
+                value,
+                annotations,
+                doc: None,
+                span: Span::default(),
+            },
+        ))(input)
+    }
+}
+
+impl<'a> Located for EnumValueRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+}
+
+impl<'a> Documented<'a> for EnumValueRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Enum {
     pub name: Identifier,
     pub children: Vec<EnumValue>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnumValue {
     pub name: Identifier,
     pub value: Option<IntConstant>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
+}
+
+impl Enum {
+    // Every value with its number. A value without explicit number is the previous one plus one,
+    // or 0 if it is the first one. Numbers are i128 so the one after i64::MAX does not overflow.
+    pub fn numbered(&self) -> Vec<(&EnumValue, i128)> {
+        let mut next = 0;
+        self.children
+            .iter()
+            .map(|child| {
+                let value = child.value.map_or(next, |v| (*v).into());
+                next = value + 1;
+                (child, value)
+            })
+            .collect()
+    }
+
+    // Names and numbers of the values, fails if a number does not fit in i32.
+    pub fn values(&self) -> Result<Vec<(&str, i32)>, Diagnostic> {
+        self.numbered()
+            .into_iter()
+            .map(|(child, value)| {
+                i32::try_from(value)
+                    .map(|value| (child.name.as_str(), value))
+                    .map_err(|_| {
+                        Diagnostic::error(
+                            child.span,
+                            format!(
+                                "value {} of `{}` in enum {} is out of i32 range",
+                                value, *child.name, *self.name
+                            ),
+                        )
+                    })
+            })
+            .collect()
+    }
 }
 
 impl<'a> From<EnumRef<'a>> for Enum {
@@ -175,6 +343,9 @@ impl<'a> From<EnumRef<'a>> for Enum {
         Self {
             name: r.name.into(),
             children: r.children.into_iter().map(Into::into).collect(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -184,6 +355,9 @@ impl<'a> From<EnumValueRef<'a>> for EnumValue {
         Self {
             name: r.name.into(),
             value: r.value,
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -200,32 +374,70 @@ impl<'a> Parser<'a> for EnumValue {
     }
 }
 
-// Struct          ::=  'struct' Identifier '{' Field* '}'
+// Struct          ::=  'struct' Identifier '{' Field* '}' TypeAnnotations?
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructRef<'a> {
     pub name: IdentifierRef<'a>,
     pub fields: Vec<FieldRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for StructRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                pair(tag("struct"), Separator::parse),
-                IdentifierRef::parse,
-                delimited(opt(Separator::parse), cchar('{'), opt(Separator::parse)),
-                separated_list0(Separator::parse, FieldRef::parse),
-                pair(opt(Separator::parse), cchar('}')),
+        context(
+            "struct",
+            located(map(
+                preceded(
+                    pair(tag("struct"), Separator::parse),
+                    cut(tuple((
+                        context("expected struct name", IdentifierRef::parse),
+                        context("expected '{'", pair(opt(Separator::parse), cchar('{'))),
+                        many0(documented(FieldRef::parse)),
+                        context(
+                            "expected '}' to close",
+                            pair(opt(Separator::parse), cchar('}')),
+                        ),
+                        opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                    ))),
+                ),
+                |(name, _, fields, _, annotations)| Self {
+                    name,
+                    fields,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(_, name, _, fields, _)| Self { name, fields },
         )(input)
     }
 }
 
+impl<'a> Located for StructRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.fields.iter_mut().for_each(|x| x.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for StructRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Struct {
     pub name: Identifier,
     pub fields: Vec<Field>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<StructRef<'a>> for Struct {
@@ -233,6 +445,9 @@ impl<'a> From<StructRef<'a>> for Struct {
         Self {
             name: r.name.into(),
             fields: r.fields.into_iter().map(Into::into).collect(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -243,32 +458,70 @@ impl<'a> Parser<'a> for Struct {
     }
 }
 
-// Union          ::=  'union' Identifier '{' Field* '}'
+// Union          ::=  'union' Identifier '{' Field* '}' TypeAnnotations?
 #[derive(Debug, Clone, PartialEq)]
 pub struct UnionRef<'a> {
     pub name: IdentifierRef<'a>,
     pub fields: Vec<FieldRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for UnionRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                pair(tag("union"), Separator::parse),
-                IdentifierRef::parse,
-                delimited(opt(Separator::parse), cchar('{'), opt(Separator::parse)),
-                separated_list0(Separator::parse, FieldRef::parse),
-                pair(opt(Separator::parse), cchar('}')),
+        context(
+            "union",
+            located(map(
+                preceded(
+                    pair(tag("union"), Separator::parse),
+                    cut(tuple((
+                        context("expected union name", IdentifierRef::parse),
+                        context("expected '{'", pair(opt(Separator::parse), cchar('{'))),
+                        many0(documented(FieldRef::parse)),
+                        context(
+                            "expected '}' to close",
+                            pair(opt(Separator::parse), cchar('}')),
+                        ),
+                        opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                    ))),
+                ),
+                |(name, _, fields, _, annotations)| Self {
+                    name,
+                    fields,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(_, name, _, fields, _)| Self { name, fields },
         )(input)
     }
 }
 
+impl<'a> Located for UnionRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.fields.iter_mut().for_each(|x| x.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for UnionRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Union {
     pub name: Identifier,
     pub fields: Vec<Field>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<UnionRef<'a>> for Union {
@@ -276,6 +529,9 @@ impl<'a> From<UnionRef<'a>> for Union {
         Self {
             name: r.name.into(),
             fields: r.fields.into_iter().map(Into::into).collect(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -286,32 +542,70 @@ impl<'a> Parser<'a> for Union {
     }
 }
 
-// Exception       ::=  'exception' Identifier '{' Field* '}'
+// Exception       ::=  'exception' Identifier '{' Field* '}' TypeAnnotations?
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExceptionRef<'a> {
     pub name: IdentifierRef<'a>,
     pub fields: Vec<FieldRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for ExceptionRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                pair(tag("exception"), Separator::parse),
-                IdentifierRef::parse,
-                delimited(opt(Separator::parse), cchar('{'), opt(Separator::parse)),
-                separated_list0(Separator::parse, FieldRef::parse),
-                pair(opt(Separator::parse), cchar('}')),
+        context(
+            "exception",
+            located(map(
+                preceded(
+                    pair(tag("exception"), Separator::parse),
+                    cut(tuple((
+                        context("expected exception name", IdentifierRef::parse),
+                        context("expected '{'", pair(opt(Separator::parse), cchar('{'))),
+                        many0(documented(FieldRef::parse)),
+                        context(
+                            "expected '}' to close",
+                            pair(opt(Separator::parse), cchar('}')),
+                        ),
+                        opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                    ))),
+                ),
+                |(name, _, fields, _, annotations)| Self {
+                    name,
+                    fields,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(_, name, _, fields, _)| Self { name, fields },
         )(input)
     }
 }
 
+impl<'a> Located for ExceptionRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.fields.iter_mut().for_each(|x| x.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for ExceptionRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Exception {
     pub name: Identifier,
     pub fields: Vec<Field>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<ExceptionRef<'a>> for Exception {
@@ -319,6 +613,9 @@ impl<'a> From<ExceptionRef<'a>> for Exception {
         Self {
             name: r.name.into(),
             fields: r.fields.into_iter().map(Into::into).collect(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -329,52 +626,80 @@ impl<'a> Parser<'a> for Exception {
     }
 }
 
-// Service         ::=  'service' Identifier ( 'extends' Identifier )? '{' Function* '}'
+// Service         ::=  'service' Identifier ( 'extends' Identifier )? '{' Function* '}' TypeAnnotations?
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServiceRef<'a> {
     pub name: IdentifierRef<'a>,
     pub extension: Option<IdentifierRef<'a>>,
     pub functions: Vec<FunctionRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for ServiceRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                delimited(
+        context(
+            "service",
+            located(map(
+                preceded(
                     pair(tag("service"), Separator::parse),
-                    IdentifierRef::parse,
-                    opt(Separator::parse),
-                ),
-                opt(map(
-                    tuple((
-                        tag("extends"),
-                        Separator::parse,
-                        IdentifierRef::parse,
-                        opt(Separator::parse),
-                    )),
-                    |(_, _, ext, _)| ext,
-                )),
-                delimited(
-                    pair(cchar('{'), opt(Separator::parse)),
-                    separated_list0(Separator::parse, FunctionRef::parse),
-                    pair(opt(Separator::parse), cchar('}')),
+                    cut(tuple((
+                        context("expected service name", IdentifierRef::parse),
+                        opt(preceded(
+                            tuple((opt(Separator::parse), tag("extends"), Separator::parse)),
+                            cut(context(
+                                "expected service name to extend",
+                                IdentifierRef::parse,
+                            )),
+                        )),
+                        context("expected '{'", pair(opt(Separator::parse), cchar('{'))),
+                        many0(documented(FunctionRef::parse)),
+                        context(
+                            "expected '}' to close",
+                            pair(opt(Separator::parse), cchar('}')),
+                        ),
+                        opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+                    ))),
                 ),
+                |(name, extension, _, functions, _, annotations)| Self {
+                    name,
+                    extension,
+                    functions,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(name, extension, functions)| Self {
-                name,
-                extension,
-                functions,
-            },
         )(input)
     }
 }
 
+impl<'a> Located for ServiceRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.functions.iter_mut().for_each(|x| x.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for ServiceRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Service {
     pub name: Identifier,
     pub extension: Option<Identifier>,
     pub functions: Vec<Function>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<ServiceRef<'a>> for Service {
@@ -383,6 +708,9 @@ impl<'a> From<ServiceRef<'a>> for Service {
             name: r.name.into(),
             extension: r.extension.map(Into::into),
             functions: r.functions.into_iter().map(Into::into).collect(),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -393,8 +721,162 @@ impl<'a> Parser<'a> for Service {
     }
 }
 
+// Definition      ::=  Const | Typedef | Enum | Struct | Union | Exception | Service
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionRef<'a> {
+    Const(ConstRef<'a>),
+    Typedef(TypedefRef<'a>),
+    Enum(EnumRef<'a>),
+    Struct(StructRef<'a>),
+    Union(UnionRef<'a>),
+    Exception(ExceptionRef<'a>),
+    Service(ServiceRef<'a>),
+}
+
+impl<'a> DefinitionRef<'a> {
+    // The name the definition declares, for a typedef it is the alias.
+    pub fn name(&self) -> &IdentifierRef<'a> {
+        match self {
+            DefinitionRef::Const(x) => &x.name,
+            DefinitionRef::Typedef(x) => &x.alias,
+            DefinitionRef::Enum(x) => &x.name,
+            DefinitionRef::Struct(x) => &x.name,
+            DefinitionRef::Union(x) => &x.name,
+            DefinitionRef::Exception(x) => &x.name,
+            DefinitionRef::Service(x) => &x.name,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            DefinitionRef::Const(x) => x.span,
+            DefinitionRef::Typedef(x) => x.span,
+            DefinitionRef::Enum(x) => x.span,
+            DefinitionRef::Struct(x) => x.span,
+            DefinitionRef::Union(x) => x.span,
+            DefinitionRef::Exception(x) => x.span,
+            DefinitionRef::Service(x) => x.span,
+        }
+    }
+}
+
+impl<'a> Parser<'a> for DefinitionRef<'a> {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        alt((
+            map(TypedefRef::parse, DefinitionRef::Typedef),
+            map(ConstRef::parse, DefinitionRef::Const),
+            map(EnumRef::parse, DefinitionRef::Enum),
+            map(StructRef::parse, DefinitionRef::Struct),
+            map(UnionRef::parse, DefinitionRef::Union),
+            map(ExceptionRef::parse, DefinitionRef::Exception),
+            map(ServiceRef::parse, DefinitionRef::Service),
+        ))(input)
+    }
+}
+
+impl<'a> Documented<'a> for DefinitionRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        match self {
+            DefinitionRef::Const(x) => x.doc_mut(),
+            DefinitionRef::Typedef(x) => x.doc_mut(),
+            DefinitionRef::Enum(x) => x.doc_mut(),
+            DefinitionRef::Struct(x) => x.doc_mut(),
+            DefinitionRef::Union(x) => x.doc_mut(),
+            DefinitionRef::Exception(x) => x.doc_mut(),
+            DefinitionRef::Service(x) => x.doc_mut(),
+        }
+    }
+}
+
+impl<'a> Located for DefinitionRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            DefinitionRef::Const(x) => x.span_mut(),
+            DefinitionRef::Typedef(x) => x.span_mut(),
+            DefinitionRef::Enum(x) => x.span_mut(),
+            DefinitionRef::Struct(x) => x.span_mut(),
+            DefinitionRef::Union(x) => x.span_mut(),
+            DefinitionRef::Exception(x) => x.span_mut(),
+            DefinitionRef::Service(x) => x.span_mut(),
+        }
+    }
+
+    fn relocate(&mut self, index: &LineIndex) {
+        match self {
+            DefinitionRef::Const(x) => x.relocate(index),
+            DefinitionRef::Typedef(x) => x.relocate(index),
+            DefinitionRef::Enum(x) => x.relocate(index),
+            DefinitionRef::Struct(x) => x.relocate(index),
+            DefinitionRef::Union(x) => x.relocate(index),
+            DefinitionRef::Exception(x) => x.relocate(index),
+            DefinitionRef::Service(x) => x.relocate(index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Definition {
+    Const(Const),
+    Typedef(Typedef),
+    Enum(Enum),
+    Struct(Struct),
+    Union(Union),
+    Exception(Exception),
+    Service(Service),
+}
+
+impl Definition {
+    // The name the definition declares, for a typedef it is the alias.
+    pub fn name(&self) -> &Identifier {
+        match self {
+            Definition::Const(x) => &x.name,
+            Definition::Typedef(x) => &x.alias,
+            Definition::Enum(x) => &x.name,
+            Definition::Struct(x) => &x.name,
+            Definition::Union(x) => &x.name,
+            Definition::Exception(x) => &x.name,
+            Definition::Service(x) => &x.name,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Definition::Const(x) => x.span,
+            Definition::Typedef(x) => x.span,
+            Definition::Enum(x) => x.span,
+            Definition::Struct(x) => x.span,
+            Definition::Union(x) => x.span,
+            Definition::Exception(x) => x.span,
+            Definition::Service(x) => x.span,
+        }
+    }
+}
+
+impl<'a> From<DefinitionRef<'a>> for Definition {
+    fn from(r: DefinitionRef<'a>) -> Self {
+        match r {
+            DefinitionRef::Const(x) => Definition::Const(x.into()),
+            DefinitionRef::Typedef(x) => Definition::Typedef(x.into()),
+            DefinitionRef::Enum(x) => Definition::Enum(x.into()),
+            DefinitionRef::Struct(x) => Definition::Struct(x.into()),
+            DefinitionRef::Union(x) => Definition::Union(x.into()),
+            DefinitionRef::Exception(x) => Definition::Exception(x.into()),
+            DefinitionRef::Service(x) => Definition::Service(x.into()),
+        }
+    }
+}
+
+impl<'a> Parser<'a> for Definition {
+    fn parse(input: &'a str) -> IResult<&'a str, Self> {
+        DefinitionRef::parse(input).map(|(remains, parsed)| (remains, parsed.into()))
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::annotation::AnnotationRef;
     use crate::basic::LiteralRef;
 
     use super::*;
@@ -408,7 +890,9 @@ mod test {
             ConstRef {
                 name: IdentifierRef::from("is_rust_easy"),
                 type_: FieldTypeRef::Bool,
-                value: ConstValueRef::Literal(LiteralRef::from("yes!"))
+                value: ConstValueRef::Literal(LiteralRef::from("yes!")),
+                doc: None,
+                span: Span::at(0, 32, 1, 1),
             }
         );
     }
@@ -419,43 +903,80 @@ mod test {
             TypedefRef::parse("typedef i32 MyI32").unwrap().1,
             TypedefRef {
                 old: FieldTypeRef::I32,
-                alias: IdentifierRef::from("MyI32")
+                alias: IdentifierRef::from("MyI32"),
+                annotations: None,
+                doc: None,
+                span: Span::at(0, 17, 1, 1),
+            }
+        );
+        assert_eq!(
+            TypedefRef::parse("typedef shared.User SharedUser")
+                .unwrap()
+                .1,
+            TypedefRef {
+                old: FieldTypeRef::Identifier(IdentifierRef::from("shared.User")),
+                alias: IdentifierRef::from("SharedUser"),
+                annotations: None,
+                doc: None,
+                span: Span::at(0, 30, 1, 1),
             }
         );
     }
 
     #[test]
     fn test_enum() {
-        let expected = EnumRef {
+        let expected = |spans: [Span; 4]| EnumRef {
             name: IdentifierRef::from("PL"),
             children: vec![
                 EnumValueRef {
                     name: IdentifierRef::from("Rust"),
                     value: None,
+                    annotations: None,
+                    doc: None,
+                    span: spans[0],
                 },
                 EnumValueRef {
                     name: IdentifierRef::from("Go"),
                     value: Some(IntConstant::from(2)),
+                    annotations: None,
+                    doc: None,
+                    span: spans[1],
                 },
                 EnumValueRef {
                     name: IdentifierRef::from("Cpp"),
                     value: Some(IntConstant::from(3)),
+                    annotations: None,
+                    doc: None,
+                    span: spans[2],
                 },
             ],
+            annotations: None,
+            span: spans[3],
+            doc: None,
         };
         assert_eq!(
             EnumRef::parse("enum PL { Rust Go=2 , Cpp = 3 }").unwrap().1,
-            expected
+            expected([
+                Span::at(10, 14, 1, 11),
+                Span::at(15, 19, 1, 16),
+                Span::at(22, 29, 1, 23),
+                Span::at(0, 31, 1, 1),
+            ])
         );
         assert_eq!(
             EnumRef::parse("enum PL{Rust Go=2,Cpp=3}").unwrap().1,
-            expected
+            expected([
+                Span::at(8, 12, 1, 9),
+                Span::at(13, 17, 1, 14),
+                Span::at(18, 23, 1, 19),
+                Span::at(0, 24, 1, 1),
+            ])
         );
     }
 
     #[test]
     fn test_struct() {
-        let expected = StructRef {
+        let expected = |spans: [Span; 3]| StructRef {
             name: IdentifierRef::from("user"),
             fields: vec![
                 FieldRef {
@@ -464,6 +985,9 @@ mod test {
                     type_: FieldTypeRef::String,
                     name: IdentifierRef::from("name"),
                     default: None,
+                    annotations: None,
+                    doc: None,
+                    span: spans[0],
                 },
                 FieldRef {
                     id: Some(IntConstant::from(2)),
@@ -471,26 +995,137 @@ mod test {
                     type_: FieldTypeRef::I32,
                     name: IdentifierRef::from("age"),
                     default: Some(ConstValueRef::Int(IntConstant::from(18))),
+                    annotations: None,
+                    doc: None,
+                    span: spans[1],
                 },
             ],
+            annotations: None,
+            span: spans[2],
+            doc: None,
         };
         assert_eq!(
             StructRef::parse("struct user{1:optional string name; 2:i32 age=18}")
                 .unwrap()
                 .1,
-            expected
+            expected([
+                Span::at(12, 34, 1, 13),
+                Span::at(36, 48, 1, 37),
+                Span::at(0, 49, 1, 1),
+            ])
         );
         assert_eq!(
             StructRef::parse("struct user { 1 : optional string name ; 2 : i32 age = 18 }")
                 .unwrap()
                 .1,
+            expected([
+                Span::at(14, 38, 1, 15),
+                Span::at(41, 57, 1, 42),
+                Span::at(0, 59, 1, 1),
+            ])
+        );
+
+        let expected = StructRef {
+            annotations: Some(AnnotationsRef::from(vec![AnnotationRef {
+                key: IdentifierRef::from("final"),
+                value: None,
+            }])),
+            ..expected([
+                Span::at(12, 34, 1, 13),
+                Span::at(36, 48, 1, 37),
+                Span::at(0, 57, 1, 1),
+            ])
+        };
+        assert_eq!(
+            StructRef::parse("struct user{1:optional string name; 2:i32 age=18} (final)")
+                .unwrap()
+                .1,
             expected
         );
     }
 
+    #[test]
+    fn test_annotated_definitions() {
+        let annotations = Some(AnnotationsRef::from(vec![AnnotationRef {
+            key: IdentifierRef::from("deprecated"),
+            value: Some(LiteralRef::from("yes")),
+        }]));
+        assert_eq!(
+            TypedefRef::parse(
+                "typedef list<i32> (cpp.template = 'std::list') Ids (deprecated = 'yes')"
+            )
+            .unwrap()
+            .1,
+            TypedefRef {
+                old: FieldTypeRef::List(
+                    Box::new(FieldTypeRef::I32),
+                    Some(AnnotationsRef::from(vec![AnnotationRef {
+                        key: IdentifierRef::from("cpp.template"),
+                        value: Some(LiteralRef::from("std::list")),
+                    }]))
+                ),
+                alias: IdentifierRef::from("Ids"),
+                annotations: annotations.clone(),
+                doc: None,
+                span: Span::at(0, 71, 1, 1),
+            }
+        );
+        assert_eq!(
+            EnumRef::parse("enum PL { Rust (deprecated = 'yes'), Go = 2 (deprecated = 'yes') } (deprecated = 'yes')")
+                .unwrap()
+                .1,
+            EnumRef {
+                name: IdentifierRef::from("PL"),
+                children: vec![
+                    EnumValueRef {
+                        name: IdentifierRef::from("Rust"),
+                        value: None,
+                        annotations: annotations.clone(),
+                        doc: None,
+                span: Span::at(10, 35, 1, 11),
+                    },
+                    EnumValueRef {
+                        name: IdentifierRef::from("Go"),
+                        value: Some(IntConstant::from(2)),
+                        annotations: annotations.clone(),
+                        doc: None,
+                span: Span::at(37, 64, 1, 38),
+                    },
+                ],
+                annotations: annotations.clone(),
+                doc: None,
+                span: Span::at(0, 87, 1, 1),
+            }
+        );
+        assert_eq!(
+            ServiceRef::parse(
+                "service Demo { void Ping() (deprecated = 'yes') } (deprecated = 'yes')"
+            )
+            .unwrap()
+            .1,
+            ServiceRef {
+                name: IdentifierRef::from("Demo"),
+                extension: None,
+                functions: vec![FunctionRef {
+                    oneway: false,
+                    returns: None,
+                    name: IdentifierRef::from("Ping"),
+                    parameters: vec![],
+                    exceptions: None,
+                    annotations: annotations.clone(),
+                    doc: None,
+                    span: Span::at(15, 47, 1, 16),
+                }],
+                annotations,
+                doc: None,
+                span: Span::at(0, 70, 1, 1),
+            }
+        );
+    }
+
     #[test]
     fn test_service() {
-        let function = FunctionRef {
+        let function = |span, parameter_span| FunctionRef {
             oneway: false,
             returns: Some(FieldTypeRef::String),
             name: IdentifierRef::from("GetUser"),
@@ -500,13 +1135,25 @@ mod test {
                 type_: FieldTypeRef::String,
                 name: IdentifierRef::from("name"),
                 default: None,
+                annotations: None,
+                doc: None,
+                span: parameter_span,
             }],
             exceptions: None,
+            annotations: None,
+            span,
+            doc: None,
         };
         let expected = ServiceRef {
             name: IdentifierRef::from("DemoService"),
             extension: Some(IdentifierRef::from("BaseService")),
-            functions: vec![function.clone(), function],
+            functions: vec![
+                function(Span::at(42, 78, 1, 43), Span::at(57, 77, 1, 58)),
+                function(Span::at(89, 125, 2, 10), Span::at(104, 124, 2, 25)),
+            ],
+            annotations: None,
+            span: Span::at(0, 127, 1, 1),
+            doc: None,
         };
         assert_eq!(
             ServiceRef::parse(
@@ -519,4 +1166,29 @@ mod test {
             expected
         );
     }
+
+    #[test]
+    fn test_enum_values() {
+        let e = Enum::parse("enum E { A, B = 5, C, D = -1, E }").unwrap().1;
+        assert_eq!(
+            e.values().unwrap(),
+            vec![("A", 0), ("B", 5), ("C", 6), ("D", -1), ("E", 0)]
+        );
+        let e = Enum::parse("enum E { A = 2147483647, B }").unwrap().1;
+        assert_eq!(
+            e.values().unwrap_err().message,
+            "value 2147483648 of `B` in enum E is out of i32 range"
+        );
+        let e = Enum::parse("enum E { A = 9223372036854775807, B }")
+            .unwrap()
+            .1;
+        assert_eq!(
+            e.numbered().last().map(|(_, value)| *value),
+            Some(9223372036854775808)
+        );
+        assert_eq!(
+            e.values().unwrap_err().message,
+            "value 9223372036854775807 of `A` in enum E is out of i32 range"
+        );
+    }
 }
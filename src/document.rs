@@ -1,29 +1,26 @@
+use std::str::FromStr;
+
 use nom::branch::alt;
 use nom::combinator::{map, opt};
 use nom::multi::many0;
-use nom::sequence::delimited;
-use nom::IResult;
+use nom::sequence::{preceded, terminated};
 
-use crate::basic::Separator;
+use crate::basic::{documented, Separator};
 use crate::definition::{
-    Const, ConstRef, Enum, EnumRef, Exception, ExceptionRef, Service, ServiceRef, Struct,
-    StructRef, Typedef, TypedefRef, Union, UnionRef,
+    Const, ConstRef, Definition, DefinitionRef, Enum, EnumRef, Exception, ExceptionRef, Service,
+    ServiceRef, Struct, StructRef, Typedef, TypedefRef, Union, UnionRef,
 };
 use crate::header::{CppInclude, CppIncludeRef, Include, IncludeRef, Namespace, NamespaceRef};
-use crate::Parser;
+use crate::span::{LineIndex, Located};
+use crate::{IResult, ParseError, Parser};
 
 #[derive(PartialEq, Debug, Clone, Default)]
 pub struct DocumentRef<'a> {
     pub includes: Vec<IncludeRef<'a>>,
     pub cpp_includes: Vec<CppIncludeRef<'a>>,
     pub namespaces: Vec<NamespaceRef<'a>>,
-    pub typedefs: Vec<TypedefRef<'a>>,
-    pub consts: Vec<ConstRef<'a>>,
-    pub enums: Vec<EnumRef<'a>>,
-    pub structs: Vec<StructRef<'a>>,
-    pub unions: Vec<UnionRef<'a>>,
-    pub exceptions: Vec<ExceptionRef<'a>>,
-    pub services: Vec<ServiceRef<'a>>,
+    // Definitions in the order they appear in the source.
+    pub definitions: Vec<DefinitionRef<'a>>,
 }
 
 impl<'a> Parser<'a> for DocumentRef<'a> {
@@ -32,46 +29,80 @@ impl<'a> Parser<'a> for DocumentRef<'a> {
         let includes = &mut target.includes;
         let cpp_includes = &mut target.cpp_includes;
         let namespaces = &mut target.namespaces;
-        let typedefs = &mut target.typedefs;
-        let consts = &mut target.consts;
-        let enums = &mut target.enums;
-        let structs = &mut target.structs;
-        let unions = &mut target.unions;
-        let exceptions = &mut target.exceptions;
-        let services = &mut target.services;
-
-        let (remains, _) = many0(delimited(
-            opt(Separator::parse),
-            alt((
-                map(IncludeRef::parse, |i| includes.push(i)),
-                map(CppIncludeRef::parse, |i| cpp_includes.push(i)),
-                map(NamespaceRef::parse, |i| namespaces.push(i)),
-                map(TypedefRef::parse, |i| typedefs.push(i)),
-                map(ConstRef::parse, |i| consts.push(i)),
-                map(EnumRef::parse, |i| enums.push(i)),
-                map(StructRef::parse, |i| structs.push(i)),
-                map(UnionRef::parse, |i| unions.push(i)),
-                map(ExceptionRef::parse, |i| exceptions.push(i)),
-                map(ServiceRef::parse, |i| services.push(i)),
-            )),
+        let definitions = &mut target.definitions;
+
+        let (remains, _) = terminated(
+            many0(alt((
+                preceded(
+                    opt(Separator::parse),
+                    alt((
+                        map(IncludeRef::parse, |i| includes.push(i)),
+                        map(CppIncludeRef::parse, |i| cpp_includes.push(i)),
+                        map(NamespaceRef::parse, |i| namespaces.push(i)),
+                    )),
+                ),
+                map(documented(DefinitionRef::parse), |i| definitions.push(i)),
+            ))),
             opt(Separator::parse),
-        ))(input)?;
+        )(input)?;
+
+        let index = LineIndex::new(input, remains);
+        target
+            .definitions
+            .iter_mut()
+            .for_each(|x| x.relocate(&index));
         Ok((remains, target))
     }
 }
 
+// Per-kind views of the definitions, each in source order.
+macro_rules! definitions_of {
+    ($definition:ident { $($name:ident: $variant:ident => $type_:ty),* $(,)? }) => {
+        $(
+            pub fn $name(&self) -> impl Iterator<Item = &$type_> {
+                self.definitions.iter().filter_map(|d| match d {
+                    $definition::$variant(x) => Some(x),
+                    _ => None,
+                })
+            }
+        )*
+    };
+}
+
+impl<'a> DocumentRef<'a> {
+    definitions_of!(DefinitionRef {
+        typedefs: Typedef => TypedefRef<'a>,
+        consts: Const => ConstRef<'a>,
+        enums: Enum => EnumRef<'a>,
+        structs: Struct => StructRef<'a>,
+        unions: Union => UnionRef<'a>,
+        exceptions: Exception => ExceptionRef<'a>,
+        services: Service => ServiceRef<'a>,
+    });
+
+    // Parse the whole input, anything left other than spaces and comments is an error.
+    pub fn parse_complete(input: &'a str) -> Result<Self, ParseError> {
+        let (remains, document) = Self::parse(input).map_err(|e| ParseError::new(input, e))?;
+        let remains = opt(Separator::parse)(remains).map_or(remains, |(remains, _)| remains);
+        match remains.split_whitespace().next() {
+            None => Ok(document),
+            Some(found) => Err(ParseError::at(
+                input,
+                input.len() - remains.len(),
+                format!("expected header or definition, found `{}`", found),
+            )),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Document {
     pub includes: Vec<Include>,
     pub cpp_includes: Vec<CppInclude>,
     pub namespaces: Vec<Namespace>,
-    pub typedefs: Vec<Typedef>,
-    pub consts: Vec<Const>,
-    pub enums: Vec<Enum>,
-    pub structs: Vec<Struct>,
-    pub unions: Vec<Union>,
-    pub exceptions: Vec<Exception>,
-    pub services: Vec<Service>,
+    // Definitions in the order they appear in the source.
+    pub definitions: Vec<Definition>,
 }
 
 impl<'a> From<DocumentRef<'a>> for Document {
@@ -80,13 +111,7 @@ impl<'a> From<DocumentRef<'a>> for Document {
             includes: r.includes.into_iter().map(Into::into).collect(),
             cpp_includes: r.cpp_includes.into_iter().map(Into::into).collect(),
             namespaces: r.namespaces.into_iter().map(Into::into).collect(),
-            typedefs: r.typedefs.into_iter().map(Into::into).collect(),
-            consts: r.consts.into_iter().map(Into::into).collect(),
-            enums: r.enums.into_iter().map(Into::into).collect(),
-            structs: r.structs.into_iter().map(Into::into).collect(),
-            unions: r.unions.into_iter().map(Into::into).collect(),
-            exceptions: r.exceptions.into_iter().map(Into::into).collect(),
-            services: r.services.into_iter().map(Into::into).collect(),
+            definitions: r.definitions.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -97,9 +122,34 @@ impl<'a> Parser<'a> for Document {
     }
 }
 
+impl Document {
+    definitions_of!(Definition {
+        typedefs: Typedef => Typedef,
+        consts: Const => Const,
+        enums: Enum => Enum,
+        structs: Struct => Struct,
+        unions: Union => Union,
+        exceptions: Exception => Exception,
+        services: Service => Service,
+    });
+
+    // Parse the whole input, anything left other than spaces and comments is an error.
+    pub fn parse_complete(input: &str) -> Result<Self, ParseError> {
+        DocumentRef::parse_complete(input).map(Into::into)
+    }
+}
+
+impl FromStr for Document {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_complete(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::basic::LiteralRef;
+    use crate::basic::{Doc, LiteralRef};
 
     use super::*;
 
@@ -114,4 +164,196 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn test_document_with_annotations() {
+        let (remains, document) = DocumentRef::parse(
+            r#"
+            struct User {
+                1: required i64 id (go.tag = "json:\"id\"", cpp.ref = "true"),
+                2: optional map<string, string> (cpp.template = "std::unordered_map") extra,
+            } (final)
+
+            service UserService {
+                User GetUser(1: i64 id) (api.get = "/user"),
+            } (namespace = "user")
+            "#,
+        )
+        .unwrap();
+        assert_eq!(remains.trim(), "");
+        assert_eq!(document.structs().count(), 1);
+        assert_eq!(document.services().count(), 1);
+    }
+
+    #[test]
+    fn test_document_spans() {
+        let input = "namespace rs demo\n\nstruct User {\n    1: required i64 id,\n    2: string name\n}\n\nservice UserService {\n  User GetUser(1: i64 id)\n}\n";
+        let document: Document = DocumentRef::parse(input).unwrap().1.into();
+
+        let user = document.structs().next().unwrap();
+        assert_eq!((user.span.line, user.span.column), (3, 1));
+        assert_eq!(
+            &input[user.span.start..user.span.end],
+            "struct User {\n    1: required i64 id,\n    2: string name\n}"
+        );
+        let id = &user.fields[0];
+        assert_eq!((id.span.line, id.span.column), (4, 5));
+        assert_eq!(&input[id.span.start..id.span.end], "1: required i64 id");
+        let name = &user.fields[1];
+        assert_eq!((name.span.line, name.span.column), (5, 5));
+        assert_eq!(&input[name.span.start..name.span.end], "2: string name");
+
+        let get_user = &document.services().next().unwrap().functions[0];
+        assert_eq!((get_user.span.line, get_user.span.column), (9, 3));
+        assert_eq!(
+            &input[get_user.span.start..get_user.span.end],
+            "User GetUser(1: i64 id)"
+        );
+        let param = &get_user.parameters[0];
+        assert_eq!((param.span.line, param.span.column), (9, 16));
+    }
+
+    #[test]
+    fn test_parse_complete() {
+        let input =
+            "namespace rs demo\n\nstruct User {\n    1: i32 id\n}\n\n// the end\n/* really */\n";
+        let document = Document::parse_complete(input).unwrap();
+        assert_eq!(document.structs().count(), 1);
+        assert_eq!(input.parse::<Document>().unwrap(), document);
+
+        let input = "struct User {\n    1: i32 id\n}\n\n// no such thing\nmessage Item {}\n";
+        let err = input.parse::<Document>().unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected header or definition, found `message`"
+        );
+        assert_eq!((err.line, err.column), (6, 1));
+
+        let err = Document::parse_complete("struct User {\n    1: i32\n}").unwrap_err();
+        assert_eq!(err.message, "expected field name in struct User");
+        assert_eq!((err.line, err.column), (3, 1));
+    }
+
+    #[test]
+    fn test_definition_order() {
+        let input = "const i32 A = 1\nstruct S {}\ntypedef i64 Id\nconst S B = {}\nservice X {}\nenum E { V }\n";
+        let document = DocumentRef::parse_complete(input).unwrap();
+        let names: Vec<&str> = document.definitions.iter().map(|d| **d.name()).collect();
+        assert_eq!(names, vec!["A", "S", "Id", "B", "X", "E"]);
+        assert!(matches!(document.definitions[2], DefinitionRef::Typedef(_)));
+        assert_eq!(document.definitions[3].span().line, 4);
+
+        let consts: Vec<&str> = document.consts().map(|c| *c.name).collect();
+        assert_eq!(consts, vec!["A", "B"]);
+        assert_eq!(document.structs().count(), 1);
+        assert_eq!(document.unions().count(), 0);
+
+        let document: Document = document.into();
+        let consts: Vec<&str> = document.consts().map(|c| c.name.as_str()).collect();
+        assert_eq!(consts, vec!["A", "B"]);
+        assert!(matches!(document.definitions[5], Definition::Enum(_)));
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = r#"
+/** The user. */
+struct User {
+    /** Unique id. */
+    1: i64 id, // not a doc
+    // plain comment
+    2: string name
+    /// Known as.
+    /// Can be empty.
+    3: string nick
+}
+
+/** Detached. */
+// License or similar
+enum Kind {
+    /** A person. */
+    PERSON = 1,
+    ROBOT
+}
+
+/// Users.
+service UserService {
+    /** Fetch one. */
+    User GetUser(/** The id. */ 1: i64 id)
+}
+"#;
+        let document = Document::parse_complete(input).unwrap();
+        let text = |doc: &Option<Doc>| doc.as_ref().map(Doc::text);
+
+        let user = document.structs().next().unwrap();
+        assert_eq!(text(&user.doc).as_deref(), Some("The user."));
+        assert_eq!(text(&user.fields[0].doc).as_deref(), Some("Unique id."));
+        assert_eq!(user.fields[1].doc, None);
+        assert_eq!(
+            text(&user.fields[2].doc).as_deref(),
+            Some("Known as.\nCan be empty.")
+        );
+
+        let kind = document.enums().next().unwrap();
+        assert_eq!(kind.doc, None);
+        assert_eq!(text(&kind.children[0].doc).as_deref(), Some("A person."));
+        assert_eq!(kind.children[1].doc, None);
+
+        let service = document.services().next().unwrap();
+        assert_eq!(text(&service.doc).as_deref(), Some("Users."));
+        let get_user = &service.functions[0];
+        assert_eq!(text(&get_user.doc).as_deref(), Some("Fetch one."));
+        assert_eq!(
+            text(&get_user.parameters[0].doc).as_deref(),
+            Some("The id.")
+        );
+    }
+
+    #[test]
+    fn test_parse_demo() {
+        let idl = include_str!("../thrift/demo.thrift");
+        let document = Document::parse_complete(idl).unwrap();
+        assert_eq!(document.namespaces.len(), 1);
+        assert_eq!(document.structs().count(), 4);
+        assert_eq!(document.services().count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let input = "namespace rs demo\n/** A user. */\nstruct User {\n  1: required map<string, i64> (a) scores = {'x': 1.5} (b = \"c\")\n}\n";
+        let document = Document::parse_complete(input).unwrap();
+        let value = serde_json::to_value(&document).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "includes": [],
+                "cpp_includes": [],
+                "namespaces": [{"scope": "rs", "name": "demo"}],
+                "definitions": [{"struct": {
+                    "name": "User",
+                    "fields": [{
+                        "id": 1,
+                        "required": true,
+                        "type": {"map": ["string", "i64", [{"key": "a", "value": null}]]},
+                        "name": "scores",
+                        "default": {"map": [[{"literal": "x"}, {"double": 1.5}]]},
+                        "annotations": [{"key": "b", "value": "c"}],
+                        "doc": null,
+                        "span": {"start": 49, "end": 111, "line": 4, "column": 3},
+                    }],
+                    "annotations": null,
+                    "doc": "/** A user. */",
+                    "span": {"start": 33, "end": 113, "line": 3, "column": 1},
+                }}],
+            })
+        );
+        let back: Document = serde_json::from_value(value).unwrap();
+        assert_eq!(back, document);
+        // Spans compare by their public fields, which serde must keep.
+        let user = back.structs().next().unwrap();
+        assert_eq!((user.span.line, user.span.column), (3, 1));
+        let scores = &user.fields[0].span;
+        assert_eq!((scores.start, scores.line, scores.column), (49, 4, 3));
+    }
 }
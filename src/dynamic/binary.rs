@@ -0,0 +1,308 @@
+// TBinaryProtocol: big-endian numbers, strings and containers prefixed with an i32 size, and
+// fields with a type id and an i16 id, up to a stop byte.
+
+use std::convert::TryInto;
+
+use super::message::decode_message;
+use super::{
+    decode, encode, invalid, DecodeError, EncodeError, Input, Message, MessageType, Output, Schema,
+    Value, WireType,
+};
+use crate::definition::Struct;
+
+const STOP: u8 = 0;
+// The high bytes of the first word of strict message envelopes, the low byte is the message type.
+const VERSION_1: u32 = 0x8001_0000;
+
+fn type_id(type_: WireType) -> u8 {
+    match type_ {
+        WireType::Bool => 2,
+        WireType::Byte => 3,
+        WireType::Double => 4,
+        WireType::I16 => 6,
+        WireType::I32 => 8,
+        WireType::I64 => 10,
+        WireType::String => 11,
+        WireType::Struct => 12,
+        WireType::Map => 13,
+        WireType::Set => 14,
+        WireType::List => 15,
+    }
+}
+
+fn wire_type(id: u8) -> Result<WireType, DecodeError> {
+    Ok(match id {
+        2 => WireType::Bool,
+        3 => WireType::Byte,
+        4 => WireType::Double,
+        6 => WireType::I16,
+        8 => WireType::I32,
+        10 => WireType::I64,
+        11 => WireType::String,
+        12 => WireType::Struct,
+        13 => WireType::Map,
+        14 => WireType::Set,
+        15 => WireType::List,
+        _ => return Err(DecodeError::Type(id)),
+    })
+}
+
+// Decode a struct of type `struct_`, whose types are looked up in the program of `schema`.
+pub fn decode_binary(
+    bytes: &[u8],
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<Value, DecodeError> {
+    decode(BinaryInput::new(bytes), struct_, schema)
+}
+
+// Encode `value`, which must be a struct of type `struct_`.
+pub fn encode_binary(
+    value: &Value,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<Vec<u8>, EncodeError> {
+    Ok(encode(BinaryOutput::default(), value, struct_, schema)?.bytes)
+}
+
+// Decode a message calling `service`, or replying to a call of it. `service` is looked up in the
+// program of `schema`. Envelopes are read with or without the version.
+pub fn decode_binary_message<'w>(
+    bytes: &[u8],
+    service: &str,
+    schema: &Schema<'w>,
+) -> Result<Message<'w>, DecodeError> {
+    let mut input = BinaryInput::new(bytes);
+    let first = input.read_i32()?;
+    let (type_, name) = match first < 0 {
+        true if first as u32 & 0xffff_0000 == VERSION_1 => {
+            let type_ = MessageType::from_id(first as u8)?;
+            (type_, input.read_binary()?)
+        }
+        true => {
+            let version = (first as u32) >> 16;
+            return Err(invalid(format!("unknown protocol version {:#x}", version)));
+        }
+        // The old envelope starts with the size of the name, the type follows it.
+        false => {
+            let name = input.take(first as usize)?.to_vec();
+            (MessageType::from_id(input.array::<1>()?[0])?, name)
+        }
+    };
+    let name = String::from_utf8_lossy(&name).into_owned();
+    let envelope = (name, type_, input.read_i32()?);
+    decode_message(input, envelope, service, schema)
+}
+
+pub(crate) struct BinaryInput<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BinaryInput<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Eof);
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn read_size(&mut self) -> Result<usize, DecodeError> {
+        let size = self.read_i32()?;
+        match size < 0 {
+            true => Err(DecodeError::Size(size.into())),
+            false => Ok(size as usize),
+        }
+    }
+}
+
+impl<'a> Input for BinaryInput<'a> {
+    fn read_field(&mut self) -> Result<Option<(WireType, i16)>, DecodeError> {
+        match self.array::<1>()?[0] {
+            STOP => Ok(None),
+            id => Ok(Some((wire_type(id)?, self.read_i16()?))),
+        }
+    }
+
+    fn read_map_begin(&mut self) -> Result<(WireType, WireType, usize), DecodeError> {
+        let [key, value] = self.array::<2>()?;
+        Ok((wire_type(key)?, wire_type(value)?, self.read_size()?))
+    }
+
+    fn read_list_begin(&mut self) -> Result<(WireType, usize), DecodeError> {
+        let element = wire_type(self.array::<1>()?[0])?;
+        Ok((element, self.read_size()?))
+    }
+
+    fn read_bool(&mut self) -> Result<bool, DecodeError> {
+        Ok(self.array::<1>()?[0] != 0)
+    }
+
+    fn read_i8(&mut self) -> Result<i8, DecodeError> {
+        Ok(i8::from_be_bytes(self.array()?))
+    }
+
+    fn read_i16(&mut self) -> Result<i16, DecodeError> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, DecodeError> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    fn read_double(&mut self) -> Result<f64, DecodeError> {
+        Ok(f64::from_be_bytes(self.array()?))
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let size = self.read_size()?;
+        Ok(self.take(size)?.to_vec())
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BinaryOutput {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Output for BinaryOutput {
+    fn write_field(&mut self, type_: WireType, id: i16) {
+        self.bytes.push(type_id(type_));
+        self.write_i16(id);
+    }
+
+    fn write_stop(&mut self) {
+        self.bytes.push(STOP);
+    }
+
+    fn write_map_begin(&mut self, key: WireType, value: WireType, size: usize) {
+        self.bytes.push(type_id(key));
+        self.bytes.push(type_id(value));
+        self.write_i32(size as i32);
+    }
+
+    fn write_list_begin(&mut self, element: WireType, size: usize) {
+        self.bytes.push(type_id(element));
+        self.write_i32(size as i32);
+    }
+
+    fn write_bool(&mut self, v: bool) {
+        self.bytes.push(v as u8);
+    }
+
+    fn write_i8(&mut self, v: i8) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_i16(&mut self, v: i16) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_i32(&mut self, v: i32) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_double(&mut self, v: f64) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_binary(&mut self, v: &[u8]) {
+        self.write_i32(v.len() as i32);
+        self.bytes.extend_from_slice(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dynamic::test::{user, SCHEMA};
+    use crate::dynamic::FieldValue;
+    use crate::workspace::Workspace;
+
+    #[test]
+    fn test_binary() {
+        let workspace = Workspace::from_source("user.thrift", SCHEMA, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+        let user_struct = schema.find("User").unwrap();
+        let address = schema.find("Address").unwrap();
+
+        let bytes = encode_binary(&user(), &user_struct, &schema).unwrap();
+        assert_eq!(decode_binary(&bytes, &user_struct, &schema), Ok(user()));
+
+        let value = Value::Struct(vec![FieldValue {
+            id: 1,
+            name: Some("city".into()),
+            value: Value::String("ab".into()),
+        }]);
+        let bytes = encode_binary(&value, &address, &schema).unwrap();
+        assert_eq!(bytes, b"\x0b\x00\x01\x00\x00\x00\x02ab\x00");
+
+        // Unknown fields and fields of other types than the schema gives have no name.
+        let bytes = b"\x08\x00\x01\x00\x00\x00\x02\x0f\x00\x09\x06\x00\x00\x00\x01\x00\x05\x00";
+        assert_eq!(
+            decode_binary(bytes, &address, &schema),
+            Ok(Value::Struct(vec![
+                FieldValue {
+                    id: 1,
+                    name: None,
+                    value: Value::I32(2),
+                },
+                FieldValue {
+                    id: 9,
+                    name: None,
+                    value: Value::List(vec![Value::I16(5)]),
+                },
+            ]))
+        );
+
+        assert_eq!(
+            decode_binary(b"\x0b\x00\x01\x00\x00\x00\x02a", &address, &schema),
+            Err(DecodeError::Eof)
+        );
+        assert_eq!(
+            decode_binary(b"\x0b\x00\x01\xff\xff\xff\xff\x00", &address, &schema),
+            Err(DecodeError::Size(-1))
+        );
+        assert_eq!(
+            decode_binary(b"\x07\x00\x01", &address, &schema),
+            Err(DecodeError::Type(7))
+        );
+        assert_eq!(
+            decode_binary(b"\x00\x00", &address, &schema),
+            Err(DecodeError::Trailing(1))
+        );
+        let nested: Vec<u8> = b"\x0c\x00\x01".repeat(100);
+        assert_eq!(
+            decode_binary(&nested, &address, &schema),
+            Err(DecodeError::Depth)
+        );
+        // Lists of lists are counted too.
+        let mut nested = b"\x0f\x00\x01".to_vec();
+        nested.extend(b"\x0f\x00\x00\x00\x01".repeat(100));
+        assert_eq!(
+            decode_binary(&nested, &address, &schema),
+            Err(DecodeError::Depth)
+        );
+    }
+}
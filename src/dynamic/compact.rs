@@ -0,0 +1,445 @@
+// TCompactProtocol: integers are zigzag varints, field ids are written as the difference from the
+// previous one when it is small, bool fields keep their value in the field type, and sizes are
+// packed with the element types when they are small.
+
+use std::convert::{TryFrom, TryInto};
+
+use super::message::decode_message;
+use super::{
+    decode, encode, invalid, DecodeError, EncodeError, Input, Message, MessageType, Output, Schema,
+    Value, WireType,
+};
+use crate::definition::Struct;
+
+const STOP: u8 = 0;
+const TRUE: u8 = 1;
+const FALSE: u8 = 2;
+// The first byte of message envelopes, and the version in the low bits of the second one, whose
+// high bits are the message type.
+const PROTOCOL_ID: u8 = 0x82;
+const VERSION: u8 = 1;
+
+// Bools are `TRUE` as the element type of a container.
+fn type_id(type_: WireType) -> u8 {
+    match type_ {
+        WireType::Bool => TRUE,
+        WireType::Byte => 3,
+        WireType::I16 => 4,
+        WireType::I32 => 5,
+        WireType::I64 => 6,
+        WireType::Double => 7,
+        WireType::String => 8,
+        WireType::List => 9,
+        WireType::Set => 10,
+        WireType::Map => 11,
+        WireType::Struct => 12,
+    }
+}
+
+fn wire_type(id: u8) -> Result<WireType, DecodeError> {
+    Ok(match id {
+        TRUE | FALSE => WireType::Bool,
+        3 => WireType::Byte,
+        4 => WireType::I16,
+        5 => WireType::I32,
+        6 => WireType::I64,
+        7 => WireType::Double,
+        8 => WireType::String,
+        9 => WireType::List,
+        10 => WireType::Set,
+        11 => WireType::Map,
+        12 => WireType::Struct,
+        _ => return Err(DecodeError::Type(id)),
+    })
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+// Decode a struct of type `struct_`, whose types are looked up in the program of `schema`.
+pub fn decode_compact(
+    bytes: &[u8],
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<Value, DecodeError> {
+    decode(CompactInput::new(bytes), struct_, schema)
+}
+
+// Encode `value`, which must be a struct of type `struct_`.
+pub fn encode_compact(
+    value: &Value,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<Vec<u8>, EncodeError> {
+    Ok(encode(CompactOutput::default(), value, struct_, schema)?.bytes)
+}
+
+// Decode a message calling `service`, or replying to a call of it. `service` is looked up in the
+// program of `schema`.
+pub fn decode_compact_message<'w>(
+    bytes: &[u8],
+    service: &str,
+    schema: &Schema<'w>,
+) -> Result<Message<'w>, DecodeError> {
+    let mut input = CompactInput::new(bytes);
+    let protocol = input.read_byte()?;
+    if protocol != PROTOCOL_ID {
+        return Err(invalid(format!("unknown protocol id {:#x}", protocol)));
+    }
+    let byte = input.read_byte()?;
+    if byte & 0x1f != VERSION {
+        return Err(invalid(format!("unknown protocol version {}", byte & 0x1f)));
+    }
+    let type_ = MessageType::from_id(byte >> 5)?;
+    // The sequence id is a varint without zigzag, of its 32 bits as unsigned.
+    let seqid = input.read_varint()?;
+    let seqid = u32::try_from(seqid)
+        .map_err(|_| invalid(format!("sequence id {} does not fit in 32 bits", seqid)))?;
+    let seqid = seqid as i32;
+    let name = String::from_utf8_lossy(&input.read_binary()?).into_owned();
+    let envelope = (name, type_, seqid);
+    decode_message(input, envelope, service, schema)
+}
+
+pub(crate) struct CompactInput<'a> {
+    bytes: &'a [u8],
+    // The id of the last field read in the current struct, and in the structs containing it.
+    last_id: i16,
+    last_ids: Vec<i16>,
+    // The value of the bool field just read.
+    bool_value: Option<bool>,
+}
+
+impl<'a> CompactInput<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            last_id: 0,
+            last_ids: Vec::new(),
+            bool_value: None,
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Eof);
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0;
+        for i in 0..10 {
+            let byte = self.read_byte()?;
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Varint)
+    }
+
+    // A zigzag varint which must fit in `T`, named `type_`.
+    fn read_int<T: TryFrom<i64>>(&mut self, type_: &str) -> Result<T, DecodeError> {
+        let v = unzigzag(self.read_varint()?);
+        T::try_from(v).map_err(|_| invalid(format!("{} does not fit in {}", v, type_)))
+    }
+
+    fn read_size(&mut self) -> Result<usize, DecodeError> {
+        let size = self.read_varint()?;
+        match size > i32::MAX as u64 {
+            true => Err(DecodeError::Size(size as i64)),
+            false => Ok(size as usize),
+        }
+    }
+}
+
+impl<'a> Input for CompactInput<'a> {
+    fn read_struct_begin(&mut self) {
+        self.last_ids.push(self.last_id);
+        self.last_id = 0;
+    }
+
+    fn read_struct_end(&mut self) {
+        self.last_id = self.last_ids.pop().unwrap_or_default();
+    }
+
+    fn read_field(&mut self) -> Result<Option<(WireType, i16)>, DecodeError> {
+        let byte = self.read_byte()?;
+        if byte == STOP {
+            return Ok(None);
+        }
+        let type_ = byte & 0x0f;
+        let id = match byte >> 4 {
+            0 => self.read_i16()?,
+            delta => self.last_id.wrapping_add(delta.into()),
+        };
+        self.last_id = id;
+        self.bool_value = match type_ {
+            TRUE => Some(true),
+            FALSE => Some(false),
+            _ => None,
+        };
+        Ok(Some((wire_type(type_)?, id)))
+    }
+
+    fn read_map_begin(&mut self) -> Result<(WireType, WireType, usize), DecodeError> {
+        // Empty maps have no types, they do not matter.
+        let size = self.read_size()?;
+        if size == 0 {
+            return Ok((WireType::Byte, WireType::Byte, 0));
+        }
+        let types = self.read_byte()?;
+        Ok((wire_type(types >> 4)?, wire_type(types & 0x0f)?, size))
+    }
+
+    fn read_list_begin(&mut self) -> Result<(WireType, usize), DecodeError> {
+        let byte = self.read_byte()?;
+        let size = match byte >> 4 {
+            15 => self.read_size()?,
+            size => size.into(),
+        };
+        Ok((wire_type(byte & 0x0f)?, size))
+    }
+
+    fn read_bool(&mut self) -> Result<bool, DecodeError> {
+        match self.bool_value.take() {
+            Some(value) => Ok(value),
+            None => Ok(self.read_byte()? == TRUE),
+        }
+    }
+
+    fn read_i8(&mut self) -> Result<i8, DecodeError> {
+        Ok(self.read_byte()? as i8)
+    }
+
+    fn read_i16(&mut self) -> Result<i16, DecodeError> {
+        self.read_int("i16")
+    }
+
+    fn read_i32(&mut self) -> Result<i32, DecodeError> {
+        self.read_int("i32")
+    }
+
+    fn read_i64(&mut self) -> Result<i64, DecodeError> {
+        Ok(unzigzag(self.read_varint()?))
+    }
+
+    fn read_double(&mut self) -> Result<f64, DecodeError> {
+        let bytes = self.take(8)?.try_into().expect("took 8 bytes");
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let size = self.read_size()?;
+        Ok(self.take(size)?.to_vec())
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CompactOutput {
+    pub(crate) bytes: Vec<u8>,
+    last_id: i16,
+    last_ids: Vec<i16>,
+    // The id of the bool field to write with its value.
+    bool_field: Option<i16>,
+}
+
+impl CompactOutput {
+    fn write_varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.bytes.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.bytes.push(v as u8);
+    }
+
+    fn write_field_header(&mut self, type_: u8, id: i16) {
+        let delta = i32::from(id) - i32::from(self.last_id);
+        match (1..=15).contains(&delta) {
+            true => self.bytes.push((delta as u8) << 4 | type_),
+            false => {
+                self.bytes.push(type_);
+                self.write_i16(id);
+            }
+        }
+        self.last_id = id;
+    }
+}
+
+impl Output for CompactOutput {
+    fn write_struct_begin(&mut self) {
+        self.last_ids.push(self.last_id);
+        self.last_id = 0;
+    }
+
+    fn write_struct_end(&mut self) {
+        self.last_id = self.last_ids.pop().unwrap_or_default();
+    }
+
+    fn write_field(&mut self, type_: WireType, id: i16) {
+        match type_ {
+            WireType::Bool => self.bool_field = Some(id),
+            _ => self.write_field_header(type_id(type_), id),
+        }
+    }
+
+    fn write_stop(&mut self) {
+        self.bytes.push(STOP);
+    }
+
+    fn write_map_begin(&mut self, key: WireType, value: WireType, size: usize) {
+        self.write_varint(size as u64);
+        if size > 0 {
+            self.bytes.push(type_id(key) << 4 | type_id(value));
+        }
+    }
+
+    fn write_list_begin(&mut self, element: WireType, size: usize) {
+        match size < 15 {
+            true => self.bytes.push((size as u8) << 4 | type_id(element)),
+            false => {
+                self.bytes.push(0xf0 | type_id(element));
+                self.write_varint(size as u64);
+            }
+        }
+    }
+
+    fn write_bool(&mut self, v: bool) {
+        let type_ = if v { TRUE } else { FALSE };
+        match self.bool_field.take() {
+            Some(id) => self.write_field_header(type_, id),
+            None => self.bytes.push(type_),
+        }
+    }
+
+    fn write_i8(&mut self, v: i8) {
+        self.bytes.push(v as u8);
+    }
+
+    fn write_i16(&mut self, v: i16) {
+        self.write_varint(zigzag(v.into()));
+    }
+
+    fn write_i32(&mut self, v: i32) {
+        self.write_varint(zigzag(v.into()));
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        self.write_varint(zigzag(v));
+    }
+
+    fn write_double(&mut self, v: f64) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_binary(&mut self, v: &[u8]) {
+        self.write_varint(v.len() as u64);
+        self.bytes.extend_from_slice(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dynamic::test::{user, SCHEMA};
+    use crate::dynamic::FieldValue;
+    use crate::workspace::Workspace;
+
+    #[test]
+    fn test_compact() {
+        let workspace = Workspace::from_source("user.thrift", SCHEMA, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+        let user_struct = schema.find("User").unwrap();
+        let address = schema.find("Address").unwrap();
+
+        let bytes = encode_compact(&user(), &user_struct, &schema).unwrap();
+        assert_eq!(decode_compact(&bytes, &user_struct, &schema), Ok(user()));
+
+        let field = |id, value| FieldValue {
+            id,
+            name: None,
+            value,
+        };
+        let value = Value::Struct(vec![
+            field(1, Value::String("ab".into())),
+            // Bools are in the field type.
+            field(3, Value::Bool(false)),
+            // Ids too far from the last one are written in full.
+            field(100, Value::I16(-1)),
+            field(
+                101,
+                Value::List((0..15).map(|i| Value::Bool(i == 0)).collect()),
+            ),
+            field(102, Value::Map(vec![])),
+            // Ids in nested structs start from 0 again, and going back writes the id in full.
+            field(
+                103,
+                Value::Struct(vec![field(2, Value::I64(300)), field(1, Value::I32(-3))]),
+            ),
+            field(104, Value::Double(1.5)),
+        ]);
+        let bytes = encode_compact(&value, &address, &schema).unwrap();
+        let mut expected = b"\x18\x02ab\x22\x04\xc8\x01\x01\x19\xf1\x0f\x01".to_vec();
+        expected.extend_from_slice(&[FALSE; 14]);
+        expected.extend_from_slice(b"\x1b\x00\x1c\x26\xd8\x04\x05\x02\x05\x00");
+        expected.extend_from_slice(b"\x17\x00\x00\x00\x00\x00\x00\xf8\x3f\x00");
+        assert_eq!(bytes, expected);
+
+        let mut decoded = value;
+        if let Value::Struct(fields) = &mut decoded {
+            fields[0].name = Some("city".into());
+        }
+        assert_eq!(decode_compact(&bytes, &address, &schema), Ok(decoded));
+
+        assert_eq!(
+            decode_compact(b"\x18\x05ab", &address, &schema),
+            Err(DecodeError::Eof)
+        );
+        // A list field holding a list holding a list, and so on.
+        assert_eq!(
+            decode_compact(&b"\x19".repeat(100), &address, &schema),
+            Err(DecodeError::Depth)
+        );
+        assert_eq!(
+            decode_compact(
+                b"\x15\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x00",
+                &address,
+                &schema
+            ),
+            Err(DecodeError::Varint)
+        );
+        assert_eq!(
+            decode_compact(b"\x1d\x00", &address, &schema),
+            Err(DecodeError::Type(13))
+        );
+        // Integers and field ids too big for their type, instead of being truncated.
+        assert_eq!(
+            decode_compact(b"\xf4\x80\x80\x04\x00", &address, &schema),
+            Err(invalid("32768 does not fit in i16".into()))
+        );
+        assert_eq!(
+            decode_compact(b"\xf5\x80\x80\x80\x80\x10\x00", &address, &schema),
+            Err(invalid("2147483648 does not fit in i32".into()))
+        );
+        assert_eq!(
+            decode_compact(b"\x04\x80\x80\x04", &address, &schema),
+            Err(invalid("32768 does not fit in i16".into()))
+        );
+    }
+}
@@ -0,0 +1,764 @@
+// The JSON protocols:
+// - TJSONProtocol keeps what the binary protocol has. Fields are keyed by their ids and values are
+//   tagged with their types, e.g. `{"1":{"i64":7},"6":{"lst":["str",1,"a"]}}`. Bools are 1 or 0,
+//   map keys are strings and binary values are base64 without padding.
+// - TSimpleJSONProtocol is for people to read. Fields are keyed by their names, enums are their
+//   names and binary values are padded base64, e.g. `{"id":7,"kind":"BOT","tags":["a"]}`. Without
+//   the types on the wire, it is read following the schema alone.
+// Strings and binary values are both JSON strings, so binary values of fields the schema does not
+// have are read back as strings of their base64.
+
+use std::convert::TryFrom;
+
+use super::{
+    agreeing, check, element_type, invalid, struct_values, DecodeError, EncodeError, FieldValue,
+    Fields, Schema, Value, WireType, MAX_DEPTH,
+};
+use crate::definition::Struct;
+use crate::json::Json;
+use crate::resolve::{CanonicalType, SymbolKind};
+
+// The JSON levels values within `MAX_DEPTH` can take, three for each: the object of a field or the
+// array of a container, and the object of the items of a map. Deeper JSON is not parsed at all.
+const JSON_DEPTH: usize = 3 * MAX_DEPTH + 1;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn tag(type_: WireType) -> &'static str {
+    match type_ {
+        WireType::Bool => "tf",
+        WireType::Byte => "i8",
+        WireType::I16 => "i16",
+        WireType::I32 => "i32",
+        WireType::I64 => "i64",
+        WireType::Double => "dbl",
+        WireType::String => "str",
+        WireType::Struct => "rec",
+        WireType::Map => "map",
+        WireType::Set => "set",
+        WireType::List => "lst",
+    }
+}
+
+fn wire_type(tag: &str) -> Result<WireType, DecodeError> {
+    Ok(match tag {
+        "tf" => WireType::Bool,
+        "i8" => WireType::Byte,
+        "i16" => WireType::I16,
+        "i32" => WireType::I32,
+        "i64" => WireType::I64,
+        "dbl" => WireType::Double,
+        "str" => WireType::String,
+        "rec" => WireType::Struct,
+        "map" => WireType::Map,
+        "set" => WireType::Set,
+        "lst" => WireType::List,
+        _ => return Err(invalid(format!("unknown type `{}`", tag))),
+    })
+}
+
+// Decode a struct of type `struct_` from TJSONProtocol text.
+pub fn decode_json(text: &str, struct_: &Struct, schema: &Schema) -> Result<Value, DecodeError> {
+    let json = Json::parse(text, JSON_DEPTH).map_err(DecodeError::Json)?;
+    Reader::new(schema).read_struct(&json, Some((schema.program, &struct_.fields)))
+}
+
+// Encode `value`, which must be a struct of type `struct_`, in TJSONProtocol.
+pub fn encode_json(
+    value: &Value,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<String, EncodeError> {
+    let values = struct_values(value, struct_)?;
+    let writer = Writer {
+        schema,
+        simple: false,
+    };
+    Ok(writer
+        .write_struct(values, Some((schema.program, &struct_.fields)))?
+        .to_string())
+}
+
+// Decode a struct of type `struct_` from TSimpleJSONProtocol text. Fields the schema does not
+// know are errors, enums may be names or numbers.
+pub fn decode_simple_json(
+    text: &str,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<Value, DecodeError> {
+    let json = Json::parse(text, JSON_DEPTH).map_err(DecodeError::Json)?;
+    Reader::new(schema).read_simple_struct(&json, (schema.program, &struct_.fields))
+}
+
+// Encode `value`, which must be a struct of type `struct_`, in TSimpleJSONProtocol. Fields the
+// schema does not know are keyed by their names if they have them, else by their ids.
+pub fn encode_simple_json(
+    value: &Value,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<String, EncodeError> {
+    let values = struct_values(value, struct_)?;
+    simple_json(values, (schema.program, &struct_.fields), schema)
+}
+
+// The values of the fields of a struct in TSimpleJSONProtocol.
+pub(crate) fn simple_json(
+    values: &[FieldValue],
+    fields: Fields<'_>,
+    schema: &Schema,
+) -> Result<String, EncodeError> {
+    let writer = Writer {
+        schema,
+        simple: true,
+    };
+    Ok(writer.write_struct(values, Some(fields))?.to_string())
+}
+
+// Writes values in TJSONProtocol, or TSimpleJSONProtocol if `simple`.
+struct Writer<'s, 'w> {
+    schema: &'s Schema<'w>,
+    simple: bool,
+}
+
+impl<'s, 'w> Writer<'s, 'w> {
+    fn write_struct(
+        &self,
+        values: &[FieldValue],
+        fields: Option<Fields<'_>>,
+    ) -> Result<Json, EncodeError> {
+        let mut members = Vec::new();
+        for field in values {
+            let known = fields.and_then(|fields| self.schema.field(fields, field.id));
+            let type_ = known.as_ref().map(|(_, type_)| type_);
+            let value = self.write_value(&field.value, type_)?;
+            let member = match (self.simple, known.as_ref()) {
+                (true, Some((known, _))) => (known.name.to_string(), value),
+                (true, None) => match &field.name {
+                    Some(name) => (name.clone(), value),
+                    None => (field.id.to_string(), value),
+                },
+                (false, _) => {
+                    let wire = type_.map_or(field.value.wire_type(), WireType::of);
+                    let value = Json::Object(vec![(tag(wire).into(), value)]);
+                    (field.id.to_string(), value)
+                }
+            };
+            members.push(member);
+        }
+        Ok(Json::Object(members))
+    }
+
+    fn write_value(
+        &self,
+        value: &Value,
+        type_: Option<&CanonicalType>,
+    ) -> Result<Json, EncodeError> {
+        check(value, type_)?;
+        Ok(match value {
+            Value::Bool(v) if self.simple => Json::Bool(*v),
+            Value::Bool(v) => Json::Int(*v as i64),
+            Value::I8(v) => Json::Int((*v).into()),
+            Value::I16(v) => Json::Int((*v).into()),
+            Value::I32(v) => match type_ {
+                Some(CanonicalType::Named(symbol)) if self.simple => {
+                    match self.schema.enum_name(symbol, *v) {
+                        Some(name) => Json::String(name.into()),
+                        None => Json::Int((*v).into()),
+                    }
+                }
+                _ => Json::Int((*v).into()),
+            },
+            Value::I64(v) => Json::Int(*v),
+            Value::Double(v) => double(*v),
+            Value::String(v) => match type_ {
+                Some(CanonicalType::Binary) => Json::String(base64(v.as_bytes(), self.simple)),
+                _ => Json::String(v.clone()),
+            },
+            // A string the binary protocols read, which is not UTF-8.
+            Value::Binary(v) => match type_ {
+                Some(CanonicalType::String) => match std::str::from_utf8(v) {
+                    Ok(text) => Json::String(text.into()),
+                    Err(_) => return Err(EncodeError::Utf8),
+                },
+                _ => Json::String(base64(v, self.simple)),
+            },
+            Value::Struct(values) => {
+                let fields = match type_ {
+                    Some(CanonicalType::Named(symbol)) => self.schema.fields_of(symbol),
+                    _ => None,
+                };
+                self.write_struct(values, fields)?
+            }
+            Value::Map(items) => {
+                let (key, value) = match type_ {
+                    Some(CanonicalType::Map(k, v)) => (Some(&**k), Some(&**v)),
+                    _ => (None, None),
+                };
+                let key_wire = element_type(items.iter().map(|(k, _)| k), key)?;
+                let value_wire = element_type(items.iter().map(|(_, v)| v), value)?;
+                let mut members = Vec::new();
+                for (k, v) in items {
+                    members.push((self.write_key(k, key)?, self.write_value(v, value)?));
+                }
+                match self.simple {
+                    true => Json::Object(members),
+                    false => Json::Array(vec![
+                        Json::String(tag(key_wire).into()),
+                        Json::String(tag(value_wire).into()),
+                        Json::Int(items.len() as i64),
+                        Json::Object(members),
+                    ]),
+                }
+            }
+            Value::Set(items) | Value::List(items) => {
+                let element = match type_ {
+                    Some(CanonicalType::Set(x)) | Some(CanonicalType::List(x)) => Some(&**x),
+                    _ => None,
+                };
+                let wire = element_type(items.iter(), element)?;
+                let mut elements = match self.simple {
+                    true => Vec::new(),
+                    false => vec![
+                        Json::String(tag(wire).into()),
+                        Json::Int(items.len() as i64),
+                    ],
+                };
+                for item in items {
+                    elements.push(self.write_value(item, element)?);
+                }
+                Json::Array(elements)
+            }
+        })
+    }
+
+    // A map key, as the key of a JSON object.
+    fn write_key(&self, key: &Value, type_: Option<&CanonicalType>) -> Result<String, EncodeError> {
+        match self.write_value(key, type_)? {
+            Json::String(s) => Ok(s),
+            Json::Array(_) | Json::Object(_) => Err(EncodeError::Key(key.kind())),
+            json => Ok(json.to_string()),
+        }
+    }
+}
+
+// JSON has no infinity or NaN, the protocols write them as strings.
+fn double(v: f64) -> Json {
+    match v {
+        v if v.is_nan() => Json::String("NaN".into()),
+        v if v == f64::INFINITY => Json::String("Infinity".into()),
+        v if v == f64::NEG_INFINITY => Json::String("-Infinity".into()),
+        v => Json::Double(v),
+    }
+}
+
+// Reads values from parsed JSON, which is not nested too deep to recurse into.
+struct Reader<'s, 'w> {
+    schema: &'s Schema<'w>,
+    depth: usize,
+}
+
+impl<'s, 'w> Reader<'s, 'w> {
+    fn new(schema: &'s Schema<'w>) -> Self {
+        Self { schema, depth: 0 }
+    }
+
+    // Go into a struct or container, failing if it is nested too deep. Leaving it again is
+    // `self.depth -= 1`.
+    fn enter(&mut self) -> Result<(), DecodeError> {
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(DecodeError::Depth),
+            false => Ok(()),
+        }
+    }
+
+    // A TJSONProtocol struct, using the schema where the types in the JSON agree with it.
+    fn read_struct(
+        &mut self,
+        json: &Json,
+        fields: Option<Fields<'_>>,
+    ) -> Result<Value, DecodeError> {
+        let members = match json {
+            Json::Object(members) => members,
+            _ => return Err(expected("an object", json)),
+        };
+        self.enter()?;
+        let mut values = Vec::new();
+        for (key, member) in members {
+            let id = key
+                .parse()
+                .map_err(|_| invalid(format!("invalid field id `{}`", key)))?;
+            let (wire, json) = match member {
+                Json::Object(typed) if typed.len() == 1 => (wire_type(&typed[0].0)?, &typed[0].1),
+                _ => return Err(expected("an object with the type of the field", member)),
+            };
+            let field = fields
+                .and_then(|fields| self.schema.field(fields, id))
+                .filter(|(_, type_)| WireType::of(type_) == wire);
+            values.push(FieldValue {
+                id,
+                name: field.as_ref().map(|(field, _)| field.name.as_str().into()),
+                value: self.read_value(wire, json, field.as_ref().map(|(_, type_)| type_))?,
+            });
+        }
+        self.depth -= 1;
+        Ok(Value::Struct(values))
+    }
+
+    // A TJSONProtocol value of type `wire`, which `type_` agrees with if it is given.
+    fn read_value(
+        &mut self,
+        wire: WireType,
+        json: &Json,
+        type_: Option<&CanonicalType>,
+    ) -> Result<Value, DecodeError> {
+        Ok(match wire {
+            WireType::Bool => Value::Bool(integer(json)? != 0),
+            WireType::Byte => Value::I8(narrow(json)?),
+            WireType::I16 => Value::I16(narrow(json)?),
+            WireType::I32 => Value::I32(narrow(json)?),
+            WireType::I64 => Value::I64(integer(json)?),
+            WireType::Double => Value::Double(number(json)?),
+            WireType::String => match type_ {
+                Some(CanonicalType::Binary) => Value::Binary(unbase64(string(json)?)?),
+                _ => Value::String(string(json)?.into()),
+            },
+            WireType::Struct => {
+                let fields = match type_ {
+                    Some(CanonicalType::Named(symbol)) => self.schema.fields_of(symbol),
+                    _ => None,
+                };
+                self.read_struct(json, fields)?
+            }
+            WireType::Map => {
+                let (key_wire, value_wire, members) = match json {
+                    Json::Array(items) if items.len() == 4 => match &items[3] {
+                        Json::Object(members) => {
+                            check_size(&items[2], members.len())?;
+                            (
+                                wire_type(string(&items[0])?)?,
+                                wire_type(string(&items[1])?)?,
+                                members,
+                            )
+                        }
+                        json => return Err(expected("an object", json)),
+                    },
+                    _ => return Err(expected("an array of the types, size and items", json)),
+                };
+                let (key, value) = match type_ {
+                    Some(CanonicalType::Map(k, v)) => {
+                        (agreeing(k, key_wire), agreeing(v, value_wire))
+                    }
+                    _ => (None, None),
+                };
+                self.enter()?;
+                let mut items = Vec::new();
+                for (k, v) in members {
+                    let k = self.read_value(key_wire, &key_json(key_wire, k), key)?;
+                    let v = self.read_value(value_wire, v, value)?;
+                    items.push((k, v));
+                }
+                self.depth -= 1;
+                Value::Map(items)
+            }
+            WireType::Set | WireType::List => {
+                let (element_wire, elements) = match json {
+                    Json::Array(items) if items.len() >= 2 => {
+                        check_size(&items[1], items.len() - 2)?;
+                        (wire_type(string(&items[0])?)?, &items[2..])
+                    }
+                    _ => return Err(expected("an array of the type, size and items", json)),
+                };
+                let schema = match type_ {
+                    Some(CanonicalType::Set(x)) | Some(CanonicalType::List(x)) => {
+                        agreeing(x, element_wire)
+                    }
+                    _ => None,
+                };
+                self.enter()?;
+                let mut items = Vec::new();
+                for element in elements {
+                    items.push(self.read_value(element_wire, element, schema)?);
+                }
+                self.depth -= 1;
+                match wire {
+                    WireType::Set => Value::Set(items),
+                    _ => Value::List(items),
+                }
+            }
+        })
+    }
+
+    // A TSimpleJSONProtocol struct, whose fields must all be in the schema.
+    fn read_simple_struct(
+        &mut self,
+        json: &Json,
+        fields: Fields<'_>,
+    ) -> Result<Value, DecodeError> {
+        let members = match json {
+            Json::Object(members) => members,
+            _ => return Err(expected("an object", json)),
+        };
+        self.enter()?;
+        let mut values = Vec::new();
+        for (key, member) in members {
+            let (id, field, type_) = self
+                .schema
+                .field_named(fields, key)
+                .ok_or_else(|| invalid(format!("unknown field `{}`", key)))?;
+            values.push(FieldValue {
+                id,
+                name: Some(field.name.as_str().into()),
+                value: self.read_simple_value(member, &type_)?,
+            });
+        }
+        self.depth -= 1;
+        Ok(Value::Struct(values))
+    }
+
+    fn read_simple_value(
+        &mut self,
+        json: &Json,
+        type_: &CanonicalType,
+    ) -> Result<Value, DecodeError> {
+        Ok(match type_ {
+            CanonicalType::Bool => match json {
+                Json::Bool(v) => Value::Bool(*v),
+                _ => return Err(expected("a bool", json)),
+            },
+            CanonicalType::Byte | CanonicalType::I8 => Value::I8(narrow(json)?),
+            CanonicalType::I16 => Value::I16(narrow(json)?),
+            CanonicalType::I32 => Value::I32(narrow(json)?),
+            CanonicalType::I64 => Value::I64(integer(json)?),
+            CanonicalType::Double => Value::Double(number(json)?),
+            CanonicalType::String => Value::String(string(json)?.into()),
+            CanonicalType::Binary => Value::Binary(unbase64(string(json)?)?),
+            CanonicalType::Named(symbol) if symbol.kind == SymbolKind::Enum => match json {
+                Json::String(name) => match self.schema.enum_value(symbol, name) {
+                    Some(v) => Value::I32(v),
+                    None => {
+                        let message = format!("`{}` is not a value of {}", name, symbol.name);
+                        return Err(invalid(message));
+                    }
+                },
+                _ => Value::I32(narrow(json)?),
+            },
+            CanonicalType::Named(symbol) => {
+                let fields = self
+                    .schema
+                    .fields_of(symbol)
+                    .ok_or_else(|| invalid(format!("cannot find {}", symbol.name)))?;
+                self.read_simple_struct(json, fields)?
+            }
+            CanonicalType::Map(k, v) => {
+                let members = match json {
+                    Json::Object(members) => members,
+                    _ => return Err(expected("an object", json)),
+                };
+                self.enter()?;
+                let mut items = Vec::new();
+                for (key, value) in members {
+                    let key = self.read_simple_value(&key_json(WireType::of(k), key), k)?;
+                    let value = self.read_simple_value(value, v)?;
+                    items.push((key, value));
+                }
+                self.depth -= 1;
+                Value::Map(items)
+            }
+            CanonicalType::Set(x) | CanonicalType::List(x) => {
+                let elements = match json {
+                    Json::Array(elements) => elements,
+                    _ => return Err(expected("an array", json)),
+                };
+                self.enter()?;
+                let mut items = Vec::new();
+                for element in elements {
+                    items.push(self.read_simple_value(element, x)?);
+                }
+                self.depth -= 1;
+                match type_ {
+                    CanonicalType::Set(_) => Value::Set(items),
+                    _ => Value::List(items),
+                }
+            }
+        })
+    }
+}
+
+// The key of a JSON object as the value of a map key of type `wire`. Keys which are not JSON stay
+// strings, like enum names and "NaN".
+fn key_json(wire: WireType, key: &str) -> Json {
+    match wire {
+        WireType::String => Json::String(key.into()),
+        _ => Json::parse(key, JSON_DEPTH).unwrap_or_else(|_| Json::String(key.into())),
+    }
+}
+
+fn expected(what: &str, found: &Json) -> DecodeError {
+    let found = match found {
+        Json::Null => "null",
+        Json::Bool(_) => "a bool",
+        Json::Int(_) | Json::Double(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+    };
+    invalid(format!("expected {}, found {}", what, found))
+}
+
+// Check the size a map, set or list declares is the number of its items.
+fn check_size(size: &Json, items: usize) -> Result<(), DecodeError> {
+    match integer(size)? {
+        size if size == items as i64 => Ok(()),
+        size => Err(invalid(format!(
+            "size {} is declared but there are {} items",
+            size, items
+        ))),
+    }
+}
+
+fn integer(json: &Json) -> Result<i64, DecodeError> {
+    match json {
+        Json::Int(v) => Ok(*v),
+        _ => Err(expected("an integer", json)),
+    }
+}
+
+fn narrow<T: TryFrom<i64>>(json: &Json) -> Result<T, DecodeError> {
+    let v = integer(json)?;
+    T::try_from(v).map_err(|_| invalid(format!("{} is out of range", v)))
+}
+
+fn number(json: &Json) -> Result<f64, DecodeError> {
+    match json {
+        Json::Int(v) => Ok(*v as f64),
+        Json::Double(v) => Ok(*v),
+        Json::String(v) if v == "NaN" => Ok(f64::NAN),
+        Json::String(v) if v == "Infinity" => Ok(f64::INFINITY),
+        Json::String(v) if v == "-Infinity" => Ok(f64::NEG_INFINITY),
+        _ => Err(expected("a number", json)),
+    }
+}
+
+fn string(json: &Json) -> Result<&str, DecodeError> {
+    match json {
+        Json::String(v) => Ok(v),
+        _ => Err(expected("a string", json)),
+    }
+}
+
+fn base64(bytes: &[u8], pad: bool) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            out.push_str(&"=".repeat(3 - chunk.len()));
+        }
+    }
+    out
+}
+
+// Base64 with or without padding.
+fn unbase64(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let error = || invalid(format!("invalid base64 `{}`", text));
+    let digits = text.trim_end_matches('=').as_bytes();
+    let mut out = Vec::new();
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(error());
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let digit = BASE64.iter().position(|&x| x == c).ok_or_else(error)?;
+            n |= (digit as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dynamic::test::{user, SCHEMA};
+    use crate::dynamic::{decode_binary, encode_binary};
+    use crate::workspace::Workspace;
+
+    #[test]
+    fn test_json() {
+        let workspace = Workspace::from_source("user.thrift", SCHEMA, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+        let user_struct = schema.find("User").unwrap();
+
+        let text = encode_json(&user(), &user_struct, &schema).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                r#"{"1":{"i64":7},"2":{"str":"ann"},"3":{"tf":1},"4":{"i32":2},"#,
+                r#""5":{"map":["str","rec",1,{"home":{"1":{"str":"Paris"}}}]},"#,
+                r#""6":{"lst":["str",1,"a"]},"7":{"str":"/wA"},"8":{"set":["i16",2,-1,300]},"#,
+                r#""9":{"dbl":-2.5},"10":{"rec":{"1":{"i64":1},"8":{"set":["i16",0]}}}}"#
+            )
+        );
+        assert_eq!(decode_json(&text, &user_struct, &schema), Ok(user()));
+
+        // From JSON to the binary protocol and back.
+        let bytes = encode_binary(
+            &decode_json(&text, &user_struct, &schema).unwrap(),
+            &user_struct,
+            &schema,
+        )
+        .unwrap();
+        let value = decode_binary(&bytes, &user_struct, &schema).unwrap();
+        assert_eq!(encode_json(&value, &user_struct, &schema), Ok(text));
+
+        // Unknown fields have no name, padding and non-finite doubles are read.
+        let value = decode_json(
+            r#"{"7":{"str":"/w=="},"9":{"dbl":"NaN"},"20":{"map":["i32","tf",1,{"-3":0}]}}"#,
+            &user_struct,
+            &schema,
+        )
+        .unwrap();
+        match value {
+            Value::Struct(fields) => {
+                assert_eq!(fields[0].value, Value::Binary(vec![0xff]));
+                assert!(matches!(fields[1].value, Value::Double(v) if v.is_nan()));
+                assert_eq!(fields[2].name, None);
+                assert_eq!(
+                    fields[2].value,
+                    Value::Map(vec![(Value::I32(-3), Value::Bool(false))])
+                );
+            }
+            value => panic!("not a struct: {:?}", value),
+        }
+
+        assert_eq!(
+            decode_json(r#"{"1":{"i64":"7"}}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "expected an integer, found a string"
+        );
+        assert_eq!(
+            decode_json(r#"{"1":{"int":7}}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown type `int`"
+        );
+        assert_eq!(
+            decode_json("{\"1\":\n{\"i64\" 7}}", &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "invalid JSON at 2:8: expected ':'"
+        );
+        assert_eq!(
+            decode_json(r#"{"6":{"lst":["str",2,"a"]}}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "size 2 is declared but there are 1 items"
+        );
+        assert_eq!(
+            decode_json(r#"{"5":{"map":["str","rec",1,{}]}}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "size 1 is declared but there are 0 items"
+        );
+        // A string which is not UTF-8 has no JSON, and a binary value of an unknown field comes
+        // back as its base64.
+        let field = |id, value| FieldValue {
+            id,
+            name: None,
+            value,
+        };
+        let value = Value::Struct(vec![field(2, Value::Binary(vec![0xff]))]);
+        assert_eq!(
+            encode_json(&value, &user_struct, &schema),
+            Err(EncodeError::Utf8)
+        );
+        let value = Value::Struct(vec![field(2, Value::Binary(b"ann".to_vec()))]);
+        let text = encode_json(&value, &user_struct, &schema).unwrap();
+        assert_eq!(text, r#"{"2":{"str":"ann"}}"#);
+        let value = Value::Struct(vec![field(20, Value::Binary(vec![0xff]))]);
+        let text = encode_json(&value, &user_struct, &schema).unwrap();
+        assert_eq!(text, r#"{"20":{"str":"/w"}}"#);
+        assert_eq!(
+            decode_json(&text, &user_struct, &schema),
+            Ok(Value::Struct(vec![field(20, Value::String("/w".into()))]))
+        );
+
+        // Structs nest as deep as in the binary protocols, and JSON too deep for them is not
+        // parsed at all.
+        let nested = |n| format!("{}{{}}{}", r#"{"10":{"rec":"#.repeat(n), "}}".repeat(n));
+        assert!(decode_json(&nested(MAX_DEPTH - 1), &user_struct, &schema).is_ok());
+        assert_eq!(
+            decode_json(&nested(MAX_DEPTH), &user_struct, &schema),
+            Err(DecodeError::Depth)
+        );
+        assert!(matches!(
+            decode_json(&nested(200), &user_struct, &schema),
+            Err(DecodeError::Json(e)) if e.message == "values nested too deep"
+        ));
+    }
+
+    #[test]
+    fn test_simple_json() {
+        let workspace = Workspace::from_source("user.thrift", SCHEMA, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+        let user_struct = schema.find("User").unwrap();
+
+        let text = encode_simple_json(&user(), &user_struct, &schema).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                r#"{"id":7,"name":"ann","admin":true,"kind":"BOT","#,
+                r#""addresses":{"home":{"city":"Paris"}},"tags":["a"],"avatar":"/wA=","#,
+                r#""groups":[-1,300],"score":-2.5,"manager":{"id":1,"groups":[]}}"#
+            )
+        );
+        assert_eq!(decode_simple_json(&text, &user_struct, &schema), Ok(user()));
+
+        // Enums may be numbers, and numbers outside of the enum are written as they are.
+        let value = decode_simple_json(r#"{"kind":7}"#, &user_struct, &schema).unwrap();
+        assert_eq!(
+            encode_simple_json(&value, &user_struct, &schema),
+            Ok(r#"{"kind":7}"#.into())
+        );
+
+        // Maps keyed by structs cannot be written.
+        let value = Value::Struct(vec![FieldValue {
+            id: 20,
+            name: Some("extra".into()),
+            value: Value::Map(vec![(Value::List(vec![]), Value::I8(1))]),
+        }]);
+        assert_eq!(
+            encode_simple_json(&value, &user_struct, &schema),
+            Err(EncodeError::Key("list"))
+        );
+
+        assert_eq!(
+            decode_simple_json(r#"{"kind":"ROBOT"}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "`ROBOT` is not a value of Kind"
+        );
+        assert_eq!(
+            decode_simple_json(r#"{"age":1}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown field `age`"
+        );
+        assert_eq!(
+            decode_simple_json(r#"{"groups":[70000]}"#, &user_struct, &schema)
+                .unwrap_err()
+                .to_string(),
+            "70000 is out of range"
+        );
+    }
+}
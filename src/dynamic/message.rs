@@ -0,0 +1,349 @@
+// Messages of service calls: an envelope with the name of the function, the type of the message
+// and a sequence id, followed by a struct. Calls carry the arguments as the struct
+// `<function>_args`, replies carry `<function>_result`, whose field 0 `success` is the returned
+// value and whose other fields are the exceptions thrown. Exceptions carry a
+// TApplicationException.
+
+use std::fmt;
+
+use super::json::simple_json;
+use super::{invalid, DecodeError, Decoder, EncodeError, Input, Schema, Value};
+use crate::basic::Identifier;
+use crate::constant::IntConstant;
+use crate::definition::{Definition, Struct};
+use crate::field::Field;
+use crate::functions::Function;
+use crate::resolve::ServiceError;
+use crate::span::Span;
+use crate::types::FieldType;
+use crate::workspace::Program;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Call,
+    Reply,
+    Exception,
+    Oneway,
+}
+
+impl MessageType {
+    // The type with `id` in the envelope, the same in every protocol.
+    pub(crate) fn from_id(id: u8) -> Result<Self, DecodeError> {
+        Ok(match id {
+            1 => MessageType::Call,
+            2 => MessageType::Reply,
+            3 => MessageType::Exception,
+            4 => MessageType::Oneway,
+            _ => return Err(invalid(format!("unknown message type {}", id))),
+        })
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MessageType::Call => "CALL",
+            MessageType::Reply => "REPLY",
+            MessageType::Exception => "EXCEPTION",
+            MessageType::Oneway => "ONEWAY",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message<'w> {
+    pub name: String,
+    pub type_: MessageType,
+    pub seqid: i32,
+    // The struct the body is decoded as, `<name>_args`, `<name>_result` or
+    // `TApplicationException`.
+    pub struct_: Struct,
+    pub body: Value,
+    // The program the types of the fields of `struct_` are looked up in.
+    program: &'w Program,
+}
+
+impl<'w> Message<'w> {
+    // The message on a line, with the body in TSimpleJSONProtocol, e.g.
+    // `CALL get #1 {"id":7}`. `schema` is the one the message is decoded with.
+    pub fn render(&self, schema: &Schema<'w>) -> Result<String, EncodeError> {
+        let values = match &self.body {
+            Value::Struct(values) => values,
+            body => {
+                return Err(EncodeError::Mismatch {
+                    expected: self.struct_.name.to_string(),
+                    found: body.kind(),
+                })
+            }
+        };
+        let body = simple_json(values, (self.program, &self.struct_.fields), schema)?;
+        Ok(format!(
+            "{} {} #{} {}",
+            self.type_, self.name, self.seqid, body
+        ))
+    }
+}
+
+// Decode the struct of a message whose envelope is read already from `input`. The function
+// `name` is looked up in `service`, which is looked up in the program of `schema`, and in the
+// services it extends.
+pub(crate) fn decode_message<'w, I: Input>(
+    input: I,
+    envelope: (String, MessageType, i32),
+    service: &str,
+    schema: &Schema<'w>,
+) -> Result<Message<'w>, DecodeError> {
+    let (name, type_, seqid) = envelope;
+    let (struct_, program) = match type_ {
+        MessageType::Exception => (application_exception(), schema.program),
+        _ => {
+            let (function, program) = find_function(schema, service, &name)?;
+            let struct_ = match type_ {
+                MessageType::Reply => result_struct(function),
+                _ => args_struct(function),
+            };
+            (struct_, program)
+        }
+    };
+    let mut decoder = Decoder::new(schema, input);
+    let body = decoder.read_struct(Some((program, &struct_.fields)))?;
+    match decoder.into_input().remaining() {
+        0 => Ok(Message {
+            name,
+            type_,
+            seqid,
+            struct_,
+            body,
+            program,
+        }),
+        n => Err(DecodeError::Trailing(n)),
+    }
+}
+
+// The function named `name` of `service` or a service it extends, with the program defining it.
+fn find_function<'w>(
+    schema: &Schema<'w>,
+    service: &str,
+    name: &str,
+) -> Result<(&'w Function, &'w Program), DecodeError> {
+    let workspace = schema.workspace;
+    let symbol = schema
+        .resolver
+        .resolve_service(schema.program, service)
+        .map_err(|error| {
+            let name = service.into();
+            invalid(ServiceError::Resolve { name, error }.to_string())
+        })?;
+    let found = match (workspace.get(&symbol.path), symbol.definition(workspace)) {
+        (Some(program), Some(Definition::Service(x))) => schema
+            .resolver
+            .service_functions(workspace, program, x)
+            .map_err(|e| invalid(e.to_string()))?
+            .into_iter()
+            .find(|f| f.function.name.as_str() == name),
+        _ => None,
+    };
+    let found =
+        found.ok_or_else(|| invalid(format!("no function `{}` in service {}", name, service)))?;
+    let program = workspace
+        .get(&found.origin.path)
+        .ok_or_else(|| invalid(format!("cannot find {}", found.origin)))?;
+    Ok((found.function, program))
+}
+
+fn args_struct(function: &Function) -> Struct {
+    synthesized(
+        format!("{}_args", function.name.as_str()),
+        function.parameters.clone(),
+    )
+}
+
+fn result_struct(function: &Function) -> Struct {
+    let mut fields = Vec::new();
+    if let Some(returns) = &function.returns {
+        fields.push(field(0, "success", returns.clone()));
+    }
+    fields.extend(function.exceptions.iter().flatten().cloned());
+    synthesized(format!("{}_result", function.name.as_str()), fields)
+}
+
+// What servers reply with when they fail to handle a call.
+fn application_exception() -> Struct {
+    synthesized(
+        "TApplicationException".into(),
+        vec![
+            field(1, "message", FieldType::String),
+            field(2, "type", FieldType::I32),
+        ],
+    )
+}
+
+fn synthesized(name: String, fields: Vec<Field>) -> Struct {
+    Struct {
+        name: Identifier::from(name),
+        fields,
+        annotations: None,
+        doc: None,
+        span: Span::default(),
+    }
+}
+
+fn field(id: i64, name: &str, type_: FieldType) -> Field {
+    Field {
+        id: Some(IntConstant::from(id)),
+        required: None,
+        type_,
+        name: Identifier::from(String::from(name)),
+        default: None,
+        annotations: None,
+        doc: None,
+        span: Span::default(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dynamic::test::SCHEMA;
+    use crate::dynamic::{decode_binary_message, decode_compact_message};
+    use crate::workspace::Workspace;
+
+    const SERVICES: &str = r#"
+exception NotFound { 1: i64 id }
+
+service Base {
+    i32 ping()
+}
+
+service Users extends Base {
+    User get(1: i64 id, 2: optional Kind kind) throws (1: NotFound missing)
+    oneway void forget(1: i64 id)
+}
+"#;
+
+    #[test]
+    fn test_binary_message() {
+        let source = format!("{}{}", SCHEMA, SERVICES);
+        let workspace = Workspace::from_source("user.thrift", &source, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+
+        let mut bytes = b"\x80\x01\x00\x01\x00\x00\x00\x03get\x00\x00\x00\x07".to_vec();
+        bytes.extend_from_slice(b"\x0a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x07");
+        bytes.extend_from_slice(b"\x08\x00\x02\x00\x00\x00\x02\x00");
+        let message = decode_binary_message(&bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            (message.name.as_str(), message.type_, message.seqid),
+            ("get", MessageType::Call, 7)
+        );
+        assert_eq!(message.struct_.name.as_str(), "get_args");
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"CALL get #7 {"id":7,"kind":"BOT"}"#
+        );
+
+        // Functions of extended services are found, and the old envelope without a version is
+        // read.
+        let bytes = b"\x00\x00\x00\x04ping\x02\x00\x00\x00\x01\x08\x00\x00\x00\x00\x00\x01\x00";
+        let message = decode_binary_message(bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"REPLY ping #1 {"success":1}"#
+        );
+
+        let mut bytes = b"\x80\x01\x00\x02\x00\x00\x00\x03get\x00\x00\x00\x02".to_vec();
+        bytes
+            .extend_from_slice(b"\x0c\x00\x01\x0a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x03\x00\x00");
+        let message = decode_binary_message(&bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"REPLY get #2 {"missing":{"id":3}}"#
+        );
+
+        let mut bytes = b"\x80\x01\x00\x03\x00\x00\x00\x03get\x00\x00\x00\x03".to_vec();
+        bytes
+            .extend_from_slice(b"\x0b\x00\x01\x00\x00\x00\x04boom\x08\x00\x02\x00\x00\x00\x01\x00");
+        let message = decode_binary_message(&bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"EXCEPTION get #3 {"message":"boom","type":1}"#
+        );
+
+        let bytes = b"\x80\x01\x00\x01\x00\x00\x00\x04nope\x00\x00\x00\x01\x00";
+        assert_eq!(
+            decode_binary_message(bytes, "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "no function `nope` in service Users"
+        );
+        assert_eq!(
+            decode_binary_message(bytes, "Groups", &schema)
+                .unwrap_err()
+                .to_string(),
+            "cannot find service `Groups`"
+        );
+        assert_eq!(
+            decode_binary_message(b"\x80\x02\x00\x01", "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown protocol version 0x8002"
+        );
+        assert_eq!(
+            decode_binary_message(b"\x80\x01\x00\x05", "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown message type 5"
+        );
+    }
+
+    #[test]
+    fn test_compact_message() {
+        let source = format!("{}{}", SCHEMA, SERVICES);
+        let workspace = Workspace::from_source("user.thrift", &source, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+
+        let bytes = b"\x82\x21\x07\x03get\x16\x0e\x15\x04\x00";
+        let message = decode_compact_message(bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"CALL get #7 {"id":7,"kind":"BOT"}"#
+        );
+
+        // Sequence ids are varints without zigzag.
+        let bytes = b"\x82\x81\xac\x02\x06forget\x16\x02\x00";
+        let message = decode_compact_message(bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"ONEWAY forget #300 {"id":1}"#
+        );
+
+        let bytes = b"\x82\x81\xff\xff\xff\xff\x0f\x06forget\x16\x02\x00";
+        let message = decode_compact_message(bytes, "Users", &schema).unwrap();
+        assert_eq!(
+            message.render(&schema).unwrap(),
+            r#"ONEWAY forget #-1 {"id":1}"#
+        );
+        assert_eq!(
+            decode_compact_message(b"\x82\x81\xff\xff\xff\xff\x1f", "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "sequence id 8589934591 does not fit in 32 bits"
+        );
+
+        assert!(matches!(
+            decode_compact_message(b"\x82\x21\x07\x03get\x00\x00", "Users", &schema),
+            Err(DecodeError::Trailing(1))
+        ));
+        assert_eq!(
+            decode_compact_message(b"\x80\x21", "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown protocol id 0x80"
+        );
+        assert_eq!(
+            decode_compact_message(b"\x82\x22", "Users", &schema)
+                .unwrap_err()
+                .to_string(),
+            "unknown protocol version 2"
+        );
+    }
+}
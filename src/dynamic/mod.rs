@@ -0,0 +1,763 @@
+// Reading and writing Thrift values without generated code, following the parsed definitions.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::definition::{Definition, Struct};
+use crate::field::Field;
+use crate::resolve::{CanonicalType, Resolver, Symbol, SymbolKind};
+use crate::validate::field_ids;
+use crate::workspace::{Program, Workspace};
+use crate::ParseError;
+
+mod binary;
+mod compact;
+mod json;
+mod message;
+
+pub use binary::{decode_binary, decode_binary_message, encode_binary};
+pub use compact::{decode_compact, decode_compact_message, encode_compact};
+pub use json::{decode_json, decode_simple_json, encode_json, encode_simple_json};
+pub use message::{Message, MessageType};
+
+// Values nested deeper than this are rejected when decoding in any protocol, instead of
+// overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+// A Thrift value. Enums are i32, unions and exceptions are structs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Struct(Vec<FieldValue>),
+    Map(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    List(Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldValue {
+    pub id: i16,
+    // The name of the field, None for decoded fields the schema does not know.
+    pub name: Option<String>,
+    pub value: Value,
+}
+
+impl Value {
+    // The name of the kind of the value, for messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::I8(_) => "i8",
+            Value::I16(_) => "i16",
+            Value::I32(_) => "i32",
+            Value::I64(_) => "i64",
+            Value::Double(_) => "double",
+            Value::String(_) => "string",
+            Value::Binary(_) => "binary",
+            Value::Struct(_) => "struct",
+            Value::Map(_) => "map",
+            Value::Set(_) => "set",
+            Value::List(_) => "list",
+        }
+    }
+
+    fn wire_type(&self) -> WireType {
+        match self {
+            Value::Bool(_) => WireType::Bool,
+            Value::I8(_) => WireType::Byte,
+            Value::I16(_) => WireType::I16,
+            Value::I32(_) => WireType::I32,
+            Value::I64(_) => WireType::I64,
+            Value::Double(_) => WireType::Double,
+            Value::String(_) | Value::Binary(_) => WireType::String,
+            Value::Struct(_) => WireType::Struct,
+            Value::Map(_) => WireType::Map,
+            Value::Set(_) => WireType::Set,
+            Value::List(_) => WireType::List,
+        }
+    }
+}
+
+// The types values have on the wire, every protocol has its own ids for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WireType {
+    Bool,
+    Byte,
+    Double,
+    I16,
+    I32,
+    I64,
+    String,
+    Struct,
+    Map,
+    Set,
+    List,
+}
+
+impl WireType {
+    fn of(type_: &CanonicalType) -> Self {
+        match type_ {
+            CanonicalType::Bool => WireType::Bool,
+            CanonicalType::Byte | CanonicalType::I8 => WireType::Byte,
+            CanonicalType::I16 => WireType::I16,
+            CanonicalType::I32 => WireType::I32,
+            CanonicalType::I64 => WireType::I64,
+            CanonicalType::Double => WireType::Double,
+            CanonicalType::String | CanonicalType::Binary => WireType::String,
+            CanonicalType::Map(..) => WireType::Map,
+            CanonicalType::Set(_) => WireType::Set,
+            CanonicalType::List(_) => WireType::List,
+            CanonicalType::Named(symbol) if symbol.kind == SymbolKind::Enum => WireType::I32,
+            CanonicalType::Named(_) => WireType::Struct,
+        }
+    }
+}
+
+// The definitions values are read and written with.
+pub struct Schema<'w> {
+    workspace: &'w Workspace,
+    program: &'w Program,
+    resolver: Resolver,
+}
+
+impl<'w> Schema<'w> {
+    // Names in the top-level struct are looked up in `program`, which is in `workspace`.
+    pub fn new(workspace: &'w Workspace, program: &'w Program) -> Self {
+        Self {
+            workspace,
+            program,
+            resolver: Resolver::new(workspace),
+        }
+    }
+
+    // The struct, union or exception named `name` in the program, as a struct.
+    pub fn find(&self, name: &str) -> Option<Struct> {
+        let symbol = self.resolver.resolve_type(self.program, name).ok()?;
+        match symbol.definition(self.workspace)? {
+            Definition::Struct(x) => Some(x.clone()),
+            Definition::Union(x) => Some(Struct {
+                name: x.name.clone(),
+                fields: x.fields.clone(),
+                annotations: x.annotations.clone(),
+                doc: x.doc.clone(),
+                span: x.span,
+            }),
+            Definition::Exception(x) => Some(Struct {
+                name: x.name.clone(),
+                fields: x.fields.clone(),
+                annotations: x.annotations.clone(),
+                doc: x.doc.clone(),
+                span: x.span,
+            }),
+            _ => None,
+        }
+    }
+
+    // The fields of a struct, union or exception, with the file defining it.
+    fn fields_of(&self, symbol: &Symbol) -> Option<Fields<'w>> {
+        let program = self.workspace.get(&symbol.path)?;
+        match symbol.definition(self.workspace)? {
+            Definition::Struct(x) => Some((program, &x.fields)),
+            Definition::Union(x) => Some((program, &x.fields)),
+            Definition::Exception(x) => Some((program, &x.fields)),
+            _ => None,
+        }
+    }
+
+    // The field with `id` and its type, if it is known.
+    fn field<'f>(
+        &self,
+        fields: (&Program, &'f [Field]),
+        id: i16,
+    ) -> Option<(&'f Field, CanonicalType)> {
+        let (program, fields) = fields;
+        let i = field_ids(fields).iter().position(|&x| x == id as i64)?;
+        let type_ = self
+            .resolver
+            .canonical_type(self.workspace, program, &fields[i].type_)
+            .ok()?;
+        Some((&fields[i], type_))
+    }
+
+    // The field named `name` with its id and type, if it is known.
+    fn field_named<'f>(
+        &self,
+        fields: (&Program, &'f [Field]),
+        name: &str,
+    ) -> Option<(i16, &'f Field, CanonicalType)> {
+        let (program, fields) = fields;
+        let i = fields.iter().position(|x| x.name.as_str() == name)?;
+        let id = i16::try_from(field_ids(fields)[i]).ok()?;
+        let type_ = self
+            .resolver
+            .canonical_type(self.workspace, program, &fields[i].type_)
+            .ok()?;
+        Some((id, &fields[i], type_))
+    }
+
+    // The name of `value` in the enum `symbol`.
+    fn enum_name(&self, symbol: &Symbol, value: i32) -> Option<&'w str> {
+        match symbol.definition(self.workspace)? {
+            Definition::Enum(x) => x
+                .numbered()
+                .into_iter()
+                .find(|(_, v)| *v == i128::from(value))
+                .map(|(child, _)| child.name.as_str()),
+            _ => None,
+        }
+    }
+
+    // The value named `name` in the enum `symbol`.
+    fn enum_value(&self, symbol: &Symbol, name: &str) -> Option<i32> {
+        match symbol.definition(self.workspace)? {
+            Definition::Enum(x) => x
+                .numbered()
+                .into_iter()
+                .find(|(child, _)| child.name.as_str() == name)
+                .and_then(|(_, v)| i32::try_from(v).ok()),
+            _ => None,
+        }
+    }
+}
+
+// The fields of a struct and the file they are defined in, which their types are looked up in.
+type Fields<'a> = (&'a Program, &'a [Field]);
+
+// What a protocol reads values from.
+pub(crate) trait Input {
+    fn read_struct_begin(&mut self) {}
+    // Called after the end of a struct is read.
+    fn read_struct_end(&mut self) {}
+    // The type and id of the next field of a struct, None at the end of the struct.
+    fn read_field(&mut self) -> Result<Option<(WireType, i16)>, DecodeError>;
+    // The types of the keys and values, and the size.
+    fn read_map_begin(&mut self) -> Result<(WireType, WireType, usize), DecodeError>;
+    // The type of the elements and the size, of a set or list.
+    fn read_list_begin(&mut self) -> Result<(WireType, usize), DecodeError>;
+    fn read_bool(&mut self) -> Result<bool, DecodeError>;
+    fn read_i8(&mut self) -> Result<i8, DecodeError>;
+    fn read_i16(&mut self) -> Result<i16, DecodeError>;
+    fn read_i32(&mut self) -> Result<i32, DecodeError>;
+    fn read_i64(&mut self) -> Result<i64, DecodeError>;
+    fn read_double(&mut self) -> Result<f64, DecodeError>;
+    fn read_binary(&mut self) -> Result<Vec<u8>, DecodeError>;
+    // The number of bytes left.
+    fn remaining(&self) -> usize;
+}
+
+// What a protocol writes values to.
+pub(crate) trait Output {
+    fn write_struct_begin(&mut self) {}
+    // Called after the end of a struct is written.
+    fn write_struct_end(&mut self) {}
+    fn write_field(&mut self, type_: WireType, id: i16);
+    // End a struct.
+    fn write_stop(&mut self);
+    fn write_map_begin(&mut self, key: WireType, value: WireType, size: usize);
+    fn write_list_begin(&mut self, element: WireType, size: usize);
+    fn write_bool(&mut self, v: bool);
+    fn write_i8(&mut self, v: i8);
+    fn write_i16(&mut self, v: i16);
+    fn write_i32(&mut self, v: i32);
+    fn write_i64(&mut self, v: i64);
+    fn write_double(&mut self, v: f64);
+    fn write_binary(&mut self, v: &[u8]);
+}
+
+// Decode a struct of type `struct_`, whose types are looked up in the program of `schema`, from
+// all of `input`.
+fn decode<I: Input>(input: I, struct_: &Struct, schema: &Schema) -> Result<Value, DecodeError> {
+    let mut decoder = Decoder::new(schema, input);
+    let value = decoder.read_struct(Some((schema.program, &struct_.fields)))?;
+    match decoder.into_input().remaining() {
+        0 => Ok(value),
+        n => Err(DecodeError::Trailing(n)),
+    }
+}
+
+// Encode `value`, which must be a struct of type `struct_`, to `output`.
+fn encode<O: Output>(
+    output: O,
+    value: &Value,
+    struct_: &Struct,
+    schema: &Schema,
+) -> Result<O, EncodeError> {
+    let mut encoder = Encoder::new(schema, output);
+    encoder.write_struct(
+        struct_values(value, struct_)?,
+        Some((schema.program, &struct_.fields)),
+    )?;
+    Ok(encoder.into_output())
+}
+
+// The fields of `value`, which must be a struct of type `struct_`.
+fn struct_values<'v>(value: &'v Value, struct_: &Struct) -> Result<&'v [FieldValue], EncodeError> {
+    match value {
+        Value::Struct(fields) => Ok(fields),
+        _ => Err(EncodeError::Mismatch {
+            expected: struct_.name.to_string(),
+            found: value.kind(),
+        }),
+    }
+}
+
+// Reads values, using the schema where the types on the wire agree with it. Fields the schema
+// does not know, or with other types than it gives, are still read but have no name.
+pub(crate) struct Decoder<'s, 'w, I> {
+    schema: &'s Schema<'w>,
+    input: I,
+    depth: usize,
+}
+
+impl<'s, 'w, I: Input> Decoder<'s, 'w, I> {
+    pub(crate) fn new(schema: &'s Schema<'w>, input: I) -> Self {
+        Self {
+            schema,
+            input,
+            depth: 0,
+        }
+    }
+
+    pub(crate) fn into_input(self) -> I {
+        self.input
+    }
+
+    // Go into a struct or container, failing if it is nested too deep. Leaving it again is
+    // `self.depth -= 1`.
+    fn enter(&mut self) -> Result<(), DecodeError> {
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(DecodeError::Depth),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn read_struct(&mut self, fields: Option<Fields<'_>>) -> Result<Value, DecodeError> {
+        self.enter()?;
+        self.input.read_struct_begin();
+        let mut values = Vec::new();
+        while let Some((wire, id)) = self.input.read_field()? {
+            let field = fields
+                .and_then(|fields| self.schema.field(fields, id))
+                .filter(|(_, type_)| WireType::of(type_) == wire);
+            let value = match field {
+                Some((field, type_)) => FieldValue {
+                    id,
+                    name: Some(field.name.as_str().into()),
+                    value: self.read_value(wire, Some(&type_))?,
+                },
+                None => FieldValue {
+                    id,
+                    name: None,
+                    value: self.read_value(wire, None)?,
+                },
+            };
+            values.push(value);
+        }
+        self.input.read_struct_end();
+        self.depth -= 1;
+        Ok(Value::Struct(values))
+    }
+
+    // Read a value of type `wire`, which `type_` agrees with if it is given.
+    fn read_value(
+        &mut self,
+        wire: WireType,
+        type_: Option<&CanonicalType>,
+    ) -> Result<Value, DecodeError> {
+        Ok(match wire {
+            WireType::Bool => Value::Bool(self.input.read_bool()?),
+            WireType::Byte => Value::I8(self.input.read_i8()?),
+            WireType::I16 => Value::I16(self.input.read_i16()?),
+            WireType::I32 => Value::I32(self.input.read_i32()?),
+            WireType::I64 => Value::I64(self.input.read_i64()?),
+            WireType::Double => Value::Double(self.input.read_double()?),
+            // Strings which are not UTF-8 are kept as binary.
+            WireType::String => match (type_, self.input.read_binary()?) {
+                (Some(CanonicalType::Binary), bytes) => Value::Binary(bytes),
+                (_, bytes) => match String::from_utf8(bytes) {
+                    Ok(s) => Value::String(s),
+                    Err(e) => Value::Binary(e.into_bytes()),
+                },
+            },
+            WireType::Struct => {
+                let fields = match type_ {
+                    Some(CanonicalType::Named(symbol)) => self.schema.fields_of(symbol),
+                    _ => None,
+                };
+                self.read_struct(fields)?
+            }
+            WireType::Map => {
+                self.enter()?;
+                let (key_wire, value_wire, size) = self.input.read_map_begin()?;
+                let (key, value) = match type_ {
+                    Some(CanonicalType::Map(k, v)) => {
+                        (agreeing(k, key_wire), agreeing(v, value_wire))
+                    }
+                    _ => (None, None),
+                };
+                let mut items = Vec::new();
+                for _ in 0..size {
+                    let k = self.read_value(key_wire, key)?;
+                    items.push((k, self.read_value(value_wire, value)?));
+                }
+                self.depth -= 1;
+                Value::Map(items)
+            }
+            WireType::Set | WireType::List => {
+                self.enter()?;
+                let (element_wire, size) = self.input.read_list_begin()?;
+                let schema = match type_ {
+                    Some(CanonicalType::Set(x)) | Some(CanonicalType::List(x)) => {
+                        agreeing(x, element_wire)
+                    }
+                    _ => None,
+                };
+                let mut items = Vec::new();
+                for _ in 0..size {
+                    items.push(self.read_value(element_wire, schema)?);
+                }
+                self.depth -= 1;
+                match wire {
+                    WireType::Set => Value::Set(items),
+                    _ => Value::List(items),
+                }
+            }
+        })
+    }
+}
+
+// Writes values, checking they have the types the schema gives. Fields the schema does not know
+// are written with the types of their values.
+pub(crate) struct Encoder<'s, 'w, O> {
+    schema: &'s Schema<'w>,
+    output: O,
+}
+
+impl<'s, 'w, O: Output> Encoder<'s, 'w, O> {
+    pub(crate) fn new(schema: &'s Schema<'w>, output: O) -> Self {
+        Self { schema, output }
+    }
+
+    pub(crate) fn into_output(self) -> O {
+        self.output
+    }
+
+    pub(crate) fn write_struct(
+        &mut self,
+        values: &[FieldValue],
+        fields: Option<Fields<'_>>,
+    ) -> Result<(), EncodeError> {
+        self.output.write_struct_begin();
+        for field in values {
+            let type_ = fields
+                .and_then(|fields| self.schema.field(fields, field.id))
+                .map(|(_, type_)| type_);
+            let wire = match &type_ {
+                Some(type_) => WireType::of(type_),
+                None => field.value.wire_type(),
+            };
+            self.output.write_field(wire, field.id);
+            self.write_value(&field.value, type_.as_ref())?;
+        }
+        self.output.write_stop();
+        self.output.write_struct_end();
+        Ok(())
+    }
+
+    pub(crate) fn write_value(
+        &mut self,
+        value: &Value,
+        type_: Option<&CanonicalType>,
+    ) -> Result<(), EncodeError> {
+        check(value, type_)?;
+        match value {
+            Value::Bool(v) => self.output.write_bool(*v),
+            Value::I8(v) => self.output.write_i8(*v),
+            Value::I16(v) => self.output.write_i16(*v),
+            Value::I32(v) => self.output.write_i32(*v),
+            Value::I64(v) => self.output.write_i64(*v),
+            Value::Double(v) => self.output.write_double(*v),
+            Value::String(v) => self.output.write_binary(v.as_bytes()),
+            Value::Binary(v) => self.output.write_binary(v),
+            Value::Struct(values) => {
+                let fields = match type_ {
+                    Some(CanonicalType::Named(symbol)) => self.schema.fields_of(symbol),
+                    _ => None,
+                };
+                self.write_struct(values, fields)?;
+            }
+            Value::Map(items) => {
+                let (key, value) = match type_ {
+                    Some(CanonicalType::Map(k, v)) => (Some(&**k), Some(&**v)),
+                    _ => (None, None),
+                };
+                let key_wire = element_type(items.iter().map(|(k, _)| k), key)?;
+                let value_wire = element_type(items.iter().map(|(_, v)| v), value)?;
+                self.output
+                    .write_map_begin(key_wire, value_wire, items.len());
+                for (k, v) in items {
+                    self.write_value(k, key)?;
+                    self.write_value(v, value)?;
+                }
+            }
+            Value::Set(items) | Value::List(items) => {
+                let element = match type_ {
+                    Some(CanonicalType::Set(x)) | Some(CanonicalType::List(x)) => Some(&**x),
+                    _ => None,
+                };
+                let wire = element_type(items.iter(), element)?;
+                self.output.write_list_begin(wire, items.len());
+                for item in items {
+                    self.write_value(item, element)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Whether `value` has the type the schema gives it, if it gives one.
+fn check(value: &Value, type_: Option<&CanonicalType>) -> Result<(), EncodeError> {
+    match type_ {
+        Some(type_) if WireType::of(type_) != value.wire_type() => Err(EncodeError::Mismatch {
+            expected: describe(type_),
+            found: value.kind(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// The schema of a value, if it agrees with the type on the wire.
+fn agreeing(type_: &CanonicalType, wire: WireType) -> Option<&CanonicalType> {
+    Some(type_).filter(|t| WireType::of(t) == wire)
+}
+
+// The wire type of the elements of a container, given by the schema or else by the elements,
+// which must then all have the same. Empty containers without a schema have byte elements.
+fn element_type<'v>(
+    mut items: impl Iterator<Item = &'v Value>,
+    type_: Option<&CanonicalType>,
+) -> Result<WireType, EncodeError> {
+    if let Some(type_) = type_ {
+        return Ok(WireType::of(type_));
+    }
+    let first = match items.next() {
+        Some(first) => first,
+        None => return Ok(WireType::Byte),
+    };
+    match items.find(|v| v.wire_type() != first.wire_type()) {
+        Some(other) => Err(EncodeError::Mismatch {
+            expected: first.kind().into(),
+            found: other.kind(),
+        }),
+        None => Ok(first.wire_type()),
+    }
+}
+
+fn invalid(message: String) -> DecodeError {
+    DecodeError::Invalid(message)
+}
+
+fn describe(type_: &CanonicalType) -> String {
+    match type_ {
+        CanonicalType::Bool => "bool".into(),
+        CanonicalType::Byte => "byte".into(),
+        CanonicalType::I8 => "i8".into(),
+        CanonicalType::I16 => "i16".into(),
+        CanonicalType::I32 => "i32".into(),
+        CanonicalType::I64 => "i64".into(),
+        CanonicalType::Double => "double".into(),
+        CanonicalType::String => "string".into(),
+        CanonicalType::Binary => "binary".into(),
+        CanonicalType::Map(k, v) => format!("map<{}, {}>", describe(k), describe(v)),
+        CanonicalType::Set(x) => format!("set<{}>", describe(x)),
+        CanonicalType::List(x) => format!("list<{}>", describe(x)),
+        CanonicalType::Named(symbol) => symbol.name.clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // The input ends in the middle of a value.
+    Eof,
+    // A type id the protocol does not have.
+    Type(u8),
+    // A negative size of a string or container.
+    Size(i64),
+    // Values are nested deeper than `MAX_DEPTH`.
+    Depth,
+    // Bytes are left after the value.
+    Trailing(usize),
+    // A variable-length integer longer than ten bytes.
+    Varint,
+    // Text which is not JSON.
+    Json(ParseError),
+    // JSON which is not a value of the protocol or of the type the schema gives, or a message
+    // which the schema has no function for.
+    Invalid(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Eof => f.write_str("unexpected end of input"),
+            DecodeError::Type(id) => write!(f, "unknown type id {}", id),
+            DecodeError::Size(size) => write!(f, "invalid size {}", size),
+            DecodeError::Depth => write!(f, "values nested deeper than {}", MAX_DEPTH),
+            DecodeError::Trailing(n) => write!(f, "{} bytes left after the value", n),
+            DecodeError::Varint => f.write_str("invalid variable-length integer"),
+            DecodeError::Json(e) => {
+                write!(f, "invalid JSON at {}:{}: {}", e.line, e.column, e.message)
+            }
+            DecodeError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    // A value does not have the type the schema gives it, or elements of a container without a
+    // schema have different types.
+    Mismatch {
+        expected: String,
+        found: &'static str,
+    },
+    // A map key of a kind JSON objects cannot be keyed by.
+    Key(&'static str),
+    // A string which is not UTF-8, written in JSON.
+    Utf8,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::Mismatch { expected, found } => {
+                write!(f, "expected a value of {}, found {}", expected, found)
+            }
+            EncodeError::Key(kind) => write!(f, "a {} cannot be a key of a JSON object", kind),
+            EncodeError::Utf8 => f.write_str("a string which is not UTF-8 cannot be JSON"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub(crate) const SCHEMA: &str = r#"
+enum Kind { PERSON = 1, BOT = 2 }
+typedef list<string> Tags
+
+struct Address {
+    1: string city
+}
+
+struct User {
+    1: i64 id
+    2: string name
+    3: optional bool admin
+    4: Kind kind
+    5: map<string, Address> addresses
+    6: Tags tags
+    7: binary avatar
+    8: set<i16> groups
+    9: double score
+    10: optional User manager
+}
+"#;
+
+    pub(crate) fn user() -> Value {
+        let field = |id, name: &str, value| FieldValue {
+            id,
+            name: Some(name.into()),
+            value,
+        };
+        let address = Value::Struct(vec![field(1, "city", Value::String("Paris".into()))]);
+        Value::Struct(vec![
+            field(1, "id", Value::I64(7)),
+            field(2, "name", Value::String("ann".into())),
+            field(3, "admin", Value::Bool(true)),
+            field(4, "kind", Value::I32(2)),
+            field(
+                5,
+                "addresses",
+                Value::Map(vec![(Value::String("home".into()), address)]),
+            ),
+            field(6, "tags", Value::List(vec![Value::String("a".into())])),
+            field(7, "avatar", Value::Binary(vec![0xff, 0])),
+            field(
+                8,
+                "groups",
+                Value::Set(vec![Value::I16(-1), Value::I16(300)]),
+            ),
+            field(9, "score", Value::Double(-2.5)),
+            field(
+                10,
+                "manager",
+                Value::Struct(vec![
+                    field(1, "id", Value::I64(1)),
+                    field(8, "groups", Value::Set(vec![])),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_schema() {
+        let workspace = Workspace::from_source("user.thrift", SCHEMA, &[]).unwrap();
+        let schema = Schema::new(&workspace, workspace.root());
+        let user = schema.find("User").unwrap();
+        assert_eq!(user.fields.len(), 10);
+        assert!(schema.find("Kind").is_none());
+
+        // Values the schema disagrees with are errors.
+        let value = Value::Struct(vec![FieldValue {
+            id: 1,
+            name: None,
+            value: Value::String("7".into()),
+        }]);
+        assert_eq!(
+            encode_binary(&value, &user, &schema)
+                .unwrap_err()
+                .to_string(),
+            "expected a value of i64, found string"
+        );
+        let value = Value::Struct(vec![FieldValue {
+            id: 5,
+            name: None,
+            value: Value::Map(vec![(Value::String("x".into()), Value::I32(1))]),
+        }]);
+        assert_eq!(
+            encode_binary(&value, &user, &schema).unwrap_err(),
+            EncodeError::Mismatch {
+                expected: "Address".into(),
+                found: "i32"
+            }
+        );
+        // Fields the schema does not know are written as they are, but elements of containers
+        // must have the same type.
+        let value = Value::Struct(vec![FieldValue {
+            id: 100,
+            name: None,
+            value: Value::List(vec![Value::I32(1), Value::Bool(true)]),
+        }]);
+        assert_eq!(
+            encode_binary(&value, &user, &schema)
+                .unwrap_err()
+                .to_string(),
+            "expected a value of i32, found bool"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use nom::bytes::complete::tag;
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::sequence::{pair, preceded};
+use nom::Err;
+
+use crate::basic::{IdentifierRef, Separator};
+use crate::span::Span;
+use crate::Parser;
+
+// Context labels wrapping a whole definition. They name the definition in messages
+// instead of being reported as what is expected.
+const DEFINITIONS: &[&str] = &["struct", "union", "exception", "enum", "service"];
+
+// A parse error pointing at the place in the source where parsing failed.
+// It is displayed like rustc does:
+//
+// error: expected '}' to close struct User
+//  --> 4:15
+//   |
+// 4 |     1: i32 id bad
+//   |               ^
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    // Byte offset in the source.
+    pub offset: usize,
+    // Line and column both start from 1, the column counts chars.
+    pub line: usize,
+    pub column: usize,
+    // The full source line the error is on, without the line break.
+    pub source_line: String,
+    // The file the source is read from, if any.
+    pub path: Option<PathBuf>,
+}
+
+impl ParseError {
+    // Build an error from what a parser returned when parsing `source`.
+    pub fn new(source: &str, err: Err<VerboseError<&str>>) -> Self {
+        match err {
+            Err::Error(e) | Err::Failure(e) => {
+                let offset = e
+                    .errors
+                    .first()
+                    .map(|(input, _)| source.len() - input.len())
+                    .unwrap_or_default();
+                Self::at(source, offset, describe(&e))
+            }
+            Err::Incomplete(_) => Self::at(source, source.len(), "unexpected end of input".into()),
+        }
+    }
+
+    // Build an error with the given message pointing at `offset` of `source`.
+    pub fn at(source: &str, offset: usize, message: String) -> Self {
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        Self {
+            message,
+            offset,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            source_line: source[line_start..line_end].trim_end_matches('\r').into(),
+            path: None,
+        }
+    }
+
+    // Report the error as in the file at `path`.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+}
+
+// Turn the error stack into a message, the innermost error comes first.
+fn describe(e: &VerboseError<&str>) -> String {
+    let definition = e.errors.iter().find_map(|(input, kind)| match kind {
+        VerboseErrorKind::Context(label) if DEFINITIONS.contains(label) => {
+            preceded(pair(tag(*label), Separator::parse), IdentifierRef::parse)(input)
+                .ok()
+                .map(|(_, name)| format!("{} {}", label, *name))
+        }
+        _ => None,
+    });
+    let expected = e
+        .errors
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(label) if !DEFINITIONS.contains(label) => {
+                Some(label.to_string())
+            }
+            _ => None,
+        })
+        .or_else(|| match e.errors.first() {
+            Some((_, VerboseErrorKind::Char(c))) => Some(format!("expected '{}'", c)),
+            _ => None,
+        });
+
+    match (expected, definition) {
+        (Some(expected), Some(definition)) if expected.ends_with("to close") => {
+            format!("{} {}", expected, definition)
+        }
+        (Some(expected), Some(definition)) => format!("{} in {}", expected, definition),
+        (Some(expected), None) => expected,
+        (None, Some(definition)) => format!("invalid syntax in {}", definition),
+        (None, None) => "invalid syntax".into(),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.line.to_string();
+        let gutter = " ".repeat(line.len());
+        // Keep tabs so that the caret lines up with the source line.
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "error: {}", self.message)?;
+        match &self.path {
+            Some(path) => writeln!(
+                f,
+                "{}--> {}:{}:{}",
+                gutter,
+                path.display(),
+                self.line,
+                self.column
+            )?,
+            None => writeln!(f, "{}--> {}:{}", gutter, self.line, self.column)?,
+        }
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line, self.source_line)?;
+        write!(f, "{} | {}^", gutter, padding)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// A problem found in a parsed file, e.g. a reference to something not defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    // The node the problem is about.
+    pub span: Span,
+    // The file the node is in, if any.
+    pub path: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+            path: None,
+        }
+    }
+
+    pub fn warning(span: Span, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(span, message)
+        }
+    }
+
+    // Report the problem as in the file at `path`.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => writeln!(f, "error: {}", self.message)?,
+            Severity::Warning => writeln!(f, "warning: {}", self.message)?,
+        }
+        match &self.path {
+            Some(path) => write!(
+                f,
+                " --> {}:{}:{}",
+                path.display(),
+                self.span.line,
+                self.span.column
+            ),
+            None => write!(f, " --> {}:{}", self.span.line, self.span.column),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod test {
+    use crate::document::DocumentRef;
+
+    use super::*;
+
+    fn parse_error(input: &str) -> ParseError {
+        ParseError::new(input, DocumentRef::parse(input).unwrap_err())
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let input = "struct User {\n    1: i32 id\n    2: string name;\n    3 i32 age\n}";
+        let err = parse_error(input);
+        assert_eq!(err.message, "expected '}' to close struct User");
+        assert_eq!((err.line, err.column), (4, 5));
+        assert_eq!(
+            err.to_string(),
+            "error: expected '}' to close struct User\n --> 4:5\n  |\n4 |     3 i32 age\n  |     ^"
+        );
+
+        let err = parse_error("struct User {\n\t1: required ;\n}");
+        assert_eq!(err.message, "expected field type in struct User");
+        assert_eq!((err.line, err.column), (2, 14));
+        assert!(err
+            .to_string()
+            .ends_with("2 | \t1: required ;\n  | \t            ^"));
+
+        let err = parse_error("struct User {\n    1: i32 id = ,\n}");
+        assert_eq!(err.message, "expected default value in struct User");
+        assert_eq!((err.line, err.column), (2, 17));
+
+        let err = parse_error("service UserService {\n  User GetUser(1: i64 id\n}");
+        assert_eq!(
+            err.message,
+            "expected ')' to close parameters in service UserService"
+        );
+        assert_eq!((err.line, err.column), (3, 1));
+
+        let err = parse_error("const i32 = 1");
+        assert_eq!(err.message, "expected constant name");
+        assert_eq!((err.line, err.column), (1, 11));
+        assert!(err
+            .with_path("demo.thrift")
+            .to_string()
+            .contains(" --> demo.thrift:1:11\n"));
+    }
+}
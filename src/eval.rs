@@ -0,0 +1,620 @@
+use std::convert::TryFrom;
+
+use crate::constant::ConstValue;
+use crate::definition::{Const, Definition};
+use crate::field::Field;
+use crate::resolve::{CanonicalType, ResolveError, Resolver, Symbol, SymbolKind};
+use crate::span::Span;
+use crate::types::FieldType;
+use crate::workspace::{Program, Workspace};
+use crate::Diagnostic;
+
+// A constant value checked against its type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedConstValue {
+    Bool(bool),
+    // Both `byte` and `i8`.
+    Byte(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    String(String),
+    Binary(Vec<u8>),
+    // A value of an enum, `name` is the name of the value without the enum name.
+    Enum { name: String, value: i32 },
+    List(Vec<ResolvedConstValue>),
+    Set(Vec<ResolvedConstValue>),
+    Map(Vec<(ResolvedConstValue, ResolvedConstValue)>),
+    // A struct, union or exception with the fields given, in the order they are given.
+    Struct(Vec<(String, ResolvedConstValue)>),
+}
+
+// Evaluates constant values in a workspace, following references to other consts and
+// enum values, and typedefs of the declared types.
+pub struct Evaluator<'w> {
+    workspace: &'w Workspace,
+    resolver: Resolver,
+}
+
+impl<'w> Evaluator<'w> {
+    pub fn new(workspace: &'w Workspace) -> Self {
+        Self {
+            workspace,
+            resolver: Resolver::new(workspace),
+        }
+    }
+
+    pub fn eval_const(
+        &self,
+        program: &Program,
+        const_: &Const,
+    ) -> Result<ResolvedConstValue, Diagnostic> {
+        let symbol = Symbol {
+            path: program.path.clone(),
+            kind: SymbolKind::Const,
+            name: const_.name.as_str().into(),
+        };
+        self.eval_as(
+            program,
+            &const_.type_,
+            program,
+            &const_.value,
+            &mut vec![symbol],
+        )
+        .map_err(|message| error(program, const_.span, message))
+    }
+
+    // The default value of a field, if any.
+    pub fn eval_default(
+        &self,
+        program: &Program,
+        field: &Field,
+    ) -> Result<Option<ResolvedConstValue>, Diagnostic> {
+        field
+            .default
+            .as_ref()
+            .map(|default| {
+                self.eval_as(program, &field.type_, program, default, &mut Vec::new())
+                    .map_err(|message| error(program, field.span, message))
+            })
+            .transpose()
+    }
+
+    // Evaluate every const and field default in the workspace.
+    pub fn check(&self) -> Result<(), Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        for program in self.workspace.programs() {
+            for definition in &program.document.definitions {
+                let fields = match definition {
+                    Definition::Const(x) => {
+                        diagnostics.extend(self.eval_const(program, x).err());
+                        continue;
+                    }
+                    Definition::Struct(x) => &x.fields,
+                    Definition::Union(x) => &x.fields,
+                    Definition::Exception(x) => &x.fields,
+                    _ => continue,
+                };
+                for field in fields {
+                    diagnostics.extend(self.eval_default(program, field).err());
+                }
+            }
+        }
+        match diagnostics.is_empty() {
+            true => Ok(()),
+            false => Err(diagnostics),
+        }
+    }
+
+    // Evaluate `value` used in `value_program` as `type_` used in `type_program`.
+    fn eval_as(
+        &self,
+        type_program: &Program,
+        type_: &FieldType,
+        value_program: &Program,
+        value: &ConstValue,
+        stack: &mut Vec<Symbol>,
+    ) -> Result<ResolvedConstValue, String> {
+        let type_ = self
+            .resolver
+            .canonical_type(self.workspace, type_program, type_)
+            .map_err(|e| e.to_string())?;
+        self.eval(&type_, value_program, value, stack)
+    }
+
+    // Evaluate `value` used in `value_program` as `type_`. `stack` holds the consts being
+    // evaluated to find consts referring to themselves.
+    fn eval(
+        &self,
+        type_: &CanonicalType,
+        value_program: &Program,
+        value: &ConstValue,
+        stack: &mut Vec<Symbol>,
+    ) -> Result<ResolvedConstValue, String> {
+        if let ConstValue::Identifier(name) = value {
+            // `true` and `false` are not keywords, they are only special as values of bool.
+            if *type_ == CanonicalType::Bool && ["true", "false"].contains(&name.as_str()) {
+                return Ok(ResolvedConstValue::Bool(name.as_str() == "true"));
+            }
+            let symbol = self
+                .resolver
+                .resolve_value(value_program, name)
+                .map_err(|e| describe(e, "constant", name))?;
+            if symbol.kind == SymbolKind::Const {
+                return self.eval_reference(type_, symbol, stack);
+            }
+        }
+
+        match type_ {
+            CanonicalType::Named(symbol) => {
+                let program = self.program(symbol)?;
+                match self.definition(symbol)? {
+                    Definition::Enum(x) => {
+                        let values = x.values().map_err(|e| e.message)?;
+                        let found = match value {
+                            ConstValue::Int(v) => {
+                                values.into_iter().find(|(_, n)| *n as i64 == **v)
+                            }
+                            ConstValue::Identifier(v) => {
+                                let symbol = self
+                                    .resolver
+                                    .resolve_value(value_program, v)
+                                    .map_err(|e| describe(e, "constant", v))?;
+                                let (enum_name, value_name) =
+                                    symbol.name.split_once('.').unwrap_or_default();
+                                match symbol.path == program.path && enum_name == x.name.as_str() {
+                                    true => values.into_iter().find(|(n, _)| *n == value_name),
+                                    false => None,
+                                }
+                            }
+                            _ => return Err(mismatch(value, &format!("enum {}", *x.name))),
+                        };
+                        found
+                            .map(|(name, value)| ResolvedConstValue::Enum {
+                                name: name.into(),
+                                value,
+                            })
+                            .ok_or_else(|| {
+                                format!("{} is not a value of enum {}", show(value), *x.name)
+                            })
+                    }
+                    Definition::Struct(x) => {
+                        self.eval_struct(program, &x.fields, value_program, value, stack)
+                    }
+                    Definition::Union(x) => {
+                        self.eval_struct(program, &x.fields, value_program, value, stack)
+                    }
+                    Definition::Exception(x) => {
+                        self.eval_struct(program, &x.fields, value_program, value, stack)
+                    }
+                    _ => Err(format!("`{}` is not a type", symbol.name)),
+                }
+            }
+            CanonicalType::Bool => match self.int(value_program, value, "bool")? {
+                0 => Ok(ResolvedConstValue::Bool(false)),
+                1 => Ok(ResolvedConstValue::Bool(true)),
+                v => Err(format!("{} is not a bool", v)),
+            },
+            CanonicalType::Byte | CanonicalType::I8 => {
+                let v = self.int(value_program, value, "i8")?;
+                fit(v, "i8").map(ResolvedConstValue::Byte)
+            }
+            CanonicalType::I16 => {
+                let v = self.int(value_program, value, "i16")?;
+                fit(v, "i16").map(ResolvedConstValue::I16)
+            }
+            CanonicalType::I32 => {
+                let v = self.int(value_program, value, "i32")?;
+                fit(v, "i32").map(ResolvedConstValue::I32)
+            }
+            CanonicalType::I64 => self
+                .int(value_program, value, "i64")
+                .map(ResolvedConstValue::I64),
+            CanonicalType::Double => match value {
+                ConstValue::Double(v) => Ok(ResolvedConstValue::Double(**v)),
+                ConstValue::Int(v) => Ok(ResolvedConstValue::Double(**v as f64)),
+                _ => Err(mismatch(value, "double")),
+            },
+            CanonicalType::String => match value {
+                ConstValue::Literal(v) => Ok(ResolvedConstValue::String(unescape(v))),
+                _ => Err(mismatch(value, "string")),
+            },
+            CanonicalType::Binary => match value {
+                ConstValue::Literal(v) => Ok(ResolvedConstValue::Binary(unescape(v).into_bytes())),
+                _ => Err(mismatch(value, "binary")),
+            },
+            CanonicalType::List(element) | CanonicalType::Set(element) => {
+                let list = match value {
+                    ConstValue::List(list) => list,
+                    _ => return Err(mismatch(value, "list")),
+                };
+                let list = list
+                    .iter()
+                    .map(|v| self.eval(element, value_program, v, stack))
+                    .collect::<Result<_, _>>()?;
+                match type_ {
+                    CanonicalType::List(_) => Ok(ResolvedConstValue::List(list)),
+                    _ => Ok(ResolvedConstValue::Set(list)),
+                }
+            }
+            CanonicalType::Map(key, val) => {
+                let map = match value {
+                    ConstValue::Map(map) => map,
+                    _ => return Err(mismatch(value, "map")),
+                };
+                map.iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            self.eval(key, value_program, k, stack)?,
+                            self.eval(val, value_program, v, stack)?,
+                        ))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(ResolvedConstValue::Map)
+            }
+        }
+    }
+
+    // Evaluate the const `symbol` refers to as its declared type, and convert it to `type_`.
+    fn eval_reference(
+        &self,
+        type_: &CanonicalType,
+        symbol: Symbol,
+        stack: &mut Vec<Symbol>,
+    ) -> Result<ResolvedConstValue, String> {
+        if stack.contains(&symbol) {
+            return Err(format!("const `{}` refers to itself", symbol.name));
+        }
+        let program = self.program(&symbol)?;
+        let const_ = match self.definition(&symbol)? {
+            Definition::Const(x) => x,
+            _ => return Err(format!("`{}` is not a const", symbol.name)),
+        };
+        let declared = self
+            .resolver
+            .canonical_type(self.workspace, program, &const_.type_)
+            .map_err(|e| e.to_string())?;
+        let name = symbol.name.clone();
+        stack.push(symbol);
+        let value = self.eval(&declared, program, &const_.value, stack);
+        stack.pop();
+        assign(value?, &declared, type_).ok_or_else(|| {
+            format!(
+                "expected {}, found const `{}` of type {}",
+                type_name(type_),
+                name,
+                const_.type_
+            )
+        })
+    }
+
+    fn eval_struct(
+        &self,
+        program: &Program,
+        fields: &[Field],
+        value_program: &Program,
+        value: &ConstValue,
+        stack: &mut Vec<Symbol>,
+    ) -> Result<ResolvedConstValue, String> {
+        let map = match value {
+            ConstValue::Map(map) => map,
+            _ => return Err(mismatch(value, "struct")),
+        };
+        map.iter()
+            .map(|(k, v)| {
+                let name = match k {
+                    ConstValue::Literal(name) => name.as_str(),
+                    _ => return Err(format!("expected field name, found {}", show(k))),
+                };
+                let field = fields
+                    .iter()
+                    .find(|f| f.name.as_str() == name)
+                    .ok_or_else(|| format!("no field named `{}`", name))?;
+                let v = self.eval_as(program, &field.type_, value_program, v, stack)?;
+                Ok((name.to_string(), v))
+            })
+            .collect::<Result<_, _>>()
+            .map(ResolvedConstValue::Struct)
+    }
+
+    // An integer, which may be an enum value.
+    fn int(&self, program: &Program, value: &ConstValue, expected: &str) -> Result<i64, String> {
+        match value {
+            ConstValue::Int(v) => Ok(**v),
+            ConstValue::Identifier(name) => {
+                let symbol = self
+                    .resolver
+                    .resolve_value(program, name)
+                    .map_err(|e| describe(e, "constant", name))?;
+                let (enum_name, value_name) = symbol.name.split_once('.').unwrap_or_default();
+                match self.definition(&symbol)? {
+                    Definition::Enum(x) => x
+                        .values()
+                        .map_err(|e| e.message)?
+                        .into_iter()
+                        .find(|(n, _)| *n == value_name)
+                        .map(|(_, v)| v as i64)
+                        .ok_or_else(|| {
+                            format!("cannot find `{}` in enum {}", value_name, enum_name)
+                        }),
+                    _ => Err(mismatch(value, expected)),
+                }
+            }
+            _ => Err(mismatch(value, expected)),
+        }
+    }
+
+    fn program(&self, symbol: &Symbol) -> Result<&'w Program, String> {
+        self.workspace
+            .get(&symbol.path)
+            .ok_or_else(|| format!("cannot find {}", symbol))
+    }
+
+    fn definition(&self, symbol: &Symbol) -> Result<&'w Definition, String> {
+        symbol
+            .definition(self.workspace)
+            .ok_or_else(|| format!("cannot find {}", symbol))
+    }
+}
+
+fn error(program: &Program, span: Span, message: String) -> Diagnostic {
+    Diagnostic::error(span, message).with_path(&program.path)
+}
+
+fn describe(e: ResolveError, expected: &str, name: &str) -> String {
+    match e {
+        ResolveError::Unresolved => format!("cannot find {} `{}`", expected, name),
+        ResolveError::Ambiguous(_) => format!("{} `{}` is ambiguous", expected, name),
+        ResolveError::Mismatched(symbol) => format!("expected {}, found {}", expected, symbol),
+    }
+}
+
+fn fit<T: TryFrom<i64>>(v: i64, type_: &str) -> Result<T, String> {
+    T::try_from(v).map_err(|_| format!("{} does not fit in {}", v, type_))
+}
+
+// Convert `value` of type `from` to `to`, if a const of `from` can be used as `to`: the types are
+// the same, or integers widen to bigger integers or doubles, also in lists, sets and maps.
+fn assign(
+    value: ResolvedConstValue,
+    from: &CanonicalType,
+    to: &CanonicalType,
+) -> Option<ResolvedConstValue> {
+    use CanonicalType as T;
+    use ResolvedConstValue as V;
+
+    if from == to {
+        return Some(value);
+    }
+    let all = |items: Vec<V>, from, to| {
+        items
+            .into_iter()
+            .map(|v| assign(v, from, to))
+            .collect::<Option<Vec<_>>>()
+    };
+    match (from, to, value) {
+        (T::List(a), T::List(b), V::List(items)) => all(items, a, b).map(V::List),
+        (T::Set(a), T::Set(b), V::Set(items)) => all(items, a, b).map(V::Set),
+        (T::Map(k1, v1), T::Map(k2, v2), V::Map(items)) => items
+            .into_iter()
+            .map(|(k, v)| Some((assign(k, k1, k2)?, assign(v, v1, v2)?)))
+            .collect::<Option<_>>()
+            .map(V::Map),
+        (_, _, value) => {
+            let v = match value {
+                V::Byte(v) => v as i64,
+                V::I16(v) => v as i64,
+                V::I32(v) => v as i64,
+                V::I64(v) => v,
+                _ => return None,
+            };
+            match to {
+                T::Double => return Some(V::Double(v as f64)),
+                _ if width(to)? < width(from)? => return None,
+                _ => {}
+            }
+            match to {
+                T::Byte | T::I8 => Some(V::Byte(v as i8)),
+                T::I16 => Some(V::I16(v as i16)),
+                T::I32 => Some(V::I32(v as i32)),
+                _ => Some(V::I64(v)),
+            }
+        }
+    }
+}
+
+// The size in bytes of an integer type.
+fn width(type_: &CanonicalType) -> Option<u8> {
+    match type_ {
+        CanonicalType::Byte | CanonicalType::I8 => Some(1),
+        CanonicalType::I16 => Some(2),
+        CanonicalType::I32 => Some(4),
+        CanonicalType::I64 => Some(8),
+        _ => None,
+    }
+}
+
+fn type_name(type_: &CanonicalType) -> String {
+    match type_ {
+        CanonicalType::Bool => "bool".into(),
+        CanonicalType::Byte => "byte".into(),
+        CanonicalType::I8 => "i8".into(),
+        CanonicalType::I16 => "i16".into(),
+        CanonicalType::I32 => "i32".into(),
+        CanonicalType::I64 => "i64".into(),
+        CanonicalType::Double => "double".into(),
+        CanonicalType::String => "string".into(),
+        CanonicalType::Binary => "binary".into(),
+        CanonicalType::Map(k, v) => format!("map<{}, {}>", type_name(k), type_name(v)),
+        CanonicalType::Set(x) => format!("set<{}>", type_name(x)),
+        CanonicalType::List(x) => format!("list<{}>", type_name(x)),
+        CanonicalType::Named(symbol) => symbol.name.clone(),
+    }
+}
+
+fn mismatch(value: &ConstValue, expected: &str) -> String {
+    format!("expected {}, found {}", expected, show(value))
+}
+
+fn show(value: &ConstValue) -> String {
+    match value {
+        ConstValue::Identifier(v) => format!("`{}`", v.as_str()),
+        ConstValue::Literal(v) => format!("\"{}\"", v.as_str()),
+        ConstValue::Double(v) => format!("{}", **v),
+        ConstValue::Int(v) => format!("{}", **v),
+        ConstValue::List(_) => "list".into(),
+        ConstValue::Map(_) => "map".into(),
+    }
+}
+
+// Literals keep escape sequences as they are in the source.
+fn unescape(literal: &str) -> String {
+    let mut result = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn eval_all(source: &str) -> Vec<Result<ResolvedConstValue, String>> {
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let evaluator = Evaluator::new(&workspace);
+        let root = workspace.root();
+        root.document
+            .consts()
+            .map(|c| evaluator.eval_const(root, c).map_err(|e| e.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_eval() {
+        let results = eval_all(
+            r#"
+enum Status { OK, FAILED = 5 }
+typedef list<Status> Statuses
+struct User {
+    1: i64 id
+    2: optional string name
+}
+const i8 SMALL = -128
+const i32 FROM_ENUM = Status.FAILED
+const Status STATUS = Status.OK
+const Statuses ALL = [STATUS, 5]
+const map<string, double> M = {"a\"b": 1, "c": 2.5}
+const bool FLAG = true
+const User ADMIN = {"id": SMALL, "name": "root"}
+const list<double> WIDE = [SMALL]
+"#,
+        );
+        use ResolvedConstValue::*;
+        assert_eq!(
+            results,
+            vec![
+                Ok(Byte(-128)),
+                Ok(I32(5)),
+                Ok(Enum {
+                    name: "OK".into(),
+                    value: 0
+                }),
+                Ok(List(vec![
+                    Enum {
+                        name: "OK".into(),
+                        value: 0
+                    },
+                    Enum {
+                        name: "FAILED".into(),
+                        value: 5
+                    },
+                ])),
+                Ok(Map(vec![
+                    (String("a\"b".into()), Double(1.0)),
+                    (String("c".into()), Double(2.5)),
+                ])),
+                Ok(Bool(true)),
+                Ok(Struct(vec![
+                    ("id".into(), I64(-128)),
+                    ("name".into(), String("root".into())),
+                ])),
+                Ok(List(vec![Double(-128.0)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let results = eval_all(
+            r#"
+enum Status { OK }
+enum Other { OK }
+struct User { 1: i64 id }
+const i8 BIG = 1000
+const list<string> L = [1, 2]
+const map<string, i32> M = {"a": "b"}
+const Status S = Other.OK
+const Status N = 3
+const User U = {"age": 1}
+const i32 A = B
+const i32 B = A
+const i32 C = MISSING
+"#,
+        );
+        let errors: Vec<_> = results.into_iter().map(Result::unwrap_err).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "1000 does not fit in i8",
+                "expected string, found 1",
+                "expected i32, found \"b\"",
+                "`Other.OK` is not a value of enum Status",
+                "3 is not a value of enum Status",
+                "no field named `age`",
+                "const `A` refers to itself",
+                "const `B` refers to itself",
+                "cannot find constant `MISSING`",
+            ]
+        );
+
+        // A referenced const is evaluated as its own type, which must fit the referring one.
+        let results = eval_all(
+            r#"
+const double D = 1
+const i32 E = D
+const list<i64> F = [1]
+const list<i16> G = F
+"#,
+        );
+        assert_eq!(
+            results,
+            vec![
+                Ok(ResolvedConstValue::Double(1.0)),
+                Err("expected i32, found const `D` of type double".into()),
+                Ok(ResolvedConstValue::List(vec![ResolvedConstValue::I64(1)])),
+                Err("expected list<i16>, found const `F` of type list<i64>".into()),
+            ]
+        );
+
+        let source = "struct User {\n    1: i32 age = \"old\"\n}\n";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let diagnostics = Evaluator::new(&workspace).check().unwrap_err();
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: expected i32, found \"old\"\n --> inline.thrift:2:5"
+        );
+    }
+}
@@ -1,16 +1,18 @@
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::char as cchar;
-use nom::combinator::{map, opt};
-use nom::sequence::{delimited, terminated, tuple};
-use nom::IResult;
+use nom::combinator::{cut, map, opt};
+use nom::error::context;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 
-use crate::basic::{Identifier, IdentifierRef, ListSeparator, Separator};
+use crate::annotation::{Annotations, AnnotationsRef};
+use crate::basic::{Doc, DocRef, Documented, Identifier, IdentifierRef, ListSeparator, Separator};
 use crate::constant::{ConstValue, ConstValueRef, IntConstant};
+use crate::span::{located, Located, Span};
 use crate::types::{FieldType, FieldTypeRef};
-use crate::Parser;
+use crate::{IResult, Parser};
 
-// Field           ::=  FieldID? FieldReq? FieldType Identifier ('=' ConstValue)? ListSeparator?
+// Field           ::=  FieldID? FieldReq? FieldType Identifier ('=' ConstValue)? TypeAnnotations? ListSeparator?
 // FieldID         ::=  IntConstant ':'
 // FieldReq        ::=  'required' | 'optional'
 // Note: XsdFieldOptions is not supported in out impl and strongly discouraged in official docs.
@@ -21,50 +23,102 @@ pub struct FieldRef<'a> {
     pub type_: FieldTypeRef<'a>,
     pub name: IdentifierRef<'a>,
     pub default: Option<ConstValueRef<'a>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for FieldRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                opt(terminated(
-                    IntConstant::parse,
-                    delimited(opt(Separator::parse), cchar(':'), opt(Separator::parse)),
+        terminated(
+            located(map(
+                alt((
+                    // Once the FieldID is seen, this can only be a field.
+                    map(
+                        pair(
+                            terminated(
+                                IntConstant::parse,
+                                delimited(opt(Separator::parse), cchar(':'), opt(Separator::parse)),
+                            ),
+                            cut(Self::parse_body),
+                        ),
+                        |(id, body)| (Some(id), body),
+                    ),
+                    map(Self::parse_body, |body| (None, body)),
                 )),
-                opt(terminated(
-                    alt((
-                        map(tag("required"), |_| true),
-                        map(tag("optional"), |_| false),
-                    )),
-                    Separator::parse,
-                )),
-                terminated(FieldTypeRef::parse, Separator::parse),
-                terminated(IdentifierRef::parse, opt(Separator::parse)),
-                opt(map(
-                    tuple((cchar('='), opt(Separator::parse), ConstValueRef::parse)),
-                    |(_, _, cv)| cv,
-                )),
-                opt(Separator::parse),
-                opt(ListSeparator::parse),
+                |(id, (required, type_, name, default, annotations))| Self {
+                    id,
+                    required,
+                    type_,
+                    name,
+                    default,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(id, required, type_, name, default, _, _)| Self {
-                id,
-                required,
-                type_,
-                name,
-                default,
-            },
+            opt(pair(opt(Separator::parse), ListSeparator::parse)),
         )(input)
     }
 }
 
+type FieldBodyRef<'a> = (
+    Option<bool>,
+    FieldTypeRef<'a>,
+    IdentifierRef<'a>,
+    Option<ConstValueRef<'a>>,
+    Option<AnnotationsRef<'a>>,
+);
+
+impl<'a> FieldRef<'a> {
+    // Everything after FieldID.
+    fn parse_body(input: &'a str) -> IResult<&'a str, FieldBodyRef<'a>> {
+        tuple((
+            opt(terminated(
+                alt((
+                    map(tag("required"), |_| true),
+                    map(tag("optional"), |_| false),
+                )),
+                Separator::parse,
+            )),
+            context("expected field type", FieldTypeRef::parse),
+            context(
+                "expected field name",
+                preceded(Separator::parse, IdentifierRef::parse),
+            ),
+            opt(preceded(
+                tuple((opt(Separator::parse), cchar('='), opt(Separator::parse))),
+                cut(context("expected default value", ConstValueRef::parse)),
+            )),
+            opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
+        ))(input)
+    }
+}
+
+impl<'a> Located for FieldRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+}
+
+impl<'a> Documented<'a> for FieldRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Field {
     pub id: Option<IntConstant>,
     pub required: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: FieldType,
     pub name: Identifier,
     pub default: Option<ConstValue>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<FieldRef<'a>> for Field {
@@ -75,6 +129,9 @@ impl<'a> From<FieldRef<'a>> for Field {
             type_: r.type_.into(),
             name: r.name.into(),
             default: r.default.map(Into::into),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -87,48 +144,82 @@ impl<'a> Parser<'a> for Field {
 
 #[cfg(test)]
 mod test {
+    use crate::annotation::AnnotationRef;
     use crate::basic::LiteralRef;
 
     use super::*;
 
     #[test]
     fn test_field() {
-        let expected = FieldRef {
-            id: None,
+        let expected = |id, span| FieldRef {
+            id,
             required: Some(true),
             type_: FieldTypeRef::String,
             name: IdentifierRef::from("name"),
             default: Some(ConstValueRef::Literal(LiteralRef::from("ihciah"))),
+            annotations: None,
+            span,
+            doc: None,
         };
         assert_eq!(
             FieldRef::parse("required  string  name  =  'ihciah'")
                 .unwrap()
                 .1,
-            expected
+            expected(None, Span::at(0, 35, 1, 1))
         );
+        // The separator is not part of the field.
         assert_eq!(
             FieldRef::parse("required string name='ihciah';").unwrap().1,
-            expected
+            expected(None, Span::at(0, 29, 1, 1))
         );
 
-        let expected = FieldRef {
-            id: Some(IntConstant::from(3)),
-            required: Some(true),
-            type_: FieldTypeRef::String,
-            name: IdentifierRef::from("name"),
-            default: Some(ConstValueRef::Literal(LiteralRef::from("ihciah"))),
-        };
+        let id = Some(IntConstant::from(3));
         assert_eq!(
             FieldRef::parse("3 : required  string  name  =  'ihciah'")
                 .unwrap()
                 .1,
-            expected
+            expected(id, Span::at(0, 39, 1, 1))
         );
         assert_eq!(
             FieldRef::parse("3:required string name='ihciah';")
                 .unwrap()
                 .1,
-            expected
+            expected(id, Span::at(0, 31, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_field_annotations() {
+        let expected = |span| FieldRef {
+            id: Some(IntConstant::from(1)),
+            required: None,
+            type_: FieldTypeRef::I64,
+            name: IdentifierRef::from("id"),
+            default: None,
+            annotations: Some(AnnotationsRef::from(vec![
+                AnnotationRef {
+                    key: IdentifierRef::from("go.tag"),
+                    value: Some(LiteralRef::from(r#"json:\"id\""#)),
+                },
+                AnnotationRef {
+                    key: IdentifierRef::from("cpp.ref"),
+                    value: Some(LiteralRef::from("true")),
+                },
+            ])),
+            span,
+            doc: None,
+        };
+        assert_eq!(
+            FieldRef::parse(r#"1: i64 id (go.tag = "json:\"id\"", cpp.ref = "true"),"#)
+                .unwrap()
+                .1,
+            expected(Span::at(0, 52, 1, 1))
+        );
+        assert_eq!(
+            FieldRef::parse(r#"1:i64 id(go.tag="json:\"id\"",cpp.ref="true")"#)
+                .unwrap()
+                .1,
+            expected(Span::at(0, 45, 1, 1))
         );
     }
 }
@@ -0,0 +1,495 @@
+use nom::combinator::opt;
+
+use crate::basic::{CommentRef, Separator};
+use crate::definition::{Definition, DefinitionRef, Enum, Exception, Service, Struct, Union};
+use crate::document::Document;
+use crate::field::Field;
+use crate::header::{CppInclude, CppIncludeRef, Include, IncludeRef, Namespace, NamespaceRef};
+use crate::printer::{Print, Printer};
+use crate::span::Span;
+use crate::{ParseError, Parser};
+
+// Reformat a Thrift file. Unlike printing the parsed Document, every comment in the source is
+// kept: a comment stays on the line of what it follows, or goes on its own line in front of
+// what comes next. Comments in the middle of a field, an enum value, a function or a header are
+// moved in front of it. Single blank lines between items are kept.
+pub fn format(source: &str, printer: &Printer) -> Result<String, ParseError> {
+    let mut document = Document::parse_complete(source)?;
+    // Doc comments are written out with the other comments, as they are in the source.
+    strip_docs(&mut document);
+
+    let formatter = Formatter {
+        source,
+        printer,
+        comments: comments(source),
+    };
+    let mut items = Vec::new();
+    let mut definitions = document.definitions.iter();
+    let mut input = source;
+    loop {
+        input = opt(Separator::parse)(input).map_or(input, |(remains, _)| remains);
+        if input.is_empty() {
+            break;
+        }
+        let start = source.len() - input.len();
+        let (remains, kind) = if let Ok((remains, x)) = IncludeRef::parse(input) {
+            (
+                remains,
+                Kind::Header("include", printer.print(&Include::from(x))),
+            )
+        } else if let Ok((remains, x)) = CppIncludeRef::parse(input) {
+            let header = printer.print(&CppInclude::from(x));
+            (remains, Kind::Header("cpp_include", header))
+        } else if let Ok((remains, x)) = NamespaceRef::parse(input) {
+            let header = printer.print(&Namespace::from(x));
+            (remains, Kind::Header("namespace", header))
+        } else {
+            let (remains, _) = DefinitionRef::parse(input).expect("the document is already parsed");
+            let definition = definitions.next().expect("the document is already parsed");
+            (remains, Kind::Definition(definition))
+        };
+        let end = source.len() - remains.len();
+        items.push(Entry::new(start, end, kind));
+        input = remains;
+    }
+
+    let entries = formatter.interleave(0, source.len(), items);
+    let mut out = String::new();
+    formatter.write(&entries, 0, true, &mut out);
+    let mut out = out.trim_start_matches('\n').to_string();
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+struct Comment<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+impl<'a> Comment<'a> {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+}
+
+// Find the comments in the source, skipping string literals.
+fn comments(source: &str) -> Vec<Comment<'_>> {
+    let mut comments = Vec::new();
+    let bytes = source.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ b'"' | quote @ b'\'' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b'/' | b'#' => match CommentRef::parse(&source[i..]) {
+                Ok((remains, _)) => {
+                    let end = source.len() - remains.len();
+                    let text = source[i..end].trim_end();
+                    comments.push(Comment { start: i, text });
+                    i = end;
+                }
+                Err(_) => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+    comments
+}
+
+struct Entry<'f, 'a> {
+    start: usize,
+    end: usize,
+    kind: Kind<'f, 'a>,
+    // A comment on the same line as the entry before it.
+    trailing: bool,
+}
+
+impl<'f, 'a> Entry<'f, 'a> {
+    fn new(start: usize, end: usize, kind: Kind<'f, 'a>) -> Self {
+        Self {
+            start,
+            end,
+            kind,
+            trailing: false,
+        }
+    }
+}
+
+enum Kind<'f, 'a> {
+    Comment(&'f Comment<'a>),
+    // A header and its keyword, headers of different kinds are separated by a blank line.
+    Header(&'static str, String),
+    Definition(&'f Definition),
+    // A field, an enum value or a function in a block, already printed.
+    Child(String),
+}
+
+impl<'f, 'a> Kind<'f, 'a> {
+    // Whether the comments inside belong to the entry, instead of being moved in front of it.
+    fn keeps_comments(&self) -> bool {
+        matches!(
+            self,
+            Kind::Definition(
+                Definition::Enum(_)
+                    | Definition::Struct(_)
+                    | Definition::Union(_)
+                    | Definition::Exception(_)
+                    | Definition::Service(_)
+            )
+        )
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    printer: &'a Printer,
+    comments: Vec<Comment<'a>>,
+}
+
+impl<'a> Formatter<'a> {
+    // Merge `items`, which are in source order, with the comments between `start` and `end`.
+    fn interleave<'f>(
+        &'f self,
+        start: usize,
+        end: usize,
+        items: Vec<Entry<'f, 'a>>,
+    ) -> Vec<Entry<'f, 'a>> {
+        let comments: Vec<_> = self
+            .comments
+            .iter()
+            .filter(|c| c.start >= start && c.start < end)
+            .collect();
+        let mut next = 0;
+        let mut entries: Vec<Entry> = Vec::new();
+        for item in items.into_iter().map(Some).chain(std::iter::once(None)) {
+            let limit = item.as_ref().map_or(end, |item| item.start);
+            while next < comments.len() && comments[next].start < limit {
+                let comment = comments[next];
+                let trailing = entries
+                    .last()
+                    .is_some_and(|e| !self.source[e.end..comment.start].contains('\n'));
+                entries.push(Entry {
+                    trailing,
+                    ..Entry::new(comment.start, comment.end(), Kind::Comment(comment))
+                });
+                next += 1;
+            }
+            let item = match item {
+                Some(item) => item,
+                None => break,
+            };
+            while next < comments.len() && comments[next].start < item.end {
+                if !item.kind.keeps_comments() {
+                    let comment = Kind::Comment(comments[next]);
+                    entries.push(Entry::new(item.start, item.start, comment));
+                }
+                next += 1;
+            }
+            entries.push(item);
+        }
+        entries
+    }
+
+    // Write each entry on its own line, except trailing comments.
+    fn write(&self, entries: &[Entry], level: usize, top: bool, out: &mut String) {
+        let mut previous: Option<(&Kind, usize)> = None;
+        for entry in entries {
+            if let (Some((_, end)), Kind::Comment(comment)) = (&mut previous, &entry.kind) {
+                if entry.trailing {
+                    out.push(' ');
+                    self.comment(comment, level, out);
+                    *end = entry.end;
+                    continue;
+                }
+            }
+            out.push('\n');
+            if let Some((kind, end)) = previous {
+                let blank = self.source[end..entry.start].matches('\n').count() > 1
+                    || (top && needs_blank(kind, &entry.kind));
+                if blank {
+                    out.push('\n');
+                }
+            }
+            out.push_str(&" ".repeat(self.printer.indent * level));
+            match &entry.kind {
+                Kind::Comment(comment) => self.comment(comment, level, out),
+                Kind::Header(_, header) => out.push_str(header),
+                Kind::Definition(definition) => self.definition(definition, entry, level, out),
+                Kind::Child(child) => out.push_str(child),
+            }
+            previous = Some((&entry.kind, entry.end));
+        }
+    }
+
+    fn comment(&self, comment: &Comment, level: usize, out: &mut String) {
+        let mut lines = comment.text.lines();
+        out.push_str(lines.next().unwrap_or_default().trim_end());
+        // Lines of a block comment are reindented, keeping their indentation relative to the
+        // first line. Lines starting with `*` are aligned under the opening `/*`.
+        let column = comment.start
+            - self.source[..comment.start]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+        let indent = " ".repeat(self.printer.indent * level);
+        for line in lines {
+            out.push('\n');
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            out.push_str(&indent);
+            if trimmed.starts_with('*') {
+                out.push(' ');
+                out.push_str(trimmed);
+            } else {
+                let skip = line
+                    .char_indices()
+                    .take(column)
+                    .find(|(_, c)| !c.is_whitespace())
+                    .map_or_else(|| line.len().min(column), |(i, _)| i);
+                out.push_str(line[skip..].trim_end());
+            }
+        }
+    }
+
+    fn definition(&self, definition: &Definition, entry: &Entry, level: usize, out: &mut String) {
+        let mut empty = definition.clone();
+        let children: Vec<_> = match (definition, &mut empty) {
+            (
+                Definition::Struct(Struct { fields, .. }),
+                Definition::Struct(Struct {
+                    fields: emptied, ..
+                }),
+            )
+            | (
+                Definition::Union(Union { fields, .. }),
+                Definition::Union(Union {
+                    fields: emptied, ..
+                }),
+            )
+            | (
+                Definition::Exception(Exception { fields, .. }),
+                Definition::Exception(Exception {
+                    fields: emptied, ..
+                }),
+            ) => {
+                emptied.clear();
+                self.fields(fields, level + 1)
+            }
+            (
+                Definition::Enum(Enum { children, .. }),
+                Definition::Enum(Enum {
+                    children: emptied, ..
+                }),
+            ) => {
+                emptied.clear();
+                children
+                    .iter()
+                    .map(|child| self.child(child, child.span, "", level + 1))
+                    .collect()
+            }
+            (
+                Definition::Service(Service { functions, .. }),
+                Definition::Service(Service {
+                    functions: emptied, ..
+                }),
+            ) => {
+                emptied.clear();
+                functions
+                    .iter()
+                    .map(|function| self.child(function, function.span, "", level + 1))
+                    .collect()
+            }
+            _ => return definition.print_to(self.printer, level, out),
+        };
+
+        // The definition without children ends with `{}`, possibly followed by annotations.
+        let printed = self.printer.print(&empty);
+        let (head, tail) = printed
+            .split_once("{}")
+            .expect("a definition with a block is printed with braces");
+        out.push_str(head);
+        out.push('{');
+        let entries = self.interleave(entry.start, entry.end, children);
+        if !entries.is_empty() {
+            self.write(&entries, level + 1, false, out);
+            out.push('\n');
+            out.push_str(&" ".repeat(self.printer.indent * level));
+        }
+        out.push('}');
+        out.push_str(tail);
+    }
+
+    // Print the fields of a struct, with their ids aligned to the right.
+    fn fields<'f>(&'f self, fields: &[Field], level: usize) -> Vec<Entry<'f, 'a>> {
+        let width = fields
+            .iter()
+            .filter_map(|f| f.id.map(|id| id.to_string().len()))
+            .max()
+            .unwrap_or(0);
+        fields
+            .iter()
+            .map(|field| {
+                let padding = field.id.map_or(0, |id| width - id.to_string().len());
+                self.child(field, field.span, &" ".repeat(padding), level)
+            })
+            .collect()
+    }
+
+    fn child<'f, T: Print>(
+        &'f self,
+        node: &T,
+        span: Span,
+        padding: &str,
+        level: usize,
+    ) -> Entry<'f, 'a> {
+        let mut printed = padding.to_string();
+        node.print_to(self.printer, level, &mut printed);
+        printed.push(self.printer.separator());
+        Entry::new(span.start, span.end, Kind::Child(printed))
+    }
+}
+
+// Top level items are separated by blank lines, except headers of the same kind and comments in
+// front of what they describe.
+fn needs_blank(previous: &Kind, next: &Kind) -> bool {
+    match (previous, next) {
+        (Kind::Definition(_), _) => true,
+        (Kind::Comment(_), _) => false,
+        (Kind::Header(a, _), Kind::Header(b, _)) => a != b,
+        (Kind::Header(..), Kind::Comment(_)) => false,
+        _ => true,
+    }
+}
+
+fn strip_docs(document: &mut Document) {
+    fn strip_fields(fields: &mut [Field]) {
+        fields.iter_mut().for_each(|f| f.doc = None);
+    }
+
+    for definition in &mut document.definitions {
+        match definition {
+            Definition::Const(x) => x.doc = None,
+            Definition::Typedef(x) => x.doc = None,
+            Definition::Enum(x) => {
+                x.doc = None;
+                x.children.iter_mut().for_each(|c| c.doc = None);
+            }
+            Definition::Struct(x) => {
+                x.doc = None;
+                strip_fields(&mut x.fields);
+            }
+            Definition::Union(x) => {
+                x.doc = None;
+                strip_fields(&mut x.fields);
+            }
+            Definition::Exception(x) => {
+                x.doc = None;
+                strip_fields(&mut x.fields);
+            }
+            Definition::Service(x) => {
+                x.doc = None;
+                for function in &mut x.functions {
+                    function.doc = None;
+                    strip_fields(&mut function.parameters);
+                    if let Some(exceptions) = &mut function.exceptions {
+                        strip_fields(exceptions);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = r##"# Generated by hand.
+namespace rs demo // for rust
+include "shared.thrift"
+include 'base.thrift'
+
+/**
+   * A user.
+   */
+struct User {
+    // Identity.
+    1:required i64 id;   /* never 0 */
+    10 : optional string name (go.tag='json:"name"')
+
+
+    /// Shown "as is".
+    100: string nick = "#not a comment",
+    // Nothing after this.
+}(final)
+// Between definitions.
+enum Status { A = 1, B /* second */ }
+
+service UserService {
+    User Get(1: i64 id /* the id */) // fetch
+    /* block
+       comment */
+}
+const i32 MAX = 10 # trailing
+"##;
+
+    const EXPECTED: &str = r##"# Generated by hand.
+namespace rs demo // for rust
+
+include "shared.thrift"
+include "base.thrift"
+
+/**
+ * A user.
+ */
+struct User {
+    // Identity.
+      1: required i64 id, /* never 0 */
+     10: optional string name (go.tag = 'json:"name"'),
+
+    /// Shown "as is".
+    100: string nick = "#not a comment",
+    // Nothing after this.
+} (final)
+
+// Between definitions.
+enum Status {
+    A = 1,
+    B, /* second */
+}
+
+service UserService {
+    /* the id */
+    User Get(1: i64 id), // fetch
+    /* block
+       comment */
+}
+
+const i32 MAX = 10 # trailing
+"##;
+
+    #[test]
+    fn test_format() {
+        let printer = Printer::default();
+        let formatted = format(SOURCE, &printer).unwrap();
+        assert_eq!(formatted, EXPECTED);
+        // Formatting is stable and does not change the meaning.
+        assert_eq!(format(&formatted, &printer).unwrap(), formatted);
+        assert_eq!(
+            printer.print(&Document::parse_complete(&formatted).unwrap()),
+            printer.print(&Document::parse_complete(SOURCE).unwrap())
+        );
+
+        assert_eq!(format("", &printer).unwrap(), "");
+        assert_eq!(format(" // only\n\n", &printer).unwrap(), "// only\n");
+        assert!(format("struct {", &printer).is_err());
+    }
+}
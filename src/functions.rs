@@ -1,17 +1,21 @@
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::char as cchar;
-use nom::combinator::{map, opt};
-use nom::multi::separated_list0;
+use nom::combinator::{cut, map, opt};
+use nom::error::context;
+use nom::multi::many0;
 use nom::sequence::{delimited, pair, preceded, terminated, tuple};
-use nom::IResult;
 
-use crate::basic::{Identifier, IdentifierRef, ListSeparator, Separator};
+use crate::annotation::{Annotations, AnnotationsRef};
+use crate::basic::{
+    documented, Doc, DocRef, Documented, Identifier, IdentifierRef, ListSeparator, Separator,
+};
 use crate::field::{Field, FieldRef};
+use crate::span::{located, LineIndex, Located, Span};
 use crate::types::{FieldType, FieldTypeRef};
-use crate::Parser;
+use crate::{IResult, Parser};
 
-// Function        ::=  'oneway'? FunctionType Identifier '(' Field* ')' Throws? ListSeparator?
+// Function        ::=  'oneway'? FunctionType Identifier '(' Field* ')' Throws? TypeAnnotations? ListSeparator?
 // FunctionType    ::=  FieldType | 'void'
 // Throws          ::=  'throws' '(' Field* ')'
 #[derive(Debug, Clone, PartialEq)]
@@ -22,50 +26,84 @@ pub struct FunctionRef<'a> {
     pub name: IdentifierRef<'a>,
     pub parameters: Vec<FieldRef<'a>>,
     pub exceptions: Option<Vec<FieldRef<'a>>>,
+    pub annotations: Option<AnnotationsRef<'a>>,
+    pub doc: Option<DocRef<'a>>,
+    pub span: Span,
 }
 
 impl<'a> Parser<'a> for FunctionRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
-        map(
-            tuple((
-                map(opt(terminated(tag("oneway"), Separator::parse)), |x| {
-                    x.is_some()
-                }),
-                terminated(
-                    alt((map(tag("void"), |_| None), map(FieldTypeRef::parse, Some))),
-                    Separator::parse,
-                ),
-                terminated(IdentifierRef::parse, opt(Separator::parse)),
-                terminated(
-                    delimited(
-                        cchar('('),
-                        separated_list0(Separator::parse, FieldRef::parse),
-                        cchar(')'),
+        terminated(
+            located(map(
+                tuple((
+                    map(opt(terminated(tag("oneway"), Separator::parse)), |x| {
+                        x.is_some()
+                    }),
+                    terminated(
+                        alt((map(tag("void"), |_| None), map(FieldTypeRef::parse, Some))),
+                        Separator::parse,
                     ),
-                    opt(Separator::parse),
-                ),
-                opt(preceded(
-                    pair(tag("throws"), Separator::parse),
-                    delimited(
+                    terminated(IdentifierRef::parse, opt(Separator::parse)),
+                    preceded(
                         cchar('('),
-                        separated_list0(Separator::parse, FieldRef::parse),
-                        cchar(')'),
+                        cut(terminated(
+                            Self::parse_fields,
+                            context("expected ')' to close parameters", cchar(')')),
+                        )),
                     ),
+                    opt(preceded(
+                        tuple((opt(Separator::parse), tag("throws"), opt(Separator::parse))),
+                        cut(delimited(
+                            context("expected '('", cchar('(')),
+                            Self::parse_fields,
+                            context("expected ')' to close exceptions", cchar(')')),
+                        )),
+                    )),
+                    opt(preceded(opt(Separator::parse), AnnotationsRef::parse)),
                 )),
-                opt(pair(opt(Separator::parse), ListSeparator::parse)),
+                |(oneway, returns, name, parameters, exceptions, annotations)| Self {
+                    oneway,
+                    returns,
+                    name,
+                    parameters,
+                    exceptions,
+                    annotations,
+                    doc: None,
+                    span: Span::default(),
+                },
             )),
-            |(oneway, returns, name, parameters, exceptions, _)| Self {
-                oneway,
-                returns,
-                name,
-                parameters,
-                exceptions,
-            },
+            opt(pair(opt(Separator::parse), ListSeparator::parse)),
         )(input)
     }
 }
 
+impl<'a> FunctionRef<'a> {
+    fn parse_fields(input: &'a str) -> IResult<&'a str, Vec<FieldRef<'a>>> {
+        terminated(many0(documented(FieldRef::parse)), opt(Separator::parse))(input)
+    }
+}
+
+impl<'a> Located for FunctionRef<'a> {
+    fn span_mut(&mut self) -> &mut Span {
+        &mut self.span
+    }
+
+    fn relocate_children(&mut self, index: &LineIndex) {
+        self.parameters
+            .iter_mut()
+            .chain(self.exceptions.iter_mut().flatten())
+            .for_each(|field| field.relocate(index));
+    }
+}
+
+impl<'a> Documented<'a> for FunctionRef<'a> {
+    fn doc_mut(&mut self) -> &mut Option<DocRef<'a>> {
+        &mut self.doc
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Function {
     pub oneway: bool,
     // returns None means void
@@ -73,6 +111,9 @@ pub struct Function {
     pub name: Identifier,
     pub parameters: Vec<Field>,
     pub exceptions: Option<Vec<Field>>,
+    pub annotations: Option<Annotations>,
+    pub doc: Option<Doc>,
+    pub span: Span,
 }
 
 impl<'a> From<FunctionRef<'a>> for Function {
@@ -85,6 +126,9 @@ impl<'a> From<FunctionRef<'a>> for Function {
             exceptions: r
                 .exceptions
                 .map(|x| x.into_iter().map(Into::into).collect()),
+            annotations: r.annotations.map(Into::into),
+            doc: r.doc.map(Into::into),
+            span: r.span,
         }
     }
 }
@@ -97,6 +141,7 @@ impl<'a> Parser<'a> for Function {
 
 #[cfg(test)]
 mod test {
+    use crate::annotation::AnnotationRef;
     use crate::basic::LiteralRef;
     use crate::constant::{ConstValueRef, IntConstant};
 
@@ -114,8 +159,14 @@ mod test {
                 type_: FieldTypeRef::String,
                 name: IdentifierRef::from("name"),
                 default: Some(ConstValueRef::Literal(LiteralRef::from("ihciah"))),
+                annotations: None,
+                doc: None,
+                span: Span::at(15, 44, 1, 16),
             }],
             exceptions: None,
+            annotations: None,
+            span: Span::at(0, 45, 1, 1),
+            doc: None,
         };
         assert_eq!(
             FunctionRef::parse("string GetUser(required string name='ihciah')")
@@ -134,8 +185,14 @@ mod test {
                 type_: FieldTypeRef::I32,
                 name: IdentifierRef::from("age"),
                 default: None,
+                annotations: None,
+                doc: None,
+                span: Span::at(23, 45, 1, 24),
             }],
             exceptions: None,
+            annotations: None,
+            span: Span::at(0, 46, 1, 1),
+            doc: None,
         };
         assert_eq!(
             FunctionRef::parse("oneway void DeleteUser(10086:optional i32 age)")
@@ -143,5 +200,23 @@ mod test {
                 .1,
             expected
         );
+
+        let expected = FunctionRef {
+            annotations: Some(AnnotationsRef::from(vec![AnnotationRef {
+                key: IdentifierRef::from("api.get"),
+                value: Some(LiteralRef::from("/user")),
+            }])),
+            // Annotations are part of the function, the separator is not.
+            span: Span::at(0, 66, 1, 1),
+            ..expected
+        };
+        assert_eq!(
+            FunctionRef::parse(
+                "oneway void DeleteUser(10086:optional i32 age) (api.get = '/user');"
+            )
+            .unwrap()
+            .1,
+            expected
+        );
     }
 }
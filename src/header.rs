@@ -1,11 +1,11 @@
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::map;
-use nom::sequence::{pair, preceded, tuple};
-use nom::IResult;
+use nom::combinator::{cut, map};
+use nom::error::context;
+use nom::sequence::{pair, preceded};
 
 use crate::basic::{Identifier, IdentifierRef, Literal, LiteralRef, Separator};
-use crate::Parser;
+use crate::{IResult, Parser};
 
 // Include         ::=  'include' Literal
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
@@ -14,13 +14,17 @@ pub struct IncludeRef<'a>(LiteralRef<'a>);
 impl<'a> Parser<'a> for IncludeRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
         map(
-            preceded(pair(tag("include"), Separator::parse), LiteralRef::parse),
+            preceded(
+                pair(tag("include"), Separator::parse),
+                cut(context("expected include path", LiteralRef::parse)),
+            ),
             Self,
         )(input)
     }
 }
 
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Include(Literal);
 
 impl<'a> From<IncludeRef<'a>> for Include {
@@ -44,7 +48,7 @@ impl<'a> Parser<'a> for CppIncludeRef<'a> {
         map(
             preceded(
                 pair(tag("cpp_include"), Separator::parse),
-                LiteralRef::parse,
+                cut(context("expected include path", LiteralRef::parse)),
             ),
             Self,
         )(input)
@@ -52,6 +56,7 @@ impl<'a> Parser<'a> for CppIncludeRef<'a> {
 }
 
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CppInclude(Literal);
 
 impl<'a> From<CppIncludeRef<'a>> for CppInclude {
@@ -83,12 +88,17 @@ pub struct NamespaceScopeRef<'a>(&'a str);
 impl<'a> Parser<'a> for NamespaceRef<'a> {
     fn parse(input: &'a str) -> IResult<&'a str, Self> {
         map(
-            tuple((
-                tag("namespace"),
-                preceded(Separator::parse, NamespaceScopeRef::parse),
-                preceded(Separator::parse, IdentifierRef::parse),
-            )),
-            |(_, scope, name)| Self { scope, name },
+            preceded(
+                pair(tag("namespace"), Separator::parse),
+                cut(pair(
+                    context("expected namespace scope", NamespaceScopeRef::parse),
+                    context(
+                        "expected namespace name",
+                        preceded(Separator::parse, IdentifierRef::parse),
+                    ),
+                )),
+            ),
+            |(scope, name)| Self { scope, name },
         )(input)
     }
 }
@@ -110,8 +120,8 @@ impl<'a> Parser<'a> for NamespaceScopeRef<'a> {
                 tag("netstd"),
                 tag("perl"),
                 tag("php"),
-                tag("py"),
                 tag("py.twisted"),
+                tag("py"),
                 tag("rb"),
                 tag("st"),
                 tag("xsd"),
@@ -122,12 +132,14 @@ impl<'a> Parser<'a> for NamespaceScopeRef<'a> {
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Namespace {
     pub scope: NamespaceScope,
     pub name: Identifier,
 }
 
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NamespaceScope(String);
 
 impl<'a> From<NamespaceRef<'a>> for Namespace {
@@ -0,0 +1,344 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while_m_n};
+use nom::character::complete::{char as cchar, digit1, multispace0, one_of};
+use nom::combinator::{all_consuming, cut, map, map_res, opt, recognize, value};
+use nom::error::{context, VerboseError, VerboseErrorKind};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::Err;
+
+use crate::{IResult, ParseError};
+
+// A JSON value for the generators to write. Objects keep their keys in insertion order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // Parse `text`, which must not nest values more than `max_depth` levels deep.
+    pub(crate) fn parse(text: &str, max_depth: usize) -> Result<Self, ParseError> {
+        all_consuming(delimited(multispace0, |i| json(i, max_depth), multispace0))(text)
+            .map(|(_, json)| json)
+            .map_err(|e| ParseError::new(text, e))
+    }
+
+    // Indent nested values by two spaces, on their own lines.
+    pub(crate) fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(Some(0), &mut out);
+        out
+    }
+
+    // `level` is None for compact output.
+    fn write(&self, level: Option<usize>, out: &mut String) {
+        let newline = |out: &mut String, level: usize| {
+            out.push('\n');
+            out.push_str(&"  ".repeat(level));
+        };
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(v) => out.push_str(if *v { "true" } else { "false" }),
+            Json::Int(v) => out.push_str(&v.to_string()),
+            Json::Double(v) if v.is_finite() => out.push_str(&format!("{:?}", v)),
+            // JSON has no infinity or NaN.
+            Json::Double(_) => out.push_str("null"),
+            Json::String(v) => write_string(v, out),
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Object(items) if items.is_empty() => out.push_str("{}"),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    if let Some(level) = level {
+                        newline(out, level + 1);
+                    }
+                    item.write(level.map(|l| l + 1), out);
+                }
+                if let Some(level) = level {
+                    newline(out, level);
+                }
+                out.push(']');
+            }
+            Json::Object(items) => {
+                out.push('{');
+                for (i, (key, value)) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    if let Some(level) = level {
+                        newline(out, level + 1);
+                    }
+                    write_string(key, out);
+                    out.push(':');
+                    if level.is_some() {
+                        out.push(' ');
+                    }
+                    value.write(level.map(|l| l + 1), out);
+                }
+                if let Some(level) = level {
+                    newline(out, level);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn failure<'a, O>(input: &'a str, message: &'static str) -> IResult<&'a str, O> {
+    Err(Err::Failure(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context(message))],
+    }))
+}
+
+// A value nested at most `depth` levels deep.
+fn json(input: &str, depth: usize) -> IResult<&str, Json> {
+    if depth == 0 {
+        return failure(input, "values nested too deep");
+    }
+    let ws = |i| delimited(multispace0, |i| json(i, depth - 1), multispace0)(i);
+    let array = map(
+        preceded(
+            cchar('['),
+            cut(terminated(
+                terminated(items(ws), multispace0),
+                context("expected ',' or ']'", cchar(']')),
+            )),
+        ),
+        Json::Array,
+    );
+    // Once there is a key, the rest of the member must follow.
+    let member = separated_pair(
+        delimited(multispace0, string, multispace0),
+        cut(context("expected ':'", cchar(':'))),
+        cut(ws),
+    );
+    let object = map(
+        preceded(
+            cchar('{'),
+            cut(terminated(
+                terminated(items(member), multispace0),
+                context("expected ',' or '}'", cchar('}')),
+            )),
+        ),
+        Json::Object,
+    );
+    context(
+        "expected a JSON value",
+        alt((
+            value(Json::Null, tag("null")),
+            value(Json::Bool(true), tag("true")),
+            value(Json::Bool(false), tag("false")),
+            number,
+            map(string, Json::String),
+            array,
+            object,
+        )),
+    )(input)
+}
+
+// Items separated by commas. Unlike `separated_list0`, an item must follow every comma.
+fn items<'a, O>(
+    mut item: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>> {
+    move |input| {
+        let (mut input, first) = match item(input) {
+            Ok(found) => found,
+            Err(Err::Error(_)) => return Ok((input, Vec::new())),
+            Err(e) => return Err(e),
+        };
+        let mut items = vec![first];
+        while let Ok((rest, _)) = cchar::<_, VerboseError<&str>>(',')(input) {
+            let (rest, next) = cut(&mut item)(rest)?;
+            items.push(next);
+            input = rest;
+        }
+        Ok((input, items))
+    }
+}
+
+fn number(input: &str) -> IResult<&str, Json> {
+    let fraction = pair(cchar('.'), digit1);
+    let exponent = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+    map_res(
+        recognize(tuple((
+            opt(cchar('-')),
+            digit1,
+            opt(fraction),
+            opt(exponent),
+        ))),
+        |text: &str| {
+            text.parse()
+                .map(Json::Int)
+                .or_else(|_| text.parse().map(Json::Double))
+        },
+    )(input)
+}
+
+fn string(input: &str) -> IResult<&str, String> {
+    let (mut input, _) = cchar('"')(input)?;
+    let mut out = String::new();
+    loop {
+        let mut chars = input.chars();
+        match chars.next() {
+            None => return failure(input, "expected '\"' to close the string"),
+            Some('"') => return Ok((chars.as_str(), out)),
+            Some('\\') => {
+                let (rest, c) = escape(chars.as_str())?;
+                out.push(c);
+                input = rest;
+            }
+            Some(c) if (c as u32) < 0x20 => {
+                return failure(input, "control characters in strings must be escaped")
+            }
+            Some(c) => {
+                out.push(c);
+                input = chars.as_str();
+            }
+        }
+    }
+}
+
+// What follows a backslash. Unpaired surrogates become the replacement character.
+fn escape(input: &str) -> IResult<&str, char> {
+    let hex = |i| {
+        map_res(take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()), |h| {
+            u32::from_str_radix(h, 16)
+        })(i)
+    };
+    let (input, c) = context(
+        "invalid escape",
+        cut(alt((
+            value('"' as u32, cchar('"')),
+            value('\\' as u32, cchar('\\')),
+            value('/' as u32, cchar('/')),
+            value(8, cchar('b')),
+            value(12, cchar('f')),
+            value('\n' as u32, cchar('n')),
+            value('\r' as u32, cchar('r')),
+            value('\t' as u32, cchar('t')),
+            preceded(cchar('u'), hex),
+        ))),
+    )(input)?;
+    if (0xd800..0xdc00).contains(&c) {
+        if let Ok((rest, low)) = preceded(tag("\\u"), hex)(input) {
+            if (0xdc00..0xe000).contains(&low) {
+                let c = 0x10000 + ((c - 0xd800) << 10) + (low - 0xdc00);
+                return Ok((rest, char::try_from(c).unwrap_or('\u{fffd}')));
+            }
+        }
+    }
+    Ok((input, char::try_from(c).unwrap_or('\u{fffd}')))
+}
+
+// Compact output.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(None, &mut out);
+        f.write_str(&out)
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let value = Json::Object(vec![
+            (
+                "a".into(),
+                Json::Array(vec![Json::Int(1), Json::Double(2.0)]),
+            ),
+            ("b".into(), Json::String("q\"\n\u{1}".into())),
+            ("c".into(), Json::Object(vec![])),
+            ("d".into(), Json::Bool(false)),
+            ("e".into(), Json::Null),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,2.0],"b":"q\"\n\u0001","c":{},"d":false,"e":null}"#
+        );
+        assert_eq!(
+            value.pretty(),
+            "{\n  \"a\": [\n    1,\n    2.0\n  ],\n  \"b\": \"q\\\"\\n\\u0001\",\n  \"c\": {},\n  \"d\": false,\n  \"e\": null\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let value = Json::parse(
+            r#"{"a":[1,2.0],"b":"q\"\n\u0001","c":{},"d":false,"e":null}"#,
+            64,
+        );
+        assert_eq!(
+            value,
+            Ok(Json::Object(vec![
+                (
+                    "a".into(),
+                    Json::Array(vec![Json::Int(1), Json::Double(2.0)]),
+                ),
+                ("b".into(), Json::String("q\"\n\u{1}".into())),
+                ("c".into(), Json::Object(vec![])),
+                ("d".into(), Json::Bool(false)),
+                ("e".into(), Json::Null),
+            ]))
+        );
+        assert_eq!(
+            Json::parse(
+                " [ -1.5e3 , 9223372036854775808, \"\\ud83d\\ude00\\/\" ] ",
+                64
+            ),
+            Ok(Json::Array(vec![
+                Json::Double(-1500.0),
+                Json::Double(9223372036854775808.0),
+                Json::String("\u{1f600}/".into()),
+            ]))
+        );
+
+        let error = Json::parse("{\"a\": [1,]}", 64).unwrap_err();
+        assert_eq!(
+            (error.message.as_str(), error.column),
+            ("expected a JSON value", 10)
+        );
+        let error = Json::parse("{\"a\" 1}", 64).unwrap_err();
+        assert_eq!((error.message.as_str(), error.column), ("expected ':'", 6));
+        let error = Json::parse("\"a\\x\"", 64).unwrap_err();
+        assert_eq!(
+            (error.message.as_str(), error.column),
+            ("invalid escape", 4)
+        );
+        let error = Json::parse(&"[".repeat(100), 64).unwrap_err();
+        assert_eq!(error.message, "values nested too deep");
+        assert!(Json::parse("1 2", 64).is_err());
+    }
+}
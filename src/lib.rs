@@ -1,15 +1,34 @@
-pub use nom::{Err, error::{Error, ErrorKind}};
-use nom::IResult;
+pub use nom::{
+    error::{Error, ErrorKind, VerboseError},
+    Err,
+};
 
+pub use crate::error::{Diagnostic, ParseError, Severity};
+
+pub mod annotation;
 pub mod basic;
+pub mod build;
+pub mod codegen;
 pub mod constant;
 pub mod definition;
 pub mod document;
+pub mod dynamic;
+pub mod error;
+pub mod eval;
 pub mod field;
+pub mod formatter;
 pub mod functions;
 pub mod header;
+mod json;
+pub mod printer;
+pub mod resolve;
+pub mod span;
 pub mod types;
 mod utils;
+pub mod validate;
+pub mod workspace;
+
+pub type IResult<I, O> = nom::IResult<I, O, VerboseError<I>>;
 
 pub trait Parser<'a>: Sized {
     fn parse(input: &'a str) -> IResult<&'a str, Self>;
@@ -0,0 +1,781 @@
+use std::fmt;
+
+use crate::annotation::{Annotation, Annotations};
+use crate::basic::{Doc, Identifier, Literal};
+use crate::constant::{ConstList, ConstMap, ConstValue, DoubleConstant, IntConstant};
+use crate::definition::{
+    Const, Definition, Enum, EnumValue, Exception, Service, Struct, Typedef, Union,
+};
+use crate::document::Document;
+use crate::field::Field;
+use crate::functions::Function;
+use crate::header::{CppInclude, Include, Namespace, NamespaceScope};
+use crate::types::FieldType;
+
+// The separator put after each field, enum value and function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeparatorStyle {
+    Comma,
+    Semicolon,
+}
+
+// The quote put around literals, unless a literal has it unescaped, then the other one is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    Double,
+    Single,
+}
+
+// Renders AST nodes back to Thrift IDL. The output parses back to the same nodes, except that
+// doc comments are normalized to /** */ blocks and spans point into the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printer {
+    // Number of spaces per indentation level.
+    pub indent: usize,
+    pub separator: SeparatorStyle,
+    pub quote: QuoteStyle,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            separator: SeparatorStyle::Comma,
+            quote: QuoteStyle::Double,
+        }
+    }
+}
+
+impl Printer {
+    pub fn print<T: Print + ?Sized>(&self, node: &T) -> String {
+        let mut out = String::new();
+        node.print_to(self, 0, &mut out);
+        out
+    }
+
+    fn newline(&self, level: usize, out: &mut String) {
+        out.push('\n');
+        out.push_str(&" ".repeat(self.indent * level));
+    }
+
+    pub(crate) fn separator(&self) -> char {
+        match self.separator {
+            SeparatorStyle::Comma => ',',
+            SeparatorStyle::Semicolon => ';',
+        }
+    }
+
+    // Write the doc comment followed by a new line, if there is one.
+    fn doc(&self, doc: &Option<Doc>, level: usize, out: &mut String) {
+        if let Some(doc) = doc {
+            doc.print_to(self, level, out);
+            self.newline(level, out);
+        }
+    }
+
+    // Write ` (key = "value", ...)` if there are annotations.
+    fn annotations(&self, annotations: &Option<Annotations>, out: &mut String) {
+        if let Some(annotations) = annotations {
+            out.push(' ');
+            annotations.print_to(self, 0, out);
+        }
+    }
+
+    // Write `{`, each item on its own line followed by the separator, then `}`.
+    fn block<T: Print>(&self, items: &[T], level: usize, out: &mut String) {
+        out.push('{');
+        if !items.is_empty() {
+            for item in items {
+                self.newline(level + 1, out);
+                item.print_to(self, level + 1, out);
+                out.push(self.separator());
+            }
+            self.newline(level, out);
+        }
+        out.push('}');
+    }
+
+    // Write the fields in parentheses, on one line unless some of them have doc comments.
+    fn fields(&self, fields: &[Field], level: usize, out: &mut String) {
+        out.push('(');
+        if fields.iter().any(|f| f.doc.is_some()) {
+            for field in fields {
+                self.newline(level + 1, out);
+                field.print_to(self, level + 1, out);
+                out.push(self.separator());
+            }
+            self.newline(level, out);
+        } else {
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                field.print_to(self, level, out);
+            }
+        }
+        out.push(')');
+    }
+
+    fn structure(
+        &self,
+        keyword: &str,
+        name: &Identifier,
+        fields: &[Field],
+        level: usize,
+        out: &mut String,
+    ) {
+        out.push_str(keyword);
+        out.push(' ');
+        out.push_str(name);
+        out.push(' ');
+        self.block(fields, level, out);
+    }
+}
+
+// Nodes a Printer can render.
+pub trait Print {
+    // Write the node to `out`. The current line is already indented to `level`, which is the level
+    // new lines are indented to.
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String);
+}
+
+impl<T: Print + ?Sized> Print for &T {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        (**self).print_to(printer, level, out)
+    }
+}
+
+impl Print for Document {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        let mut sections = Vec::new();
+        let headers: [Vec<&dyn Print>; 3] = [
+            self.includes.iter().map(|x| x as &dyn Print).collect(),
+            self.cpp_includes.iter().map(|x| x as &dyn Print).collect(),
+            self.namespaces.iter().map(|x| x as &dyn Print).collect(),
+        ];
+        for header in headers.iter().filter(|h| !h.is_empty()) {
+            let lines: Vec<_> = header.iter().map(|h| printer.print(h)).collect();
+            sections.push(lines.join("\n"));
+        }
+        for definition in &self.definitions {
+            let mut section = String::new();
+            definition.print_to(printer, level, &mut section);
+            sections.push(section);
+        }
+        out.push_str(&sections.join("\n\n"));
+        if !sections.is_empty() {
+            out.push('\n');
+        }
+    }
+}
+
+impl Print for Include {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push_str("include ");
+        Literal::print_to(self, printer, level, out);
+    }
+}
+
+impl Print for CppInclude {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push_str("cpp_include ");
+        Literal::print_to(self, printer, level, out);
+    }
+}
+
+impl Print for Namespace {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push_str("namespace ");
+        self.scope.print_to(printer, level, out);
+        out.push(' ');
+        self.name.print_to(printer, level, out);
+    }
+}
+
+impl Print for NamespaceScope {
+    fn print_to(&self, _: &Printer, _: usize, out: &mut String) {
+        out.push_str(self);
+    }
+}
+
+impl Print for Definition {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        match self {
+            Definition::Const(x) => x.print_to(printer, level, out),
+            Definition::Typedef(x) => x.print_to(printer, level, out),
+            Definition::Enum(x) => x.print_to(printer, level, out),
+            Definition::Struct(x) => x.print_to(printer, level, out),
+            Definition::Union(x) => x.print_to(printer, level, out),
+            Definition::Exception(x) => x.print_to(printer, level, out),
+            Definition::Service(x) => x.print_to(printer, level, out),
+        }
+    }
+}
+
+impl Print for Const {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        out.push_str("const ");
+        self.type_.print_to(printer, level, out);
+        out.push(' ');
+        out.push_str(&self.name);
+        out.push_str(" = ");
+        self.value.print_to(printer, level, out);
+    }
+}
+
+impl Print for Typedef {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        out.push_str("typedef ");
+        self.old.print_to(printer, level, out);
+        out.push(' ');
+        out.push_str(&self.alias);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Enum {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        out.push_str("enum ");
+        out.push_str(&self.name);
+        out.push(' ');
+        printer.block(&self.children, level, out);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for EnumValue {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        out.push_str(&self.name);
+        if let Some(value) = &self.value {
+            out.push_str(" = ");
+            value.print_to(printer, level, out);
+        }
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Struct {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        printer.structure("struct", &self.name, &self.fields, level, out);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Union {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        printer.structure("union", &self.name, &self.fields, level, out);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Exception {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        printer.structure("exception", &self.name, &self.fields, level, out);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Service {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        out.push_str("service ");
+        out.push_str(&self.name);
+        if let Some(extension) = &self.extension {
+            out.push_str(" extends ");
+            out.push_str(extension);
+        }
+        out.push(' ');
+        printer.block(&self.functions, level, out);
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Function {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        if self.oneway {
+            out.push_str("oneway ");
+        }
+        match &self.returns {
+            Some(returns) => returns.print_to(printer, level, out),
+            None => out.push_str("void"),
+        }
+        out.push(' ');
+        out.push_str(&self.name);
+        printer.fields(&self.parameters, level, out);
+        if let Some(exceptions) = &self.exceptions {
+            out.push_str(" throws ");
+            printer.fields(exceptions, level, out);
+        }
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for Field {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        printer.doc(&self.doc, level, out);
+        if let Some(id) = &self.id {
+            id.print_to(printer, level, out);
+            out.push_str(": ");
+        }
+        match self.required {
+            Some(true) => out.push_str("required "),
+            Some(false) => out.push_str("optional "),
+            None => {}
+        }
+        self.type_.print_to(printer, level, out);
+        out.push(' ');
+        out.push_str(&self.name);
+        if let Some(default) = &self.default {
+            out.push_str(" = ");
+            default.print_to(printer, level, out);
+        }
+        printer.annotations(&self.annotations, out);
+    }
+}
+
+impl Print for FieldType {
+    fn print_to(&self, printer: &Printer, _: usize, out: &mut String) {
+        let annotations = match self {
+            FieldType::Identifier(name) => {
+                out.push_str(name);
+                return;
+            }
+            FieldType::Bool => return out.push_str("bool"),
+            FieldType::Byte => return out.push_str("byte"),
+            FieldType::I8 => return out.push_str("i8"),
+            FieldType::I16 => return out.push_str("i16"),
+            FieldType::I32 => return out.push_str("i32"),
+            FieldType::I64 => return out.push_str("i64"),
+            FieldType::Double => return out.push_str("double"),
+            FieldType::String => return out.push_str("string"),
+            FieldType::Binary => return out.push_str("binary"),
+            FieldType::Map(key, value, annotations) => {
+                out.push_str("map<");
+                key.print_to(printer, 0, out);
+                out.push_str(", ");
+                value.print_to(printer, 0, out);
+                annotations
+            }
+            FieldType::Set(value, annotations) => {
+                out.push_str("set<");
+                value.print_to(printer, 0, out);
+                annotations
+            }
+            FieldType::List(value, annotations) => {
+                out.push_str("list<");
+                value.print_to(printer, 0, out);
+                annotations
+            }
+        };
+        out.push('>');
+        printer.annotations(annotations, out);
+    }
+}
+
+impl Print for ConstValue {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        match self {
+            ConstValue::Identifier(x) => x.print_to(printer, level, out),
+            ConstValue::Literal(x) => x.print_to(printer, level, out),
+            ConstValue::Double(x) => x.print_to(printer, level, out),
+            ConstValue::Int(x) => x.print_to(printer, level, out),
+            ConstValue::List(x) => x.print_to(printer, level, out),
+            ConstValue::Map(x) => x.print_to(printer, level, out),
+        }
+    }
+}
+
+impl Print for ConstList {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push('[');
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            value.print_to(printer, level, out);
+        }
+        out.push(']');
+    }
+}
+
+impl Print for ConstMap {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push('{');
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            key.print_to(printer, level, out);
+            out.push_str(": ");
+            value.print_to(printer, level, out);
+        }
+        out.push('}');
+    }
+}
+
+impl Print for IntConstant {
+    fn print_to(&self, _: &Printer, _: usize, out: &mut String) {
+        out.push_str(&self.into_inner().to_string());
+    }
+}
+
+impl Print for DoubleConstant {
+    fn print_to(&self, _: &Printer, _: usize, out: &mut String) {
+        // Debug keeps the fraction of whole numbers, so they are not read back as ints.
+        out.push_str(&format!("{:?}", self.into_inner()));
+    }
+}
+
+impl Print for Literal {
+    fn print_to(&self, printer: &Printer, _: usize, out: &mut String) {
+        let (preferred, other) = match printer.quote {
+            QuoteStyle::Double => ('"', '\''),
+            QuoteStyle::Single => ('\'', '"'),
+        };
+        // Literals read back the same in a quote they do not have unescaped. Only literals not
+        // from the source can have both, the preferred one is escaped in them.
+        let quote = match (has_unescaped(self, preferred), has_unescaped(self, other)) {
+            (true, false) => other,
+            _ => preferred,
+        };
+        out.push(quote);
+        let mut chars = self.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                // Escape sequences are kept as they are.
+                out.push(c);
+                out.extend(chars.next());
+            } else {
+                if c == quote {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+        out.push(quote);
+    }
+}
+
+fn has_unescaped(literal: &str, quote: char) -> bool {
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+impl Print for Identifier {
+    fn print_to(&self, _: &Printer, _: usize, out: &mut String) {
+        out.push_str(self);
+    }
+}
+
+impl Print for Annotations {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push('(');
+        for (i, annotation) in self.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            annotation.print_to(printer, level, out);
+        }
+        out.push(')');
+    }
+}
+
+impl Print for Annotation {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        out.push_str(&self.key);
+        if let Some(value) = &self.value {
+            out.push_str(" = ");
+            value.print_to(printer, level, out);
+        }
+    }
+}
+
+impl Print for Doc {
+    fn print_to(&self, printer: &Printer, level: usize, out: &mut String) {
+        let text = self.text();
+        if !text.contains('\n') {
+            out.push_str("/** ");
+            out.push_str(&text);
+            out.push_str(" */");
+            return;
+        }
+        out.push_str("/**");
+        for line in text.lines() {
+            printer.newline(level, out);
+            out.push_str(" *");
+            if !line.is_empty() {
+                out.push(' ');
+                out.push_str(line);
+            }
+        }
+        printer.newline(level, out);
+        out.push_str(" */");
+    }
+}
+
+// Display renders with the default Printer.
+macro_rules! impl_display {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl fmt::Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(&Printer::default().print(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_display!(
+    Document,
+    Include,
+    CppInclude,
+    Namespace,
+    NamespaceScope,
+    Definition,
+    Const,
+    Typedef,
+    Enum,
+    EnumValue,
+    Struct,
+    Union,
+    Exception,
+    Service,
+    Function,
+    Field,
+    FieldType,
+    ConstValue,
+    ConstList,
+    ConstMap,
+    IntConstant,
+    DoubleConstant,
+    Literal,
+    Identifier,
+    Annotations,
+    Annotation,
+    Doc,
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::Span;
+    use crate::Parser;
+
+    const SOURCE: &str = r#"
+include "shared.thrift"
+cpp_include 'vector'
+namespace rs demo
+namespace * demo.all
+
+/** The id of a user. */
+typedef i64 Id (cpp.type = "int64_t")
+const map<string, list<double>> LIMITS = {'a': [1.0, 2.5e3], "b": []}
+
+/**
+ * Status of a user.
+ *
+ * Only active users can log in.
+ */
+enum Status {
+    /// Just signed up.
+    NEW
+    ACTIVE = 5 (note = 'it\'s "on"')
+}
+
+struct User {
+    1: required Id id = 0,
+    2: optional map<string, string> (cpp.template = "std::map") extra;
+    string nick (go.tag = 'json:"nick"')
+} (final)
+
+union Either {}
+
+exception NotFound { 1: string message }
+
+service UserService extends shared.Base {
+    oneway void Ping()
+    User Get(/** Which user. */ 1: Id id, 2: bool cached) throws (1: NotFound e) (api.get = "/user")
+}
+"#;
+
+    #[test]
+    fn test_print_document() {
+        let document = Document::parse_complete(SOURCE).unwrap();
+        let printed = document.to_string();
+        assert_eq!(
+            printed,
+            r#"include "shared.thrift"
+
+cpp_include "vector"
+
+namespace rs demo
+namespace * demo.all
+
+/** The id of a user. */
+typedef i64 Id (cpp.type = "int64_t")
+
+const map<string, list<double>> LIMITS = {"a": [1.0, 2500.0], "b": []}
+
+/**
+ * Status of a user.
+ *
+ * Only active users can log in.
+ */
+enum Status {
+    /** Just signed up. */
+    NEW,
+    ACTIVE = 5 (note = 'it\'s "on"'),
+}
+
+struct User {
+    1: required Id id = 0,
+    2: optional map<string, string> (cpp.template = "std::map") extra,
+    string nick (go.tag = 'json:"nick"'),
+} (final)
+
+union Either {}
+
+exception NotFound {
+    1: string message,
+}
+
+service UserService extends shared.Base {
+    oneway void Ping(),
+    User Get(
+        /** Which user. */
+        1: Id id,
+        2: bool cached,
+    ) throws (1: NotFound e) (api.get = "/user"),
+}
+"#
+        );
+
+        // Printing is stable and keeps everything but spans and the spelling of doc comments.
+        let reparsed = Document::parse_complete(&printed).unwrap();
+        assert_eq!(reparsed.to_string(), printed);
+        let status = reparsed.enums().next().unwrap();
+        assert_eq!(
+            status.children[0].doc.as_ref().unwrap().text(),
+            "Just signed up."
+        );
+        assert_eq!(normalized(reparsed), normalized(document));
+    }
+
+    // `document` without spans, and with doc comments as their text.
+    fn normalized(mut document: Document) -> Document {
+        fn doc(doc: &mut Option<Doc>) {
+            *doc = doc.as_ref().map(|d| Doc::from(d.text()));
+        }
+        fn field(f: &mut Field) {
+            doc(&mut f.doc);
+            f.span = Span::default();
+        }
+        for definition in &mut document.definitions {
+            match definition {
+                Definition::Const(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                }
+                Definition::Typedef(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                }
+                Definition::Enum(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                    for value in &mut x.children {
+                        doc(&mut value.doc);
+                        value.span = Span::default();
+                    }
+                }
+                Definition::Struct(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                    x.fields.iter_mut().for_each(field);
+                }
+                Definition::Union(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                    x.fields.iter_mut().for_each(field);
+                }
+                Definition::Exception(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                    x.fields.iter_mut().for_each(field);
+                }
+                Definition::Service(x) => {
+                    doc(&mut x.doc);
+                    x.span = Span::default();
+                    for function in &mut x.functions {
+                        doc(&mut function.doc);
+                        function.span = Span::default();
+                        function.parameters.iter_mut().for_each(field);
+                        function.exceptions.iter_mut().flatten().for_each(field);
+                    }
+                }
+            }
+        }
+        document
+    }
+
+    #[test]
+    fn test_printer_options() {
+        let printer = Printer {
+            indent: 2,
+            separator: SeparatorStyle::Semicolon,
+            quote: QuoteStyle::Single,
+        };
+        let document = Document::parse_complete(
+            r#"enum E { A = 1, B } struct S { 1: string s = "it's \"x\"" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            printer.print(&document),
+            "enum E {\n  A = 1;\n  B;\n}\n\nstruct S {\n  1: string s = \"it's \\\"x\\\"\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let field = Field::parse("1: optional list<i32> (a) ids = [1, 2] (b = 'c')")
+            .unwrap()
+            .1;
+        assert_eq!(
+            field.to_string(),
+            r#"1: optional list<i32> (a) ids = [1, 2] (b = "c")"#
+        );
+        assert_eq!(
+            FieldType::parse("map<string,set<binary>>")
+                .unwrap()
+                .1
+                .to_string(),
+            "map<string, set<binary>>"
+        );
+        assert_eq!(
+            ConstValue::parse("-1.5e-7").unwrap().1.to_string(),
+            "-1.5e-7"
+        );
+        assert_eq!(
+            Printer::default().print(&ConstValue::parse("1.0").unwrap().1),
+            "1.0"
+        );
+    }
+}
@@ -0,0 +1,885 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::constant::ConstValue;
+use crate::definition::{Definition, Service};
+use crate::field::Field;
+use crate::functions::Function;
+use crate::span::Span;
+use crate::types::FieldType;
+use crate::workspace::{Program, Workspace};
+use crate::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Const,
+    Typedef,
+    Enum,
+    EnumValue,
+    Struct,
+    Union,
+    Exception,
+    Service,
+}
+
+impl SymbolKind {
+    // Whether the symbol can be used as a field type.
+    pub fn is_type(self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Typedef
+                | SymbolKind::Enum
+                | SymbolKind::Struct
+                | SymbolKind::Union
+                | SymbolKind::Exception
+        )
+    }
+
+    // Whether the symbol can be used as a constant value.
+    pub fn is_value(self) -> bool {
+        matches!(self, SymbolKind::Const | SymbolKind::EnumValue)
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SymbolKind::Const => "const",
+            SymbolKind::Typedef => "typedef",
+            SymbolKind::Enum => "enum",
+            SymbolKind::EnumValue => "enum value",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Union => "union",
+            SymbolKind::Exception => "exception",
+            SymbolKind::Service => "service",
+        })
+    }
+}
+
+// Something defined in a file of a workspace that can be referred to by name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    // Path of the file, as the key of the file in the workspace.
+    pub path: PathBuf,
+    pub kind: SymbolKind,
+    // The name in the defining file, which is `Enum.VALUE` for an enum value.
+    pub name: String,
+}
+
+impl Symbol {
+    // The definition of the symbol, for an enum value it is the enum.
+    pub fn definition<'w>(&self, workspace: &'w Workspace) -> Option<&'w Definition> {
+        let name = match self.kind {
+            SymbolKind::EnumValue => self.name.split('.').next()?,
+            _ => self.name.as_str(),
+        };
+        workspace
+            .get(&self.path)?
+            .document
+            .definitions
+            .iter()
+            .find(|d| d.name().as_str() == name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} `{}` in {}",
+            self.kind,
+            self.name,
+            self.path.display()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    Unresolved,
+    // More than one symbol has the name.
+    Ambiguous(Vec<Symbol>),
+    // The only symbol with the name is of a kind not allowed there, e.g. a const used as a type.
+    Mismatched(Symbol),
+}
+
+// An error resolving a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    // A name in the type does not resolve to a type.
+    Resolve { name: String, error: ResolveError },
+    // Typedefs defined in terms of each other, the first one is repeated at the end.
+    Cycle(Vec<Symbol>),
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::Resolve { name, error } => f.write_str(&describe("type", name, error)),
+            TypeError::Cycle(cycle) => {
+                let names: Vec<_> = cycle.iter().map(|s| s.name.as_str()).collect();
+                write!(f, "typedef cycle: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+// A type with all the typedefs in it seen through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalType {
+    Bool,
+    Byte,
+    I8,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Binary,
+    Map(Box<CanonicalType>, Box<CanonicalType>),
+    Set(Box<CanonicalType>),
+    List(Box<CanonicalType>),
+    // A struct, union, exception or enum.
+    Named(Symbol),
+}
+
+// An error flattening the functions of a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    // The service to extend cannot be resolved.
+    Resolve {
+        name: String,
+        error: ResolveError,
+    },
+    // Services extending each other, the first one is repeated at the end.
+    Cycle(Vec<Symbol>),
+    // A function defined by `service` has the same name as one defined before by `other`,
+    // which may be the same service or one it extends.
+    Duplicate {
+        name: String,
+        service: Symbol,
+        other: Symbol,
+        span: Span,
+    },
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Resolve { name, error } => f.write_str(&describe("service", name, error)),
+            ServiceError::Cycle(cycle) => {
+                let names: Vec<_> = cycle.iter().map(|s| s.name.as_str()).collect();
+                write!(f, "service inheritance cycle: {}", names.join(" -> "))
+            }
+            ServiceError::Duplicate {
+                name,
+                service,
+                other,
+                ..
+            } if service == other => {
+                write!(
+                    f,
+                    "function `{}` is defined more than once in service {}",
+                    name, service.name
+                )
+            }
+            ServiceError::Duplicate {
+                name,
+                service,
+                other,
+                ..
+            } => write!(
+                f,
+                "function `{}` of service {} shadows the one of service {}",
+                name, service.name, other.name
+            ),
+        }
+    }
+}
+
+impl ServiceError {
+    // Whether the error is about `service` itself rather than a service it extends, so that an
+    // error is reported once no matter how many services extend the one with the error. A cycle
+    // is reported by its first member only.
+    fn reported_by(&self, program: &Program, service: &Service) -> bool {
+        let is = |s: &Symbol| s.path == program.path && s.name == service.name.as_str();
+        match self {
+            ServiceError::Resolve { .. } => false,
+            ServiceError::Cycle(cycle) => is(&cycle[0]) && first_in_cycle(cycle) == &cycle[0],
+            ServiceError::Duplicate { service, .. } => is(service),
+        }
+    }
+}
+
+// The member of a cycle which reports it, the first one by file and name.
+fn first_in_cycle(cycle: &[Symbol]) -> &Symbol {
+    cycle
+        .iter()
+        .min_by(|a, b| (&a.path, &a.name).cmp(&(&b.path, &b.name)))
+        .expect("cycles are not empty")
+}
+
+// Whether the typedef cycle `e`, found following `typedef`, is reported by it.
+fn reported_cycle(e: &TypeError, typedef: &Symbol) -> bool {
+    match e {
+        TypeError::Cycle(cycle) => &cycle[0] == typedef && first_in_cycle(cycle) == typedef,
+        TypeError::Resolve { .. } => false,
+    }
+}
+
+// A function of a service, which may be inherited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceFunction<'w> {
+    pub function: &'w Function,
+    // The service defining the function.
+    pub origin: Symbol,
+}
+
+// Binds names used in the files of a workspace to what they refer to.
+//
+// A name is looked up in the file it is used in. A name like `shared.User` is also looked up as
+// `User` in the file included as `shared`, includes of that file are not visible.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    // For every file, the kinds of the symbols defined with each name.
+    symbols: HashMap<PathBuf, HashMap<String, Vec<SymbolKind>>>,
+}
+
+impl Resolver {
+    pub fn new(workspace: &Workspace) -> Self {
+        let symbols = workspace
+            .programs()
+            .map(|program| {
+                let mut table: HashMap<String, Vec<SymbolKind>> = HashMap::new();
+                for definition in &program.document.definitions {
+                    let (kind, values) = match definition {
+                        Definition::Const(_) => (SymbolKind::Const, None),
+                        Definition::Typedef(_) => (SymbolKind::Typedef, None),
+                        Definition::Enum(x) => (SymbolKind::Enum, Some(&x.children)),
+                        Definition::Struct(_) => (SymbolKind::Struct, None),
+                        Definition::Union(_) => (SymbolKind::Union, None),
+                        Definition::Exception(_) => (SymbolKind::Exception, None),
+                        Definition::Service(_) => (SymbolKind::Service, None),
+                    };
+                    let name = definition.name().as_str();
+                    table.entry(name.into()).or_default().push(kind);
+                    for value in values.into_iter().flatten() {
+                        table
+                            .entry(format!("{}.{}", name, value.name.as_str()))
+                            .or_default()
+                            .push(SymbolKind::EnumValue);
+                    }
+                }
+                (program.path.clone(), table)
+            })
+            .collect();
+        Self { symbols }
+    }
+
+    pub fn resolve_type(&self, program: &Program, name: &str) -> Result<Symbol, ResolveError> {
+        self.resolve(program, name, SymbolKind::is_type)
+    }
+
+    // Resolve a name used as a constant value, which is a const or an enum value.
+    pub fn resolve_value(&self, program: &Program, name: &str) -> Result<Symbol, ResolveError> {
+        self.resolve(program, name, SymbolKind::is_value)
+    }
+
+    pub fn resolve_service(&self, program: &Program, name: &str) -> Result<Symbol, ResolveError> {
+        self.resolve(program, name, |kind| kind == SymbolKind::Service)
+    }
+
+    fn resolve(
+        &self,
+        program: &Program,
+        name: &str,
+        accept: impl Fn(SymbolKind) -> bool,
+    ) -> Result<Symbol, ResolveError> {
+        let mut found = self.lookup(&program.path, name);
+        if let Some((alias, rest)) = name.split_once('.') {
+            if let Some(path) = program.includes.get(alias) {
+                found.extend(self.lookup(path, rest));
+            }
+        }
+        let (mut accepted, others): (Vec<_>, Vec<_>) =
+            found.into_iter().partition(|symbol| accept(symbol.kind));
+        match accepted.len() {
+            1 => Ok(accepted.remove(0)),
+            0 => match others.into_iter().next() {
+                Some(symbol) => Err(ResolveError::Mismatched(symbol)),
+                None => Err(ResolveError::Unresolved),
+            },
+            _ => Err(ResolveError::Ambiguous(accepted)),
+        }
+    }
+
+    fn lookup(&self, path: &Path, name: &str) -> Vec<Symbol> {
+        self.symbols
+            .get(path)
+            .and_then(|table| table.get(name))
+            .into_iter()
+            .flatten()
+            .map(|&kind| Symbol {
+                path: path.to_path_buf(),
+                kind,
+                name: name.into(),
+            })
+            .collect()
+    }
+
+    // The type `type_` used in `program` is, following typedefs in any file.
+    pub fn canonical_type(
+        &self,
+        workspace: &Workspace,
+        program: &Program,
+        type_: &FieldType,
+    ) -> Result<CanonicalType, TypeError> {
+        self.canonical(workspace, program, type_, &mut Vec::new())
+    }
+
+    // `stack` holds the typedefs being followed.
+    fn canonical(
+        &self,
+        workspace: &Workspace,
+        program: &Program,
+        type_: &FieldType,
+        stack: &mut Vec<Symbol>,
+    ) -> Result<CanonicalType, TypeError> {
+        Ok(match type_ {
+            FieldType::Identifier(name) => {
+                let symbol =
+                    self.resolve_type(program, name)
+                        .map_err(|error| TypeError::Resolve {
+                            name: name.as_str().into(),
+                            error,
+                        })?;
+                if symbol.kind != SymbolKind::Typedef {
+                    return Ok(CanonicalType::Named(symbol));
+                }
+                if let Some(pos) = stack.iter().position(|s| *s == symbol) {
+                    let mut cycle = stack[pos..].to_vec();
+                    cycle.push(symbol);
+                    return Err(TypeError::Cycle(cycle));
+                }
+                let (typedef_program, old) =
+                    match (workspace.get(&symbol.path), symbol.definition(workspace)) {
+                        (Some(p), Some(Definition::Typedef(x))) => (p, &x.old),
+                        _ => {
+                            return Err(TypeError::Resolve {
+                                name: name.as_str().into(),
+                                error: ResolveError::Unresolved,
+                            })
+                        }
+                    };
+                stack.push(symbol);
+                let canonical = self.canonical(workspace, typedef_program, old, stack);
+                stack.pop();
+                return canonical;
+            }
+            FieldType::Bool => CanonicalType::Bool,
+            FieldType::Byte => CanonicalType::Byte,
+            FieldType::I8 => CanonicalType::I8,
+            FieldType::I16 => CanonicalType::I16,
+            FieldType::I32 => CanonicalType::I32,
+            FieldType::I64 => CanonicalType::I64,
+            FieldType::Double => CanonicalType::Double,
+            FieldType::String => CanonicalType::String,
+            FieldType::Binary => CanonicalType::Binary,
+            FieldType::Map(k, v, _) => CanonicalType::Map(
+                Box::new(self.canonical(workspace, program, k, stack)?),
+                Box::new(self.canonical(workspace, program, v, stack)?),
+            ),
+            FieldType::Set(x, _) => {
+                CanonicalType::Set(Box::new(self.canonical(workspace, program, x, stack)?))
+            }
+            FieldType::List(x, _) => {
+                CanonicalType::List(Box::new(self.canonical(workspace, program, x, stack)?))
+            }
+        })
+    }
+
+    // All the functions of `service` defined in `program`, including the ones of the services it
+    // extends directly or not. Inherited functions come first, the most basic service first.
+    #[allow(clippy::result_large_err)]
+    pub fn service_functions<'w>(
+        &self,
+        workspace: &'w Workspace,
+        program: &'w Program,
+        service: &'w Service,
+    ) -> Result<Vec<ServiceFunction<'w>>, ServiceError> {
+        let symbol = Symbol {
+            path: program.path.clone(),
+            kind: SymbolKind::Service,
+            name: service.name.as_str().into(),
+        };
+        let mut chain = vec![(program, service, symbol)];
+        while let Some(name) = &chain[chain.len() - 1].1.extension {
+            let program = chain[chain.len() - 1].0;
+            let symbol =
+                self.resolve_service(program, name)
+                    .map_err(|error| ServiceError::Resolve {
+                        name: name.as_str().into(),
+                        error,
+                    })?;
+            if let Some(pos) = chain.iter().position(|(_, _, s)| *s == symbol) {
+                let mut cycle: Vec<_> = chain.drain(pos..).map(|(_, _, s)| s).collect();
+                cycle.push(symbol);
+                return Err(ServiceError::Cycle(cycle));
+            }
+            match (workspace.get(&symbol.path), symbol.definition(workspace)) {
+                (Some(p), Some(Definition::Service(s))) => chain.push((p, s, symbol)),
+                _ => {
+                    return Err(ServiceError::Resolve {
+                        name: name.as_str().into(),
+                        error: ResolveError::Unresolved,
+                    })
+                }
+            }
+        }
+
+        let mut functions: Vec<ServiceFunction> = Vec::new();
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        for (_, service, symbol) in chain.into_iter().rev() {
+            for function in &service.functions {
+                let name = function.name.as_str();
+                if let Some(&i) = names.get(name) {
+                    return Err(ServiceError::Duplicate {
+                        name: name.into(),
+                        service: symbol,
+                        other: functions[i].origin.clone(),
+                        span: function.span,
+                    });
+                }
+                names.insert(name, functions.len());
+                functions.push(ServiceFunction {
+                    function,
+                    origin: symbol.clone(),
+                });
+            }
+        }
+        Ok(functions)
+    }
+
+    // Check every name used in the workspace refers to exactly one thing of the right kind,
+    // no typedef is defined in terms of itself, and services extend others properly.
+    pub fn check(&self, workspace: &Workspace) -> Result<(), Vec<Diagnostic>> {
+        let mut checker = Checker {
+            workspace,
+            resolver: self,
+            diagnostics: Vec::new(),
+        };
+        workspace.programs().for_each(|p| checker.check_program(p));
+        match checker.diagnostics.is_empty() {
+            true => Ok(()),
+            false => Err(checker.diagnostics),
+        }
+    }
+}
+
+struct Checker<'a> {
+    workspace: &'a Workspace,
+    resolver: &'a Resolver,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn check_program(&mut self, program: &'a Program) {
+        for definition in &program.document.definitions {
+            match definition {
+                Definition::Const(x) => {
+                    self.check_type(program, &x.type_, x.span);
+                    self.check_value(program, &x.value, x.span);
+                }
+                Definition::Typedef(x) => {
+                    self.check_type(program, &x.old, x.span);
+                    // Following the typedef from itself, a cycle it is in starts with it.
+                    let symbol = Symbol {
+                        path: program.path.clone(),
+                        kind: SymbolKind::Typedef,
+                        name: x.alias.as_str().into(),
+                    };
+                    let mut stack = vec![symbol.clone()];
+                    let result =
+                        self.resolver
+                            .canonical(self.workspace, program, &x.old, &mut stack);
+                    match result {
+                        Err(e @ TypeError::Cycle(_)) if reported_cycle(&e, &symbol) => {
+                            self.diagnostics.push(
+                                Diagnostic::error(x.span, e.to_string()).with_path(&program.path),
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+                Definition::Enum(_) => {}
+                Definition::Struct(x) => self.check_fields(program, &x.fields),
+                Definition::Union(x) => self.check_fields(program, &x.fields),
+                Definition::Exception(x) => self.check_fields(program, &x.fields),
+                Definition::Service(x) => {
+                    if let Some(extension) = &x.extension {
+                        let result = self.resolver.resolve_service(program, extension);
+                        self.report(program, "service", extension, x.span, result);
+                    }
+                    let result = self.resolver.service_functions(self.workspace, program, x);
+                    match result {
+                        Err(e @ ServiceError::Cycle(_))
+                        | Err(e @ ServiceError::Duplicate { .. })
+                            if e.reported_by(program, x) =>
+                        {
+                            let span = match e {
+                                ServiceError::Duplicate { span, .. } => span,
+                                _ => x.span,
+                            };
+                            self.diagnostics.push(
+                                Diagnostic::error(span, e.to_string()).with_path(&program.path),
+                            );
+                        }
+                        _ => {}
+                    }
+                    for function in &x.functions {
+                        if let Some(returns) = &function.returns {
+                            self.check_type(program, returns, function.span);
+                        }
+                        self.check_fields(program, &function.parameters);
+                        self.check_fields(program, function.exceptions.iter().flatten());
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_fields<'f>(&mut self, program: &Program, fields: impl IntoIterator<Item = &'f Field>) {
+        for field in fields {
+            self.check_type(program, &field.type_, field.span);
+            if let Some(default) = &field.default {
+                self.check_value(program, default, field.span);
+            }
+        }
+    }
+
+    fn check_type(&mut self, program: &Program, type_: &FieldType, span: Span) {
+        match type_ {
+            FieldType::Identifier(name) => {
+                let result = self.resolver.resolve_type(program, name);
+                self.report(program, "type", name, span, result);
+            }
+            FieldType::Map(k, v, _) => {
+                self.check_type(program, k, span);
+                self.check_type(program, v, span);
+            }
+            FieldType::Set(x, _) | FieldType::List(x, _) => self.check_type(program, x, span),
+            _ => {}
+        }
+    }
+
+    fn check_value(&mut self, program: &Program, value: &ConstValue, span: Span) {
+        match value {
+            ConstValue::Identifier(name) => {
+                let result = self.resolver.resolve_value(program, name);
+                self.report(program, "constant", name, span, result);
+            }
+            ConstValue::List(list) => list.iter().for_each(|x| self.check_value(program, x, span)),
+            ConstValue::Map(map) => map.iter().for_each(|(k, v)| {
+                self.check_value(program, k, span);
+                self.check_value(program, v, span);
+            }),
+            _ => {}
+        }
+    }
+
+    fn report(
+        &mut self,
+        program: &Program,
+        expected: &str,
+        name: &str,
+        span: Span,
+        result: Result<Symbol, ResolveError>,
+    ) {
+        let message = match result {
+            Ok(_) => return,
+            Err(e) => describe(expected, name, &e),
+        };
+        self.diagnostics
+            .push(Diagnostic::error(span, message).with_path(&program.path));
+    }
+}
+
+fn describe(expected: &str, name: &str, e: &ResolveError) -> String {
+    match e {
+        ResolveError::Unresolved => format!("cannot find {} `{}`", expected, name),
+        ResolveError::Mismatched(symbol) => format!("expected {}, found {}", expected, symbol),
+        ResolveError::Ambiguous(symbols) => {
+            let candidates: Vec<_> = symbols.iter().map(ToString::to_string).collect();
+            format!(
+                "{} `{}` is ambiguous, it can be {}",
+                expected,
+                name,
+                candidates.join(" or ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::basic::Identifier;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "thrift", "workspace", name]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let source = r#"
+include "shared.thrift"
+
+enum Status {
+    OK = 0
+}
+const Status DEFAULT_STATUS = Status.OK
+const list<i32> ID = [1]
+typedef shared.Shared Alias
+service Base {}
+"#;
+        let workspace =
+            Workspace::from_source(fixture("inline.thrift"), source, &[fixture("lib")]).unwrap();
+        let resolver = Resolver::new(&workspace);
+        let root = workspace.root();
+
+        let symbol = resolver.resolve_type(root, "shared.Shared").unwrap();
+        assert_eq!(symbol.kind, SymbolKind::Struct);
+        assert_eq!(symbol.name, "Shared");
+        assert_eq!(
+            symbol.path,
+            fixture("shared.thrift").canonicalize().unwrap()
+        );
+        assert!(matches!(
+            symbol.definition(&workspace),
+            Some(Definition::Struct(_))
+        ));
+
+        let symbol = resolver.resolve_value(root, "Status.OK").unwrap();
+        assert_eq!(symbol.kind, SymbolKind::EnumValue);
+        assert!(matches!(
+            symbol.definition(&workspace),
+            Some(Definition::Enum(_))
+        ));
+        assert_eq!(
+            resolver.resolve_type(root, "Alias").unwrap().kind,
+            SymbolKind::Typedef
+        );
+        assert_eq!(resolver.resolve_service(root, "Base").unwrap().name, "Base");
+
+        // Includes of included files are not visible.
+        assert_eq!(
+            resolver.resolve_type(root, "base.Id"),
+            Err(ResolveError::Unresolved)
+        );
+        assert!(matches!(
+            resolver.resolve_type(root, "ID"),
+            Err(ResolveError::Mismatched(Symbol {
+                kind: SymbolKind::Const,
+                ..
+            }))
+        ));
+        assert!(resolver.check(&workspace).is_ok());
+
+        let workspace = Workspace::load(fixture("main.thrift"), &[fixture("lib")]).unwrap();
+        assert!(Resolver::new(&workspace).check(&workspace).is_ok());
+    }
+
+    #[test]
+    fn test_check() {
+        let source = r#"
+const i32 A = 1
+struct A {}
+enum Kind { A }
+
+struct User {
+    1: Missing missing
+    2: map<string, list<A>> ambiguous
+    3: i32 kind = Kind.B
+}
+
+service UserService extends User {
+    A Get(1: Kind kind = A)
+}
+"#;
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let diagnostics = Resolver::new(&workspace).check(&workspace).unwrap_err();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "cannot find type `Missing`",
+                "cannot find constant `Kind.B`",
+                "expected service, found struct `User` in inline.thrift",
+            ]
+        );
+        assert_eq!(
+            (diagnostics[0].span.line, diagnostics[0].span.column),
+            (7, 5)
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: cannot find type `Missing`\n --> inline.thrift:7:5"
+        );
+
+        let source = "struct A {}\nenum A { X }\nstruct B { 1: A a }\n";
+        let workspace = Workspace::from_source("inline.thrift", source, &[]).unwrap();
+        let diagnostics = Resolver::new(&workspace).check(&workspace).unwrap_err();
+        assert_eq!(
+            diagnostics[0].message,
+            "type `A` is ambiguous, it can be struct `A` in inline.thrift or enum `A` in inline.thrift"
+        );
+    }
+
+    #[test]
+    fn test_canonical_type() {
+        let source = r#"
+include "shared.thrift"
+
+typedef shared.Shared SharedUser
+typedef SharedUser Admin
+typedef map<string, list<Admin>> Admins
+typedef A B
+typedef B A
+typedef list<B> Bs
+struct Group {
+    1: Admins admins
+    2: A broken
+}
+"#;
+        let workspace =
+            Workspace::from_source(fixture("inline.thrift"), source, &[fixture("lib")]).unwrap();
+        let resolver = Resolver::new(&workspace);
+        let root = workspace.root();
+        let canonical = |name: &str| {
+            let type_ = FieldType::Identifier(Identifier::from(name.to_string()));
+            resolver.canonical_type(&workspace, root, &type_)
+        };
+
+        let shared = Symbol {
+            path: fixture("shared.thrift").canonicalize().unwrap(),
+            kind: SymbolKind::Struct,
+            name: "Shared".into(),
+        };
+        assert_eq!(canonical("Admin"), Ok(CanonicalType::Named(shared.clone())));
+        assert_eq!(
+            canonical("Admins"),
+            Ok(CanonicalType::Map(
+                Box::new(CanonicalType::String),
+                Box::new(CanonicalType::List(Box::new(CanonicalType::Named(shared)))),
+            ))
+        );
+        // The typedef in shared.thrift refers to a type in its own include.
+        let shared = workspace.included(root, "shared").unwrap();
+        let id = &shared.document.structs().next().unwrap().fields[0].type_;
+        assert_eq!(
+            resolver.canonical_type(&workspace, shared, id),
+            Ok(CanonicalType::I64)
+        );
+
+        match canonical("A") {
+            Err(TypeError::Cycle(cycle)) => {
+                let names: Vec<_> = cycle.iter().map(|s| s.name.as_str()).collect();
+                assert_eq!(names, vec!["A", "B", "A"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            canonical("Missing"),
+            Err(TypeError::Resolve {
+                error: ResolveError::Unresolved,
+                ..
+            })
+        ));
+
+        let messages: Vec<_> = resolver
+            .check(&workspace)
+            .unwrap_err()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(messages, vec!["typedef cycle: A -> B -> A"]);
+    }
+
+    #[test]
+    fn test_service_functions() {
+        let source = r#"
+include "shared.thrift"
+
+service Base extends shared.SharedService {
+    i32 get()
+}
+service Derived extends Base {
+    void put()
+}
+service Shadow extends Derived {
+    void ping()
+}
+service Twice {
+    void a()
+    void a()
+}
+service D extends C {}
+service C extends D {}
+service E extends D {}
+"#;
+        let workspace =
+            Workspace::from_source(fixture("inline.thrift"), source, &[fixture("lib")]).unwrap();
+        let resolver = Resolver::new(&workspace);
+        let root = workspace.root();
+        let service = |name: &str| root.document.services().find(|s| s.name.as_str() == name);
+
+        let functions = resolver
+            .service_functions(&workspace, root, service("Derived").unwrap())
+            .unwrap();
+        let names: Vec<_> = functions
+            .iter()
+            .map(|f| (f.origin.name.as_str(), f.function.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("SharedService", "ping"),
+                ("Base", "get"),
+                ("Derived", "put")
+            ]
+        );
+        assert_eq!(
+            functions[0].origin.path,
+            fixture("shared.thrift").canonicalize().unwrap()
+        );
+
+        let err = resolver
+            .service_functions(&workspace, root, service("Shadow").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "function `ping` of service Shadow shadows the one of service SharedService"
+        );
+        let err = resolver
+            .service_functions(&workspace, root, service("C").unwrap())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "service inheritance cycle: C -> D -> C");
+
+        let diagnostics = resolver.check(&workspace).unwrap_err();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "function `ping` of service Shadow shadows the one of service SharedService",
+                "function `a` is defined more than once in service Twice",
+                // Once, by its first member.
+                "service inheritance cycle: C -> D -> C",
+            ]
+        );
+        assert_eq!(diagnostics[0].span.line, 11);
+    }
+}
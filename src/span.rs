@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::IResult;
+
+// Where a node comes from in the parsed source.
+// `start` and `end` are byte offsets, `line` and `column` (both start from 1) point at `start`.
+// Definitions, enum values, fields and functions carry spans. Field types, headers and
+// annotations do not, errors about them point at the node containing them.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    // Distance between `start` and the end of the source. Every input seen while parsing is a
+    // suffix of the source, so this does not change no matter which input we locate against.
+    // It is not serialized, so deserialized spans cannot be relocated, only their public fields
+    // are kept.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    from_end: usize,
+}
+
+impl Span {
+    // A span of `start..end`, starting at `line` and `column`.
+    pub fn at(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+            from_end: 0,
+        }
+    }
+
+    fn new(input: &str, remains: &str) -> Self {
+        Self {
+            start: 0,
+            end: input.len() - remains.len(),
+            line: 1,
+            column: 1,
+            from_end: input.len(),
+        }
+    }
+
+    // Recompute offsets, line and column against the input `index` is built from.
+    fn relocate(&mut self, index: &LineIndex) {
+        let len = self.end - self.start;
+        self.start = index.input_len - self.from_end;
+        self.end = self.start + len;
+        let (line, column) = index.line_column(self.start);
+        self.line = line;
+        self.column = column;
+    }
+}
+
+// `from_end` only matters while parsing, and is lost when spans are deserialized.
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        (self.start, self.end, self.line, self.column)
+            == (other.start, other.end, other.line, other.column)
+    }
+}
+
+impl Eq for Span {}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Span({}..{} at {}:{})",
+            self.start, self.end, self.line, self.column
+        )
+    }
+}
+
+// Line starts of the consumed part of an input.
+pub(crate) struct LineIndex<'a> {
+    input_len: usize,
+    consumed: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(input: &'a str, remains: &'a str) -> Self {
+        let consumed = &input[..input.len() - remains.len()];
+        let line_starts = std::iter::once(0)
+            .chain(consumed.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            input_len: input.len(),
+            consumed,
+            line_starts,
+        }
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.consumed[line_start..offset].chars().count() + 1;
+        (line, column)
+    }
+}
+
+// Nodes that carry a span.
+pub(crate) trait Located {
+    fn span_mut(&mut self) -> &mut Span;
+
+    // Relocate the spans of child nodes.
+    fn relocate_children(&mut self, _index: &LineIndex) {}
+
+    fn relocate(&mut self, index: &LineIndex) {
+        self.span_mut().relocate(index);
+        self.relocate_children(index);
+    }
+}
+
+// Record the span of what `parser` consumed, and make all the spans inside relative to `input`.
+pub(crate) fn located<'a, O, F>(mut parser: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+where
+    O: Located,
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    move |input: &'a str| {
+        let (remains, mut node) = parser(input)?;
+        *node.span_mut() = Span::new(input, remains);
+        node.relocate(&LineIndex::new(input, remains));
+        Ok((remains, node))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line_index() {
+        let input = "ab\nc\n\u{4f60}d";
+        let index = LineIndex::new(input, "");
+        assert_eq!(index.line_column(0), (1, 1));
+        assert_eq!(index.line_column(1), (1, 2));
+        assert_eq!(index.line_column(3), (2, 1));
+        assert_eq!(index.line_column(5), (3, 1));
+        assert_eq!(index.line_column(8), (3, 2));
+    }
+}
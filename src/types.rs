@@ -1,21 +1,22 @@
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::char as cchar;
-use nom::combinator::{map, opt};
+use nom::character::complete::{char as cchar, satisfy};
+use nom::combinator::{map, not, opt};
 use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
-use nom::IResult;
 
+use crate::annotation::{Annotations, AnnotationsRef};
 use crate::basic::{Identifier, IdentifierRef, Literal, LiteralRef, Separator};
-use crate::Parser;
+use crate::{IResult, Parser};
 
 // FieldType       ::=  Identifier | BaseType | ContainerType
 // BaseType        ::=  'bool' | 'byte' | 'i8' | 'i16' | 'i32' | 'i64' | 'double' | 'string' | 'binary'
 // ContainerType   ::=  MapType | SetType | ListType
-// MapType         ::=  'map' CppType? '<' FieldType ',' FieldType '>'
-// SetType         ::=  'set' CppType? '<' FieldType '>'
-// ListType        ::=  'list' '<' FieldType '>' CppType?
+// MapType         ::=  'map' CppType? '<' FieldType ',' FieldType '>' TypeAnnotations?
+// SetType         ::=  'set' CppType? '<' FieldType '>' TypeAnnotations?
+// ListType        ::=  'list' '<' FieldType '>' CppType? TypeAnnotations?
 // CppType         ::=  'cpp_type' Literal
 // Note: CppType is not fully supported in out impl.
+// Types have no span, the field or definition using them has.
 #[derive(Debug, Clone, PartialEq)]
 pub enum FieldTypeRef<'a> {
     Identifier(IdentifierRef<'a>),
@@ -28,24 +29,34 @@ pub enum FieldTypeRef<'a> {
     Double,
     String,
     Binary,
-    Map(Box<FieldTypeRef<'a>>, Box<FieldTypeRef<'a>>),
-    Set(Box<FieldTypeRef<'a>>),
-    List(Box<FieldTypeRef<'a>>),
+    Map(
+        Box<FieldTypeRef<'a>>,
+        Box<FieldTypeRef<'a>>,
+        Option<AnnotationsRef<'a>>,
+    ),
+    Set(Box<FieldTypeRef<'a>>, Option<AnnotationsRef<'a>>),
+    List(Box<FieldTypeRef<'a>>, Option<AnnotationsRef<'a>>),
 }
 
 impl<'a> FieldTypeRef<'a> {
     pub fn parse_base_type(input: &'a str) -> IResult<&'a str, Self> {
-        alt((
-            map(tag("bool"), |_| Self::Bool),
-            map(tag("byte"), |_| Self::Byte),
-            map(tag("i8"), |_| Self::I8),
-            map(tag("i16"), |_| Self::I16),
-            map(tag("i32"), |_| Self::I32),
-            map(tag("i64"), |_| Self::I64),
-            map(tag("double"), |_| Self::Double),
-            map(tag("string"), |_| Self::String),
-            map(tag("binary"), |_| Self::Binary),
-        ))(input)
+        terminated(
+            alt((
+                map(tag("bool"), |_| Self::Bool),
+                map(tag("byte"), |_| Self::Byte),
+                map(tag("i8"), |_| Self::I8),
+                map(tag("i16"), |_| Self::I16),
+                map(tag("i32"), |_| Self::I32),
+                map(tag("i64"), |_| Self::I64),
+                map(tag("double"), |_| Self::Double),
+                map(tag("string"), |_| Self::String),
+                map(tag("binary"), |_| Self::Binary),
+            )),
+            // So that identifiers like `stringList` are not taken as base types.
+            not(satisfy(|c: char| {
+                c.is_ascii_alphanumeric() || c == '.' || c == '_'
+            })),
+        )(input)
     }
 
     pub fn parse_container_type(input: &'a str) -> IResult<&'a str, Self> {
@@ -57,17 +68,20 @@ impl<'a> FieldTypeRef<'a> {
                         opt(Separator::parse),
                         opt(terminated(CppTypeRef::parse, opt(Separator::parse))),
                     )),
-                    delimited(
-                        pair(cchar('<'), opt(Separator::parse)),
-                        separated_pair(
-                            FieldTypeRef::parse,
-                            tuple((opt(Separator::parse), cchar(','), opt(Separator::parse))),
-                            FieldTypeRef::parse,
+                    pair(
+                        delimited(
+                            pair(cchar('<'), opt(Separator::parse)),
+                            separated_pair(
+                                FieldTypeRef::parse,
+                                tuple((opt(Separator::parse), cchar(','), opt(Separator::parse))),
+                                FieldTypeRef::parse,
+                            ),
+                            pair(opt(Separator::parse), cchar('>')),
                         ),
-                        pair(opt(Separator::parse), cchar('>')),
+                        Self::parse_annotations,
                     ),
                 ),
-                |(k, v)| Self::Map(Box::new(k), Box::new(v)),
+                |((k, v), annotations)| Self::Map(Box::new(k), Box::new(v), annotations),
             ),
             map(
                 preceded(
@@ -76,33 +90,44 @@ impl<'a> FieldTypeRef<'a> {
                         opt(Separator::parse),
                         opt(terminated(CppTypeRef::parse, opt(Separator::parse))),
                     )),
-                    delimited(
-                        pair(cchar('<'), opt(Separator::parse)),
-                        FieldTypeRef::parse,
-                        pair(opt(Separator::parse), cchar('>')),
+                    pair(
+                        delimited(
+                            pair(cchar('<'), opt(Separator::parse)),
+                            FieldTypeRef::parse,
+                            pair(opt(Separator::parse), cchar('>')),
+                        ),
+                        Self::parse_annotations,
                     ),
                 ),
-                |v| Self::Set(Box::new(v)),
+                |(v, annotations)| Self::Set(Box::new(v), annotations),
             ),
             map(
-                delimited(
+                preceded(
                     pair(tag("list"), opt(Separator::parse)),
-                    delimited(
-                        pair(cchar('<'), opt(Separator::parse)),
-                        FieldTypeRef::parse,
-                        pair(opt(Separator::parse), cchar('>')),
+                    pair(
+                        terminated(
+                            delimited(
+                                pair(cchar('<'), opt(Separator::parse)),
+                                FieldTypeRef::parse,
+                                pair(opt(Separator::parse), cchar('>')),
+                            ),
+                            opt(pair(opt(Separator::parse), CppTypeRef::parse)),
+                        ),
+                        Self::parse_annotations,
                     ),
-                    opt(pair(opt(Separator::parse), CppTypeRef::parse)),
                 ),
-                |v| Self::List(Box::new(v)),
+                |(v, annotations)| Self::List(Box::new(v), annotations),
             ),
-            map(IdentifierRef::parse, Self::Identifier),
         ))(input)
     }
 
     pub fn parse_identifier_type(input: &'a str) -> IResult<&'a str, Self> {
         map(IdentifierRef::parse, Self::Identifier)(input)
     }
+
+    fn parse_annotations(input: &'a str) -> IResult<&'a str, Option<AnnotationsRef<'a>>> {
+        opt(preceded(opt(Separator::parse), AnnotationsRef::parse))(input)
+    }
 }
 
 impl<'a> Parser<'a> for FieldTypeRef<'a> {
@@ -116,6 +141,8 @@ impl<'a> Parser<'a> for FieldTypeRef<'a> {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FieldType {
     Identifier(Identifier),
     Bool,
@@ -127,9 +154,9 @@ pub enum FieldType {
     Double,
     String,
     Binary,
-    Map(Box<FieldType>, Box<FieldType>),
-    Set(Box<FieldType>),
-    List(Box<FieldType>),
+    Map(Box<FieldType>, Box<FieldType>, Option<Annotations>),
+    Set(Box<FieldType>, Option<Annotations>),
+    List(Box<FieldType>, Option<Annotations>),
 }
 
 impl<'a> From<FieldTypeRef<'a>> for FieldType {
@@ -145,11 +172,17 @@ impl<'a> From<FieldTypeRef<'a>> for FieldType {
             FieldTypeRef::Double => FieldType::Double,
             FieldTypeRef::String => FieldType::String,
             FieldTypeRef::Binary => FieldType::Binary,
-            FieldTypeRef::Map(k, v) => {
-                FieldType::Map(Box::new(k.as_ref().into()), Box::new(v.as_ref().into()))
+            FieldTypeRef::Map(k, v, a) => FieldType::Map(
+                Box::new(k.as_ref().into()),
+                Box::new(v.as_ref().into()),
+                a.map(Into::into),
+            ),
+            FieldTypeRef::Set(v, a) => {
+                FieldType::Set(Box::new(v.as_ref().into()), a.map(Into::into))
+            }
+            FieldTypeRef::List(v, a) => {
+                FieldType::List(Box::new(v.as_ref().into()), a.map(Into::into))
             }
-            FieldTypeRef::Set(v) => FieldType::Set(Box::new(v.as_ref().into())),
-            FieldTypeRef::List(v) => FieldType::List(Box::new(v.as_ref().into())),
         }
     }
 }
@@ -167,11 +200,17 @@ impl<'a> From<&FieldTypeRef<'a>> for FieldType {
             FieldTypeRef::Double => FieldType::Double,
             FieldTypeRef::String => FieldType::String,
             FieldTypeRef::Binary => FieldType::Binary,
-            FieldTypeRef::Map(k, v) => {
-                FieldType::Map(Box::new(k.as_ref().into()), Box::new(v.as_ref().into()))
+            FieldTypeRef::Map(k, v, a) => FieldType::Map(
+                Box::new(k.as_ref().into()),
+                Box::new(v.as_ref().into()),
+                a.clone().map(Into::into),
+            ),
+            FieldTypeRef::Set(v, a) => {
+                FieldType::Set(Box::new(v.as_ref().into()), a.clone().map(Into::into))
+            }
+            FieldTypeRef::List(v, a) => {
+                FieldType::List(Box::new(v.as_ref().into()), a.clone().map(Into::into))
             }
-            FieldTypeRef::Set(v) => FieldType::Set(Box::new(v.as_ref().into())),
-            FieldTypeRef::List(v) => FieldType::List(Box::new(v.as_ref().into())),
         }
     }
 }
@@ -198,6 +237,7 @@ impl<'a> Parser<'a> for CppTypeRef<'a> {
     }
 }
 #[derive(derive_newtype::NewType, Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CppType(Literal);
 
 impl<'a> From<CppTypeRef<'a>> for CppType {
@@ -214,6 +254,7 @@ impl<'a> Parser<'a> for CppType {
 
 #[cfg(test)]
 mod test {
+    use crate::annotation::AnnotationRef;
     use crate::utils::*;
 
     use super::*;
@@ -238,27 +279,51 @@ mod test {
         );
         assert_eq!(
             FieldTypeRef::parse("map <bool, bool>").unwrap().1,
-            FieldTypeRef::Map(Box::new(FieldTypeRef::Bool), Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::Map(
+                Box::new(FieldTypeRef::Bool),
+                Box::new(FieldTypeRef::Bool),
+                None
+            )
         );
         assert_eq!(
             FieldTypeRef::parse("map<bool,bool>").unwrap().1,
-            FieldTypeRef::Map(Box::new(FieldTypeRef::Bool), Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::Map(
+                Box::new(FieldTypeRef::Bool),
+                Box::new(FieldTypeRef::Bool),
+                None
+            )
         );
         assert_eq!(
             FieldTypeRef::parse("set <bool>").unwrap().1,
-            FieldTypeRef::Set(Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::Set(Box::new(FieldTypeRef::Bool), None)
         );
         assert_eq!(
             FieldTypeRef::parse("set<bool>").unwrap().1,
-            FieldTypeRef::Set(Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::Set(Box::new(FieldTypeRef::Bool), None)
         );
         assert_eq!(
             FieldTypeRef::parse("list <bool>").unwrap().1,
-            FieldTypeRef::List(Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::List(Box::new(FieldTypeRef::Bool), None)
         );
         assert_eq!(
             FieldTypeRef::parse("list<bool>").unwrap().1,
-            FieldTypeRef::List(Box::new(FieldTypeRef::Bool))
+            FieldTypeRef::List(Box::new(FieldTypeRef::Bool), None)
+        );
+        assert_eq!(
+            FieldTypeRef::parse("list<bool> cpp_type 'std::vector<bool>' (cpp.ref = 'true')")
+                .unwrap()
+                .1,
+            FieldTypeRef::List(
+                Box::new(FieldTypeRef::Bool),
+                Some(AnnotationsRef::from(vec![AnnotationRef {
+                    key: IdentifierRef::from("cpp.ref"),
+                    value: Some(LiteralRef::from("true")),
+                }]))
+            )
+        );
+        assert_eq!(
+            FieldTypeRef::parse("stringList").unwrap().1,
+            FieldTypeRef::Identifier(IdentifierRef::from("stringList"))
         );
         assert_eq!(
             FieldTypeRef::parse("ihc_iah").unwrap().1,
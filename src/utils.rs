@@ -1,5 +1,5 @@
 #[cfg(test)]
-use nom::IResult;
+use crate::IResult;
 
 #[cfg(test)]
 #[allow(unused)]
@@ -21,7 +21,7 @@ where
 {
     input
         .into_iter()
-        .zip(expected.into_iter())
+        .zip(expected)
         .for_each(|(i, e)| assert_pair_eq(i, e))
 }
 
@@ -60,7 +60,7 @@ pub fn assert_list_eq_with_f<'a, T, IS, ES, ISI, ESI, IF, EF>(
 {
     input
         .into_iter()
-        .zip(expected.into_iter())
+        .zip(expected)
         .for_each(|(i, e)| assert_pair_eq(input_f(i), expected_f(e)))
 }
 
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::definition::{Definition, Enum};
+use crate::document::Document;
+use crate::field::Field;
+use crate::Diagnostic;
+
+// Field ids are i16 on the wire.
+const MAX_FIELD_ID: i64 = i16::MAX as i64;
+
+// Check a document for mistakes the parser lets through. Warnings are included, use
+// `Diagnostic::is_error` to tell them from errors.
+pub fn validate(document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for definition in &document.definitions {
+        match definition {
+            Definition::Enum(x) => validate_enum(x, &mut diagnostics),
+            Definition::Struct(x) => {
+                validate_fields(&x.fields, &format!("struct {}", *x.name), &mut diagnostics)
+            }
+            Definition::Union(x) => {
+                validate_fields(&x.fields, &format!("union {}", *x.name), &mut diagnostics)
+            }
+            Definition::Exception(x) => validate_fields(
+                &x.fields,
+                &format!("exception {}", *x.name),
+                &mut diagnostics,
+            ),
+            Definition::Service(x) => {
+                for function in &x.functions {
+                    let owner = format!("{}.{}", *x.name, *function.name);
+                    validate_fields(
+                        &function.parameters,
+                        &format!("parameters of {}", owner),
+                        &mut diagnostics,
+                    );
+                    if let Some(exceptions) = &function.exceptions {
+                        validate_fields(
+                            exceptions,
+                            &format!("exceptions of {}", owner),
+                            &mut diagnostics,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+// The id of each field. Like the Apache compiler does, fields without id are numbered -1, -2 and
+// so on in order.
+pub fn field_ids(fields: &[Field]) -> Vec<i64> {
+    let mut implicit = 0;
+    fields
+        .iter()
+        .map(|field| match field.id {
+            Some(id) => *id,
+            None => {
+                implicit -= 1;
+                implicit
+            }
+        })
+        .collect()
+}
+
+fn validate_fields(fields: &[Field], owner: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let mut ids = HashMap::new();
+    let mut names = HashMap::new();
+    for (field, id) in fields.iter().zip(field_ids(fields)) {
+        let name = field.name.as_str();
+        match field.id {
+            None => diagnostics.push(Diagnostic::warning(
+                field.span,
+                format!(
+                    "field `{}` in {} has no id, {} is assigned",
+                    name, owner, id
+                ),
+            )),
+            Some(_) if id < 1 => diagnostics.push(Diagnostic::error(
+                field.span,
+                format!("field id {} of `{}` in {} is not positive", id, name, owner),
+            )),
+            Some(_) if id > MAX_FIELD_ID => diagnostics.push(Diagnostic::error(
+                field.span,
+                format!(
+                    "field id {} of `{}` in {} is larger than {}",
+                    id, name, owner, MAX_FIELD_ID
+                ),
+            )),
+            Some(_) => {}
+        }
+        if let Some(other) = ids.insert(id, name) {
+            diagnostics.push(Diagnostic::error(
+                field.span,
+                format!(
+                    "field id {} of `{}` in {} is already used by `{}`",
+                    id, name, owner, other
+                ),
+            ));
+        }
+        if names.insert(name, id).is_some() {
+            diagnostics.push(Diagnostic::error(
+                field.span,
+                format!("field `{}` is defined more than once in {}", name, owner),
+            ));
+        }
+    }
+}
+
+fn validate_enum(enum_: &Enum, diagnostics: &mut Vec<Diagnostic>) {
+    let mut values = HashMap::new();
+    let mut names = HashMap::new();
+    for (child, value) in enum_.numbered() {
+        let name = child.name.as_str();
+        if i32::try_from(value).is_err() {
+            diagnostics.push(Diagnostic::error(
+                child.span,
+                format!(
+                    "value {} of `{}` in enum {} is out of i32 range",
+                    value, name, *enum_.name
+                ),
+            ));
+        }
+        if let Some(other) = values.insert(value, name) {
+            diagnostics.push(Diagnostic::error(
+                child.span,
+                format!(
+                    "value {} of `{}` in enum {} is already used by `{}`",
+                    value, name, *enum_.name, other
+                ),
+            ));
+        }
+        if names.insert(name, value).is_some() {
+            diagnostics.push(Diagnostic::error(
+                child.span,
+                format!(
+                    "`{}` is defined more than once in enum {}",
+                    name, *enum_.name
+                ),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Severity;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_fields() {
+        let document = Document::parse_complete(
+            r#"
+struct User {
+    1: i64 id
+    2: string name
+    2: string nick
+    3: string name
+    -1: i32 negative
+    40000: i32 huge
+    bool implicit
+    bool another
+}
+
+service UserService {
+    void Update(1: User user, 1: User old) throws (1: Error e, Error f)
+}
+"#,
+        )
+        .unwrap();
+        let diagnostics = validate(&document);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "field id 2 of `nick` in struct User is already used by `name`",
+                "field `name` is defined more than once in struct User",
+                "field id -1 of `negative` in struct User is not positive",
+                "field id 40000 of `huge` in struct User is larger than 32767",
+                "field `implicit` in struct User has no id, -1 is assigned",
+                "field id -1 of `implicit` in struct User is already used by `negative`",
+                "field `another` in struct User has no id, -2 is assigned",
+                "field id 1 of `old` in parameters of UserService.Update is already used by `user`",
+                "field `f` in exceptions of UserService.Update has no id, -1 is assigned",
+            ]
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(!diagnostics[4].is_error());
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: field id 2 of `nick` in struct User is already used by `name`\n --> 5:5"
+        );
+
+        let document = Document::parse_complete("union U { 1: i32 a, 2: string b }").unwrap();
+        assert!(validate(&document).is_empty());
+    }
+
+    #[test]
+    fn test_validate_enum() {
+        let document = Document::parse_complete(
+            r#"
+enum Status {
+    A
+    B = 5
+    C
+    D = 6
+    A = 10
+    E = 2147483647
+    F
+    G = -2147483649
+}
+
+enum Big {
+    A = 9223372036854775807
+    B
+}
+"#,
+        )
+        .unwrap();
+        let messages: Vec<_> = validate(&document).into_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "value 6 of `D` in enum Status is already used by `C`",
+                "`A` is defined more than once in enum Status",
+                "value 2147483648 of `F` in enum Status is out of i32 range",
+                "value -2147483649 of `G` in enum Status is out of i32 range",
+                "value 9223372036854775807 of `A` in enum Big is out of i32 range",
+                "value 9223372036854775808 of `B` in enum Big is out of i32 range",
+            ]
+        );
+    }
+}
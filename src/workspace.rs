@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::document::Document;
+use crate::ParseError;
+
+// A parsed Thrift file.
+#[derive(Debug, Clone)]
+pub struct Program {
+    // Canonical path of the file.
+    pub path: PathBuf,
+    // The name other files use to refer to this one, which is the file stem.
+    pub alias: String,
+    pub source: String,
+    pub document: Document,
+    // Included files of this one, keyed by their alias, e.g. `shared` for `include "shared.thrift"`.
+    pub includes: BTreeMap<String, PathBuf>,
+}
+
+// A root Thrift file and every file it includes, directly or not.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    // Included files always come before the files including them, so the root is the last one.
+    programs: Vec<Program>,
+    index: HashMap<PathBuf, usize>,
+}
+
+impl Workspace {
+    // Load `root` and all the files it includes. An include path is looked up relative to the
+    // including file first, then in `include_dirs` in order.
+    pub fn load(root: impl AsRef<Path>, include_dirs: &[PathBuf]) -> Result<Self, LoadError> {
+        let root = root.as_ref();
+        let root = root.canonicalize().map_err(|error| LoadError::Io {
+            path: root.to_path_buf(),
+            error,
+        })?;
+        let alias = alias_of(&root);
+        let mut loader = Loader {
+            include_dirs,
+            workspace: Workspace {
+                programs: Vec::new(),
+                index: HashMap::new(),
+            },
+            stack: Vec::new(),
+        };
+        loader.load(root, alias)?;
+        Ok(loader.workspace)
+    }
+
+    // Build a workspace from a file that is already read, as if it were at `path`. Files it
+    // includes are looked up like in `load`.
+    pub fn from_source(
+        path: impl AsRef<Path>,
+        source: impl Into<String>,
+        include_dirs: &[PathBuf],
+    ) -> Result<Self, LoadError> {
+        let path = path.as_ref().to_path_buf();
+        let alias = alias_of(&path);
+        let mut loader = Loader {
+            include_dirs,
+            workspace: Workspace {
+                programs: Vec::new(),
+                index: HashMap::new(),
+            },
+            stack: Vec::new(),
+        };
+        loader.load_source(path, alias, source.into())?;
+        Ok(loader.workspace)
+    }
+
+    pub fn root(&self) -> &Program {
+        self.programs
+            .last()
+            .expect("a workspace always has its root")
+    }
+
+    // All the files, included files come before the files including them.
+    pub fn programs(&self) -> impl Iterator<Item = &Program> {
+        self.programs.iter()
+    }
+
+    // Find a file by its path, which does not need to be canonical.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&Program> {
+        let path = path.as_ref();
+        self.index
+            .get(path)
+            .or_else(|| self.index.get(&path.canonicalize().ok()?))
+            .map(|&i| &self.programs[i])
+    }
+
+    // Find the file `program` refers to as `alias`.
+    pub fn included(&self, program: &Program, alias: &str) -> Option<&Program> {
+        program.includes.get(alias).and_then(|path| self.get(path))
+    }
+}
+
+struct Loader<'a> {
+    include_dirs: &'a [PathBuf],
+    workspace: Workspace,
+    // Files being loaded, each one includes the next.
+    stack: Vec<PathBuf>,
+}
+
+impl<'a> Loader<'a> {
+    fn load(&mut self, path: PathBuf, alias: String) -> Result<(), LoadError> {
+        if self.workspace.index.contains_key(&path) {
+            return Ok(());
+        }
+        if let Some(pos) = self.stack.iter().position(|p| *p == path) {
+            let mut cycle = self.stack[pos..].to_vec();
+            cycle.push(path);
+            return Err(LoadError::Cycle(cycle));
+        }
+
+        let source = std::fs::read_to_string(&path).map_err(|error| LoadError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        self.load_source(path, alias, source)
+    }
+
+    fn load_source(
+        &mut self,
+        path: PathBuf,
+        alias: String,
+        source: String,
+    ) -> Result<(), LoadError> {
+        let document =
+            Document::parse_complete(&source).map_err(|e| LoadError::Parse(e.with_path(&path)))?;
+
+        self.stack.push(path.clone());
+        let mut includes = BTreeMap::new();
+        for include in &document.includes {
+            let target = self.find(&path, include.as_str())?;
+            let include_alias = alias_of(Path::new(include.as_str()));
+            match includes.get(&include_alias) {
+                Some(other) if *other != target => {
+                    return Err(LoadError::DuplicateAlias {
+                        alias: include_alias,
+                        path,
+                    })
+                }
+                _ => {}
+            }
+            self.load(target.clone(), alias_of(&target))?;
+            includes.insert(include_alias, target);
+        }
+        self.stack.pop();
+
+        let programs = &mut self.workspace.programs;
+        self.workspace.index.insert(path.clone(), programs.len());
+        programs.push(Program {
+            path,
+            alias,
+            source,
+            document,
+            includes,
+        });
+        Ok(())
+    }
+
+    // Find the canonical path of what `from` includes as `include`.
+    fn find(&self, from: &Path, include: &str) -> Result<PathBuf, LoadError> {
+        from.parent()
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(include))
+            .find(|candidate| candidate.is_file())
+            .and_then(|found| found.canonicalize().ok())
+            .ok_or_else(|| LoadError::NotFound {
+                include: include.into(),
+                from: from.to_path_buf(),
+            })
+    }
+}
+
+fn alias_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io { path: PathBuf, error: io::Error },
+    Parse(ParseError),
+    // An included file is found in neither the directory of `from` nor the include dirs.
+    NotFound { include: String, from: PathBuf },
+    // Files including each other, the first one is repeated at the end.
+    Cycle(Vec<PathBuf>),
+    // Two different files are included by `path` under the same alias.
+    DuplicateAlias { alias: String, path: PathBuf },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, error } => {
+                write!(f, "error: cannot read {}: {}", path.display(), error)
+            }
+            LoadError::Parse(e) => write!(f, "{}", e),
+            LoadError::NotFound { include, from } => write!(
+                f,
+                "error: cannot find \"{}\" included by {}",
+                include,
+                from.display()
+            ),
+            LoadError::Cycle(cycle) => {
+                let cycle: Vec<_> = cycle.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "error: include cycle: {}", cycle.join(" -> "))
+            }
+            LoadError::DuplicateAlias { alias, path } => write!(
+                f,
+                "error: different files are included as `{}` by {}",
+                alias,
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { error, .. } => Some(error),
+            LoadError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "thrift", "workspace", name]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_load() {
+        let workspace = Workspace::load(fixture("main.thrift"), &[fixture("lib")]).unwrap();
+        let aliases: Vec<_> = workspace.programs().map(|p| p.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["base", "shared", "main"]);
+
+        let root = workspace.root();
+        assert_eq!(root.path, fixture("main.thrift").canonicalize().unwrap());
+        assert_eq!(root.document.structs().count(), 1);
+        let shared = workspace.included(root, "shared").unwrap();
+        assert_eq!(
+            shared.document.structs().next().unwrap().name.as_str(),
+            "Shared"
+        );
+        let base = workspace.included(shared, "base").unwrap();
+        assert_eq!(base.path, workspace.included(root, "base").unwrap().path);
+        assert!(workspace.included(root, "main").is_none());
+        assert_eq!(
+            workspace
+                .get(fixture("lib/../lib/base.thrift"))
+                .unwrap()
+                .alias,
+            "base"
+        );
+
+        // base.thrift can only be found in the include dirs.
+        let err = Workspace::load(fixture("main.thrift"), &[]).unwrap_err();
+        assert!(matches!(err, LoadError::NotFound { ref include, .. } if include == "base.thrift"));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let err = Workspace::load(fixture("cycle_a.thrift"), &[]).unwrap_err();
+        match &err {
+            LoadError::Cycle(cycle) => {
+                let names: Vec<_> = cycle.iter().map(|p| alias_of(p)).collect();
+                assert_eq!(names, vec!["cycle_a", "cycle_b", "cycle_a"]);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+
+        let err = Workspace::load(fixture("missing.thrift"), &[]).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("error: cannot find \"nowhere.thrift\" included by"));
+
+        let err = Workspace::load(fixture("no_such_file.thrift"), &[]).unwrap_err();
+        assert!(matches!(err, LoadError::Io { .. }));
+
+        let err = Workspace::from_source("inline.thrift", "struct User {", &[]).unwrap_err();
+        assert!(err.to_string().contains(" --> inline.thrift:1:14\n"));
+    }
+
+    #[test]
+    fn test_from_source() {
+        let source = "include \"shared.thrift\"\nstruct User {}";
+        let workspace =
+            Workspace::from_source(fixture("inline.thrift"), source, &[fixture("lib")]).unwrap();
+        assert_eq!(workspace.root().alias, "inline");
+        assert_eq!(workspace.root().source, source);
+        assert!(workspace.included(workspace.root(), "shared").is_some());
+    }
+}
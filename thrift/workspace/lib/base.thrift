@@ -0,0 +1 @@
+typedef i64 Id
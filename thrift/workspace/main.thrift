@@ -0,0 +1,9 @@
+include "shared.thrift"
+include "base.thrift"
+
+namespace rs workspace.main
+
+struct Request {
+    1: shared.Shared shared
+    2: base.Id id
+}
@@ -0,0 +1,10 @@
+include "base.thrift"
+
+struct Shared {
+    1: base.Id id
+    2: string name
+}
+
+service SharedService {
+    void ping()
+}